@@ -1,6 +1,9 @@
 use anyhow::Result;
+use aperf::control::{mark, Mark, APERF_TMP_PREFIX};
+use aperf::daemon::{daemon, Daemon};
 use aperf::pmu::{custom_pmu, CustomPMU};
 use aperf::record::{record, Record};
+use aperf::recover::{recover, Recover};
 use aperf::report::{report, Report};
 use aperf::{PDError, APERF_RUNLOG, APERF_TMP};
 use clap::{Parser, Subcommand};
@@ -38,9 +41,18 @@ enum Commands {
     /// Collect performance data.
     Record(Record),
 
+    /// Collect performance data continuously, starting a new archive every rotation window.
+    Daemon(Daemon),
+
     /// Generate an HTML report based on the data collected.
     Report(Report),
 
+    /// Add a marker to a running recording, e.g. aperf mark "warmup done".
+    Mark(Mark),
+
+    /// Recover the data of a recording that did not finish, e.g. after a crash, into an archive.
+    Recover(Recover),
+
     /// Create a custom PMU configuration file for use with Aperf record.
     CustomPMU(CustomPMU),
 }
@@ -91,7 +103,7 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     let tmp_dir = TempBuilder::new()
-        .prefix("aperf-tmp-")
+        .prefix(APERF_TMP_PREFIX)
         .tempdir_in(&cli.tmp_dir)?;
     fs::set_permissions(&tmp_dir, fs::Permissions::from_mode(0o1777))?;
     let tmp_dir_path_buf = tmp_dir.path().to_path_buf();
@@ -101,7 +113,10 @@ fn main() -> Result<()> {
 
     match cli.command {
         Commands::Record(r) => record(&r, &tmp_dir_path_buf, &runlog),
+        Commands::Daemon(d) => daemon(&d, &tmp_dir_path_buf, &runlog),
         Commands::Report(r) => report(&r, &tmp_dir_path_buf),
+        Commands::Mark(m) => mark(&m, &tmp_dir_path_buf),
+        Commands::Recover(r) => recover(&r),
         Commands::CustomPMU(r) => custom_pmu(&r),
     }?;
     fs::remove_dir_all(tmp_dir_path_buf)?;
@@ -0,0 +1,500 @@
+use crate::daemon::{parse_size, Daemon};
+use crate::overhead::OverheadBudget;
+use crate::record::{parse_duration, Record};
+use crate::PDError;
+use anyhow::Result;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Name of the file in the run directory holding the resolved record settings.
+pub static RECORD_CONFIG_FILE_NAME: &str = "record_config.yaml";
+
+/// Settings of `aperf record` and `aperf daemon` that can be kept in a YAML or TOML file and
+/// given with --config. Flags given on the command line take precedence over the file.
+///
+/// ```yaml
+/// run_name: db_baseline
+/// output_dir: /var/tmp/aperf
+/// interval: 500ms
+/// period: 60
+/// max_overhead: 2%
+/// collectors:
+///   exclude: [processes]
+/// cgroup:
+///   root: /sys/fs/cgroup/kubepods.slice
+///   depth: 3
+/// pmu_config: db_pmu.json
+/// profile:
+///   perf: true
+///   frequency: 199
+///   java: [kafka]
+/// threads: [java]
+/// slabinfo: true
+/// tags:
+///   role: database
+/// ```
+///
+/// The daemon takes the interval, max_overhead, collectors, cgroup, pmu_config and tags, and
+/// the settings of its own section. It reads the file again on SIGHUP.
+///
+/// ```yaml
+/// interval: 5s
+/// daemon:
+///   rotate: 30m
+///   keep: 48
+///   max_size: 2G
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordConfig {
+    /// Name of the run directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_name: Option<String>,
+    /// Directory the run directory is created in. Ignored if the run name is an absolute path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<PathBuf>,
+    #[serde(
+        deserialize_with = "deserialize_duration",
+        serialize_with = "serialize_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub interval: Option<Duration>,
+    /// In seconds, as with --period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<u64>,
+    /// As with --max-overhead, e.g. "2%" or "20ms".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_overhead: Option<OverheadBudget>,
+    pub collectors: CollectorsConfig,
+    pub cgroup: CgroupConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pmu_config: Option<String>,
+    pub profile: ProfileConfig,
+    /// Processes to sample the threads of, by PID or name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub threads: Vec<String>,
+    /// Sample the slab caches, as with --slabinfo.
+    pub slabinfo: bool,
+    pub tags: BTreeMap<String, String>,
+    pub daemon: DaemonConfig,
+    /// Only given on the command line, taken here to check them against the file settings.
+    #[serde(skip)]
+    pub flight_recorder: Option<Duration>,
+    #[serde(skip)]
+    pub workload: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectorsConfig {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CgroupConfig {
+    /// Root of the cgroup v2 hierarchy to sample.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<PathBuf>,
+    /// Levels of cgroups below the root to sample.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Profile with the 'perf' binary.
+    pub perf: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<u32>,
+    /// JVMs to profile by PID or name. An empty list profiles all JVMs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// How often to start a new archive, as with --rotate.
+    #[serde(
+        deserialize_with = "deserialize_duration",
+        serialize_with = "serialize_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rotate: Option<Duration>,
+    /// Number of archives to keep.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep: Option<usize>,
+    /// Total size of the archives to keep, in bytes or as with --max-size, e.g. "2G".
+    #[serde(
+        deserialize_with = "deserialize_size",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_size: Option<u64>,
+}
+
+/// Accept a duration as a string with a unit suffix, or a bare number of seconds.
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawDuration {
+        Number(f64),
+        Text(String),
+    }
+    let arg = match Option::<RawDuration>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(RawDuration::Number(n)) => n.to_string(),
+        Some(RawDuration::Text(s)) => s,
+    };
+    parse_duration(&arg).map(Some).map_err(D::Error::custom)
+}
+
+fn serialize_duration<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match duration {
+        Some(d) if d.subsec_millis() == 0 => serializer.serialize_str(&format!("{}s", d.as_secs())),
+        Some(d) => serializer.serialize_str(&format!("{}ms", d.as_millis())),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Accept a size as a string with a unit suffix, or a bare number of bytes.
+fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawSize {
+        Number(u64),
+        Text(String),
+    }
+    match Option::<RawSize>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(RawSize::Number(n)) => Ok(Some(n)),
+        Some(RawSize::Text(s)) => parse_size(&s).map(Some).map_err(D::Error::custom),
+    }
+}
+
+/// Parse a --tag given as KEY=VALUE.
+pub fn parse_tag(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("Invalid tag '{}', expected KEY=VALUE", arg)),
+    }
+}
+
+impl RecordConfig {
+    /// Read a config file. The format is taken from the extension: .yaml, .yml or .toml.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let name = path.display().to_string();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| PDError::ConfigFileInvalid(name.clone(), e.to_string()))?;
+        let config = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&contents)
+                .map_err(|e| PDError::ConfigFileInvalid(name.clone(), e.to_string()))?,
+            Some("toml") => toml::from_str(&contents)
+                .map_err(|e| PDError::ConfigFileInvalid(name.clone(), e.to_string()))?,
+            _ => return Err(PDError::ConfigFileUnsupported(name).into()),
+        };
+        Ok(config)
+    }
+
+    /// Override the settings with the flags given on the command line.
+    pub fn merge_record(&mut self, record: &Record) {
+        if record.run_name.is_some() {
+            self.run_name = record.run_name.clone();
+        }
+        if record.interval.is_some() {
+            self.interval = record.interval;
+        }
+        if record.period.is_some() {
+            self.period = record.period;
+        }
+        if record.max_overhead.is_some() {
+            self.max_overhead = record.max_overhead;
+        }
+        /* --include and --exclude replace the whole selection from the file */
+        if !record.include.is_empty() || !record.exclude.is_empty() {
+            self.collectors.include = record.include.clone();
+            self.collectors.exclude = record.exclude.clone();
+        }
+        if let Some(root) = &record.cgroup_root {
+            self.cgroup.root = Some(PathBuf::from(root));
+        }
+        if record.cgroup_depth.is_some() {
+            self.cgroup.depth = record.cgroup_depth;
+        }
+        if record.pmu_config.is_some() {
+            self.pmu_config = record.pmu_config.clone();
+        }
+        if record.profile {
+            self.profile.perf = true;
+        }
+        if record.perf_frequency.is_some() {
+            self.profile.frequency = record.perf_frequency;
+        }
+        if let Some(java) = &record.profile_java {
+            self.profile.java = Some(match java.as_str() {
+                "jps" => Vec::new(),
+                j => j.split(',').map(|s| s.trim().to_string()).collect(),
+            });
+        }
+        if !record.threads.is_empty() {
+            self.threads = record.threads.clone();
+        }
+        if record.slabinfo {
+            self.slabinfo = true;
+        }
+        for (key, value) in &record.tag {
+            self.tags.insert(key.clone(), value.clone());
+        }
+        self.flight_recorder = record.flight_recorder;
+        self.workload = record.workload.clone();
+    }
+
+    /// Override the settings with the flags given to the daemon.
+    pub fn merge_daemon(&mut self, daemon: &Daemon) {
+        if daemon.interval.is_some() {
+            self.interval = daemon.interval;
+        }
+        if daemon.rotate.is_some() {
+            self.daemon.rotate = daemon.rotate;
+        }
+        if daemon.keep.is_some() {
+            self.daemon.keep = daemon.keep;
+        }
+        if daemon.max_size.is_some() {
+            self.daemon.max_size = daemon.max_size;
+        }
+        if daemon.max_overhead.is_some() {
+            self.max_overhead = daemon.max_overhead;
+        }
+        if !daemon.include.is_empty() || !daemon.exclude.is_empty() {
+            self.collectors.include = daemon.include.clone();
+            self.collectors.exclude = daemon.exclude.clone();
+        }
+        if let Some(root) = &daemon.cgroup_root {
+            self.cgroup.root = Some(PathBuf::from(root));
+        }
+        if daemon.cgroup_depth.is_some() {
+            self.cgroup.depth = daemon.cgroup_depth;
+        }
+        if daemon.pmu_config.is_some() {
+            self.pmu_config = daemon.pmu_config.clone();
+        }
+    }
+
+    /// Check the settings that are not checked when the run is set up.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(PDError::RecordConfigInvalid(msg).into());
+        if !self.collectors.include.is_empty() && !self.collectors.exclude.is_empty() {
+            return invalid(
+                "collectors to include and to exclude cannot both be given".to_string(),
+            );
+        }
+        if self.cgroup.depth == Some(0) {
+            return invalid("the cgroup depth must be at least 1".to_string());
+        }
+        if self.profile.frequency == Some(0) {
+            return invalid("the perf profiling frequency cannot be 0".to_string());
+        }
+        if let Some(p) = &self.pmu_config {
+            if !Path::new(p).is_file() {
+                return invalid(format!("PMU config file '{}' does not exist", p));
+            }
+        }
+        if let Some(key) = self.tags.keys().find(|k| k.trim().is_empty()) {
+            return invalid(format!("tag '{}' has an empty name", key));
+        }
+        if self.daemon.keep == Some(0) {
+            return invalid("at least one archive must be kept".to_string());
+        }
+        if self.flight_recorder.is_some() {
+            if self.profile.perf || self.profile.java.is_some() {
+                return invalid("the flight recorder cannot be used with profiling".to_string());
+            }
+            if !self.workload.is_empty() {
+                return invalid("the flight recorder cannot be used with a workload".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_to_file(&self, dir: &Path) -> Result<()> {
+        fs::write(
+            dir.join(RECORD_CONFIG_FILE_NAME),
+            serde_yaml::to_string(self)?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecordConfig;
+    use crate::daemon::Daemon;
+    use crate::record::Record;
+    use clap::Parser;
+    use std::fs;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        record: Record,
+    }
+
+    #[derive(Parser)]
+    struct DaemonCli {
+        #[command(flatten)]
+        daemon: Daemon,
+    }
+
+    #[test]
+    fn test_config_file_formats() {
+        let dir = TempDir::with_prefix("aperf_config").unwrap();
+        let yaml = dir.path().join("db.yaml");
+        fs::write(
+            &yaml,
+            "interval: 500ms\nperiod: 30\ncollectors:\n  exclude: [processes]\n\
+             profile:\n  java: []\ntags:\n  role: database\n",
+        )
+        .unwrap();
+        let toml = dir.path().join("db.toml");
+        fs::write(
+            &toml,
+            "interval = \"500ms\"\nperiod = 30\n[collectors]\nexclude = [\"processes\"]\n\
+             [profile]\njava = []\n[tags]\nrole = \"database\"\n",
+        )
+        .unwrap();
+
+        let config = RecordConfig::from_file(&yaml).unwrap();
+        assert_eq!(config, RecordConfig::from_file(&toml).unwrap());
+        assert_eq!(config.interval, Some(Duration::from_millis(500)));
+        assert_eq!(config.period, Some(30));
+        assert_eq!(config.collectors.exclude, vec!["processes"]);
+        assert_eq!(config.profile.java, Some(Vec::new()));
+        assert_eq!(config.tags["role"], "database");
+
+        /* Round trip through the file saved in the run directory */
+        config.write_to_file(dir.path()).unwrap();
+        let saved = dir.path().join(super::RECORD_CONFIG_FILE_NAME);
+        assert_eq!(config, RecordConfig::from_file(&saved).unwrap());
+
+        fs::write(&yaml, "intervl: 1\n").unwrap();
+        let err = RecordConfig::from_file(&yaml).unwrap_err().to_string();
+        assert!(err.contains("unknown field `intervl`"));
+        fs::write(&yaml, "interval: 5x\n").unwrap();
+        assert!(RecordConfig::from_file(&yaml).is_err());
+        assert!(RecordConfig::from_file(&dir.path().join("db.json")).is_err());
+    }
+
+    #[test]
+    fn test_config_merge_record() {
+        let mut config = RecordConfig {
+            interval: Some(Duration::from_secs(2)),
+            period: Some(30),
+            ..Default::default()
+        };
+        config.collectors.include = vec!["vmstat".to_string()];
+        config
+            .tags
+            .insert("role".to_string(), "database".to_string());
+
+        let cli = Cli::parse_from([
+            "aperf",
+            "--period",
+            "5",
+            "--exclude",
+            "processes",
+            "--profile-java",
+            "--tag",
+            "role=cache",
+            "--cgroup-depth",
+            "3",
+            "--threads",
+            "java,1234",
+            "--slabinfo",
+        ]);
+        config.merge_record(&cli.record);
+        assert_eq!(config.interval, Some(Duration::from_secs(2)));
+        assert_eq!(config.period, Some(5));
+        assert!(config.collectors.include.is_empty());
+        assert_eq!(config.collectors.exclude, vec!["processes"]);
+        assert_eq!(config.profile.java, Some(Vec::new()));
+        assert_eq!(config.tags["role"], "cache");
+        assert_eq!(config.cgroup.depth, Some(3));
+        assert_eq!(config.threads, vec!["java", "1234"]);
+        assert!(config.slabinfo);
+        config.validate().unwrap();
+
+        config.cgroup.depth = Some(0);
+        assert!(config.validate().is_err());
+        config.cgroup.depth = None;
+
+        config.collectors.include = vec!["vmstat".to_string()];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_flight_recorder() {
+        let dir = TempDir::with_prefix("aperf_config").unwrap();
+        let yaml = dir.path().join("perf.yaml");
+        fs::write(&yaml, "profile:\n  perf: true\n").unwrap();
+        let mut config = RecordConfig::from_file(&yaml).unwrap();
+
+        let cli = Cli::parse_from(["aperf", "--flight-recorder", "10m"]);
+        config.merge_record(&cli.record);
+        assert!(config.validate().is_err());
+
+        config.profile.perf = false;
+        config.validate().unwrap();
+        /* Neither is saved with the run */
+        config.write_to_file(dir.path()).unwrap();
+        let saved = RecordConfig::from_file(&dir.path().join(super::RECORD_CONFIG_FILE_NAME));
+        assert_eq!(saved.unwrap().flight_recorder, None);
+    }
+
+    #[test]
+    fn test_config_merge_daemon() {
+        let dir = TempDir::with_prefix("aperf_config").unwrap();
+        let toml = dir.path().join("daemon.toml");
+        fs::write(
+            &toml,
+            "interval = 5\n[daemon]\nrotate = \"30m\"\nkeep = 48\nmax_size = \"2G\"\n",
+        )
+        .unwrap();
+        let mut config = RecordConfig::from_file(&toml).unwrap();
+        assert_eq!(config.daemon.rotate, Some(Duration::from_secs(1800)));
+        assert_eq!(config.daemon.max_size, Some(2 << 30));
+
+        let cli = DaemonCli::parse_from(["aperf", "--keep", "12", "--include", "vmstat"]);
+        config.merge_daemon(&cli.daemon);
+        assert_eq!(config.interval, Some(Duration::from_secs(5)));
+        assert_eq!(config.daemon.keep, Some(12));
+        assert_eq!(config.collectors.include, vec!["vmstat"]);
+        config.validate().unwrap();
+
+        config.daemon.keep = Some(0);
+        assert!(config.validate().is_err());
+
+        fs::write(&toml, "[daemon]\nmax_size = \"2X\"\n").unwrap();
+        assert!(RecordConfig::from_file(&toml).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::APERF_TMP;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use log::{debug, info, warn};
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::io::{AsFd, BorrowedFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Name of the control socket of a recording, in its tmp dir.
+pub static CONTROL_SOCKET_NAME: &str = "aperf.sock";
+
+/// Prefix of the tmp dir created by each aperf process.
+pub static APERF_TMP_PREFIX: &str = "aperf-tmp-";
+
+/// Longest request accepted on the control socket.
+const MAX_REQUEST_LEN: u64 = 4096;
+
+/// How long a client gets to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Args, Debug)]
+pub struct Mark {
+    /// Label of the marker, e.g. "warmup done".
+    #[clap(value_parser)]
+    pub label: String,
+
+    /// Control socket of the recording to mark. Only needed if more than one recording is running.
+    #[clap(long, value_parser)]
+    pub socket: Option<String>,
+}
+
+/// A request sent to a running recording. Sent as a single line of text.
+#[derive(Debug, PartialEq)]
+pub enum Request {
+    Mark(String),
+}
+
+impl Request {
+    fn parse(line: &str) -> Result<Self> {
+        match line.trim_end().split_once(' ') {
+            Some(("mark", label)) if !label.trim().is_empty() => {
+                Ok(Request::Mark(label.trim().to_string()))
+            }
+            _ => Err(anyhow!("Unknown request '{}'", line.trim_end())),
+        }
+    }
+
+    fn to_line(&self) -> String {
+        match self {
+            Request::Mark(label) => format!("mark {}\n", label.replace(['\r', '\n'], " ")),
+        }
+    }
+}
+
+/// Unix domain socket on which a recording accepts requests from `aperf mark`.
+pub struct ControlSocket {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    pub fn bind(tmp_dir: &Path) -> Result<Self> {
+        let path = tmp_dir.join(CONTROL_SOCKET_NAME);
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        debug!("Control socket at {}", path.display());
+        Ok(ControlSocket { listener, path })
+    }
+
+    /// Serve the clients waiting to connect, replying to each with the result of `handle`.
+    pub fn serve<F>(&self, mut handle: F) -> Result<()>
+    where
+        F: FnMut(Request) -> Result<()>,
+    {
+        loop {
+            let stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            /* A misbehaving client must not stop the collection */
+            if let Err(e) = Self::serve_client(stream, &mut handle) {
+                warn!("Control socket request failed: {}", e);
+            }
+        }
+    }
+
+    fn serve_client<F>(mut stream: UnixStream, handle: &mut F) -> Result<()>
+    where
+        F: FnMut(Request) -> Result<()>,
+    {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut line = String::new();
+        /* `aperf mark` connects without a request to find running recordings */
+        if BufReader::new((&stream).take(MAX_REQUEST_LEN)).read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let reply = match Request::parse(&line).and_then(&mut *handle) {
+            Ok(()) => "ok\n".to_string(),
+            Err(e) => format!("error {}\n", e),
+        };
+        stream.write_all(reply.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl AsFd for ControlSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.listener.as_fd()
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Send a request to a recording and wait for its reply.
+pub fn send_request(socket: &Path, request: &Request) -> Result<()> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.write_all(request.to_line().as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    match reply.trim_end() {
+        "ok" => Ok(()),
+        r => Err(anyhow!(
+            "{}",
+            r.strip_prefix("error ")
+                .unwrap_or("No reply from the recording")
+        )),
+    }
+}
+
+/// Find the control sockets of the recordings running with their tmp dir in `tmp_root`.
+fn find_sockets(tmp_root: &Path) -> Result<Vec<PathBuf>> {
+    let mut sockets = Vec::new();
+    for entry in fs::read_dir(tmp_root)? {
+        let entry = entry?;
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(APERF_TMP_PREFIX)
+        {
+            continue;
+        }
+        let socket = entry.path().join(CONTROL_SOCKET_NAME);
+        if socket.exists() && UnixStream::connect(&socket).is_ok() {
+            sockets.push(socket);
+        }
+    }
+    Ok(sockets)
+}
+
+pub fn mark(mark: &Mark, tmp_dir: &Path) -> Result<()> {
+    let socket = match &mark.socket {
+        Some(s) => PathBuf::from(s),
+        None => {
+            let tmp_root = tmp_dir.parent().unwrap_or(Path::new(APERF_TMP));
+            let mut sockets = find_sockets(tmp_root)?;
+            match sockets.len() {
+                0 => {
+                    return Err(anyhow!(
+                        "No running recording found in {}",
+                        tmp_root.display()
+                    ))
+                }
+                1 => sockets.remove(0),
+                _ => {
+                    let list: Vec<String> =
+                        sockets.iter().map(|s| s.display().to_string()).collect();
+                    return Err(anyhow!(
+                        "More than one recording is running, choose one with --socket: {}",
+                        list.join(", ")
+                    ));
+                }
+            }
+        }
+    };
+    send_request(&socket, &Request::Mark(mark.label.clone()))?;
+    info!("Marked '{}'", mark.label);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{send_request, ControlSocket, Request};
+    use anyhow::anyhow;
+    use std::thread;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_control_socket() {
+        assert_eq!(
+            Request::parse("mark load step 3\n").unwrap(),
+            Request::Mark("load step 3".to_string())
+        );
+        assert!(Request::parse("mark \n").is_err());
+        assert!(Request::parse("unmark x\n").is_err());
+
+        let dir = TempDir::with_prefix("aperf_control").unwrap();
+        let socket = ControlSocket::bind(dir.path()).unwrap();
+        let path = dir.path().join(super::CONTROL_SOCKET_NAME);
+        let client = thread::spawn(move || {
+            let ok = send_request(&path, &Request::Mark("warmup\ndone".to_string()));
+            let err = send_request(&path, &Request::Mark("fail".to_string()));
+            (ok, err)
+        });
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            socket
+                .serve(|request| {
+                    let Request::Mark(label) = request;
+                    received.push(label.clone());
+                    match label.as_str() {
+                        "fail" => Err(anyhow!("not now")),
+                        _ => Ok(()),
+                    }
+                })
+                .unwrap();
+            thread::yield_now();
+        }
+        let (ok, err) = client.join().unwrap();
+        assert!(ok.is_ok());
+        assert_eq!(err.unwrap_err().to_string(), "not now");
+        assert_eq!(received, vec!["warmup done", "fail"]);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::config::{ProfileConfig, RecordConfig};
+use crate::data::cgroup::{DEFAULT_CGROUP_DEPTH, DEFAULT_CGROUP_ROOT};
+use crate::overhead::{parse_overhead, OverheadBudget};
+use crate::record::{
+    collect_static_data, parse_duration, prepare_data_collectors, start_collection_serial,
+    DEFAULT_INTERVAL, UNBOUNDED_PERIOD,
+};
+use crate::{InitParams, PDError, PERFORMANCE_DATA};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use clap::Args;
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Args, Clone, Debug)]
+pub struct Daemon {
+    /// Name of the run. Each archive is named after it, with the time its window started appended.
+    #[clap(short, long, value_parser, default_value = DEFAULT_RUN_NAME)]
+    pub run_name: String,
+
+    /// Interval at which performance data is to be collected. Accepts a unit suffix
+    /// of 'ms' or 's' (e.g. 250ms). A bare number is taken as seconds. Defaults to 1.
+    #[clap(short, long, value_parser = parse_duration)]
+    pub interval: Option<Duration>,
+
+    /// How often to start a new archive (e.g. 30m, 1h). Defaults to 1h.
+    #[clap(long, value_parser = parse_duration)]
+    pub rotate: Option<Duration>,
+
+    /// Number of archives to keep. The oldest archives are removed first.
+    #[clap(long, value_parser)]
+    pub keep: Option<usize>,
+
+    /// Total size of the archives to keep (e.g. 500M, 2G). The oldest archives are removed first.
+    #[clap(long, value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// Most time to spend collecting, as a percentage of one CPU (e.g. 2%) or as a time per
+    /// interval (e.g. 20ms). Collectors that keep going over their share are collected less often.
+    #[clap(long, value_parser = parse_overhead)]
+    pub max_overhead: Option<OverheadBudget>,
+
+    /// Root of the cgroup v2 hierarchy to sample. Defaults to /sys/fs/cgroup.
+    #[clap(long, value_parser)]
+    pub cgroup_root: Option<String>,
+
+    /// Levels of cgroups below the root to sample. Defaults to 2.
+    #[clap(long, value_parser)]
+    pub cgroup_depth: Option<usize>,
+
+    /// Custom PMU config file to use.
+    #[clap(long, value_parser)]
+    pub pmu_config: Option<String>,
+
+    /// Collectors to run, as a comma separated list. Runs all collectors if not given.
+    #[clap(long, value_parser, value_delimiter = ',', conflicts_with = "exclude")]
+    pub include: Vec<String>,
+
+    /// Collectors to skip, as a comma separated list.
+    #[clap(long, value_parser, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// YAML or TOML file with the settings for the daemon. Flags given here override it. The
+    /// file is read again on SIGHUP.
+    #[clap(long, value_parser)]
+    pub config: Option<String>,
+}
+
+pub const DEFAULT_RUN_NAME: &str = "aperf_daemon";
+
+pub const DEFAULT_ROTATE: Duration = Duration::from_secs(3600);
+
+/// Read the config file of the daemon, override it with the flags and check the result. This
+/// is done again on SIGHUP, so an error here must not stop a running daemon.
+pub fn daemon_config(daemon: &Daemon) -> Result<RecordConfig> {
+    let mut config = match &daemon.config {
+        Some(c) => RecordConfig::from_file(Path::new(c))?,
+        None => RecordConfig::default(),
+    };
+    config.merge_daemon(daemon);
+    config.validate()?;
+
+    let invalid = |msg: String| Err(PDError::RecordConfigInvalid(msg).into());
+    let unused = [
+        ("run_name", config.run_name.is_some()),
+        ("output_dir", config.output_dir.is_some()),
+        ("period", config.period.is_some()),
+        ("profile", config.profile != ProfileConfig::default()),
+        ("threads", !config.threads.is_empty()),
+        ("slabinfo", config.slabinfo),
+    ];
+    if let Some((name, _)) = unused.iter().find(|(_, set)| *set) {
+        return invalid(format!("{} is not used by the daemon", name));
+    }
+    let interval = config.interval.unwrap_or(DEFAULT_INTERVAL);
+    let rotate = config.daemon.rotate.unwrap_or(DEFAULT_ROTATE);
+    if interval.is_zero() {
+        return invalid("the collection interval cannot be 0".to_string());
+    }
+    if interval >= rotate {
+        return invalid(format!(
+            "the rotation window of {:?} needs to be longer than the interval of {:?}",
+            rotate, interval
+        ));
+    }
+    Ok(config)
+}
+
+/// Set the settings of the daemon on the parameters of the run. They are used from the next
+/// window on.
+pub fn apply_config(params: &mut InitParams, config: &RecordConfig) {
+    params.interval = config.interval.unwrap_or(DEFAULT_INTERVAL);
+    params.rotate = Some(config.daemon.rotate.unwrap_or(DEFAULT_ROTATE));
+    params.keep = config.daemon.keep;
+    params.max_size = config.daemon.max_size;
+    params.max_overhead = config.max_overhead;
+    params.cgroup_root = config
+        .cgroup
+        .root
+        .clone()
+        .unwrap_or(PathBuf::from(DEFAULT_CGROUP_ROOT));
+    params.cgroup_depth = config.cgroup.depth.unwrap_or(DEFAULT_CGROUP_DEPTH);
+    params.pmu_config = config.pmu_config.as_ref().map(PathBuf::from);
+    params.tags = config.tags.clone().into_iter().collect();
+}
+
+/// Parse a size such as "500M" or "2G". Suffixes are powers of 1024. A bare number is bytes.
+pub fn parse_size(arg: &str) -> Result<u64, String> {
+    let arg = arg.trim();
+    let split = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let (value, unit) = arg.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("Invalid size '{}'", arg))?;
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" => 10,
+        "M" | "MB" => 20,
+        "G" | "GB" => 30,
+        "T" | "TB" => 40,
+        u => return Err(format!("Invalid unit '{}' in size '{}'", u, arg)),
+    };
+    value
+        .checked_mul(1 << shift)
+        .ok_or(format!("Size '{}' is too large", arg))
+}
+
+/// Remove the oldest archives of a daemon run until at most `keep` remain and they take up
+/// no more than `max_size` bytes. The newest archive is always kept.
+pub fn prune_archives(prefix: &str, keep: Option<usize>, max_size: Option<u64>) -> Result<()> {
+    if keep.is_none() && max_size.is_none() {
+        return Ok(());
+    }
+    let prefix = Path::new(prefix);
+    let dir = match prefix.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let stem = format!("{}_", prefix.file_name().unwrap().to_str().unwrap());
+
+    /* Only match archives of this run, not those of a run whose name starts the same */
+    let mut archives = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().unwrap_or_default();
+        let time_str = match name
+            .strip_prefix(&stem)
+            .and_then(|n| n.strip_suffix(".tar.gz"))
+        {
+            Some(t) => t,
+            None => continue,
+        };
+        if NaiveDateTime::parse_from_str(time_str, "%Y-%m-%d_%H_%M_%S").is_ok()
+            && entry.file_type()?.is_file()
+        {
+            archives.push((name, entry.metadata()?.len()));
+        }
+    }
+    archives.sort();
+
+    let mut count = archives.len();
+    let mut total: u64 = archives.iter().map(|(_, size)| size).sum();
+    for (name, size) in archives {
+        let over_count = keep.is_some_and(|k| count > k);
+        let over_size = max_size.is_some_and(|m| total > m);
+        if count <= 1 || (!over_count && !over_size) {
+            break;
+        }
+        info!("Removing old archive {}", name);
+        fs::remove_file(dir.join(&name))?;
+        count -= 1;
+        total -= size;
+    }
+    Ok(())
+}
+
+pub fn daemon(daemon: &Daemon, tmp_dir: &Path, runlog: &Path) -> Result<()> {
+    let config = daemon_config(daemon)?;
+
+    /*
+     * Each window is collected into its own run directory named after the run and the time the
+     * window started. The directory is removed once it has been archived.
+     */
+    let mut params = InitParams::new(daemon.run_name.clone());
+    params.dump_prefix = params.dir_name.clone();
+    params.dir_name = format!("{}_{}", params.dump_prefix, params.time_str);
+    params.period = UNBOUNDED_PERIOD;
+    params.tmp_dir = tmp_dir.to_path_buf();
+    params.runlog = runlog.to_path_buf();
+    apply_config(&mut params, &config);
+
+    PERFORMANCE_DATA.lock().unwrap().set_params(params);
+    PERFORMANCE_DATA.lock().unwrap().daemon = Some(daemon.clone());
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .select_collectors(&config.collectors.include, &config.collectors.exclude)?;
+    PERFORMANCE_DATA.lock().unwrap().init_collectors()?;
+    info!("Starting Data collection...");
+    prepare_data_collectors()?;
+    collect_static_data()?;
+    start_collection_serial()?;
+    info!("Data collection complete.");
+    PERFORMANCE_DATA.lock().unwrap().end()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_size, prune_archives};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("4K").unwrap(), 4096);
+        assert_eq!(parse_size("500M").unwrap(), 500 << 20);
+        assert_eq!(parse_size("2gb").unwrap(), 2 << 30);
+        assert!(parse_size("1.5G").is_err());
+        assert!(parse_size("G").is_err());
+        assert!(parse_size("5X").is_err());
+    }
+
+    #[test]
+    fn test_prune_archives() {
+        let dir = TempDir::with_prefix("aperf_prune").unwrap();
+        let prefix = dir.path().join("daemon").to_str().unwrap().to_string();
+        let names = [
+            "daemon_2024-01-01_00_00_00.tar.gz",
+            "daemon_2024-01-01_01_00_00.tar.gz",
+            "daemon_2024-01-01_02_00_00.tar.gz",
+            "daemon_2024-01-01_03_00_00.tar.gz",
+            "daemon_other_2024-01-01_00_00_00.tar.gz",
+        ];
+        for name in names {
+            fs::write(dir.path().join(name), [0u8; 100]).unwrap();
+        }
+
+        prune_archives(&prefix, Some(3), None).unwrap();
+        assert!(!dir.path().join(names[0]).exists());
+        assert!(dir.path().join(names[1]).exists());
+        assert!(dir.path().join(names[4]).exists());
+
+        prune_archives(&prefix, None, Some(150)).unwrap();
+        assert!(!dir.path().join(names[2]).exists());
+        assert!(dir.path().join(names[3]).exists());
+
+        prune_archives(&prefix, None, Some(10)).unwrap();
+        assert!(dir.path().join(names[3]).exists());
+        assert!(dir.path().join(names[4]).exists());
+    }
+}
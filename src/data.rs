@@ -1,21 +1,40 @@
 pub mod aperf_runlog;
 pub mod aperf_stats;
+pub mod cgroup;
 pub mod constants;
 pub mod cpu_utilization;
+pub mod cpufreq;
+pub mod cpufreq_settings;
 pub mod diskstats;
+pub mod filesystem_usage;
 pub mod flamegraphs;
+pub mod fragmentation;
+pub mod hardware_topology;
 pub mod interrupts;
 pub mod java_profile;
 pub mod kernel_config;
+pub mod kernel_runtime;
+pub mod markers;
 pub mod meminfodata;
+pub mod mounts;
+pub mod net_dev;
 pub mod netstat;
+pub mod numa;
 pub mod perf_profile;
 pub mod perf_stat;
+pub mod pressure;
 pub mod processes;
+pub mod records;
+pub mod scheduler;
+pub mod slabinfo;
+pub mod snmp;
+pub mod softirqs;
 pub mod sysctldata;
 pub mod systeminfo;
+pub mod threads;
 pub mod utils;
 pub mod vmstat;
+pub mod workload;
 
 use crate::utils::DataMetrics;
 use crate::visualizer::{GetData, ReportParams};
@@ -23,33 +42,54 @@ use crate::{noop, InitParams, APERF_FILE_FORMAT};
 use anyhow::Result;
 use aperf_runlog::AperfRunlog;
 use aperf_stats::AperfStat;
+use cgroup::{Cgroup, CgroupRaw};
 use chrono::prelude::*;
 use cpu_utilization::{CpuUtilization, CpuUtilizationRaw};
+use cpufreq::{Cpufreq, CpufreqRaw};
+use cpufreq_settings::CpufreqSettings;
 use diskstats::{Diskstats, DiskstatsRaw};
+use filesystem_usage::{FilesystemUsage, FilesystemUsageRaw};
 use flamegraphs::{Flamegraph, FlamegraphRaw};
+use fragmentation::{Fragmentation, FragmentationRaw};
+use hardware_topology::HardwareTopology;
 use interrupts::{InterruptData, InterruptDataRaw};
 use java_profile::{JavaProfile, JavaProfileRaw};
 use kernel_config::KernelConfig;
+use kernel_runtime::KernelRuntimeData;
 use log::trace;
+use markers::{Markers, MarkersRaw};
 use meminfodata::{MeminfoData, MeminfoDataRaw};
+use mounts::Mounts;
+use net_dev::{NetDev, NetDevRaw};
 use netstat::{Netstat, NetstatRaw};
 use nix::sys::{signal, signal::Signal};
+use numa::{Numa, NumaRaw};
 use perf_profile::{PerfProfile, PerfProfileRaw};
 use perf_stat::{PerfStat, PerfStatRaw};
+use pressure::{Pressure, PressureRaw};
 use processes::{Processes, ProcessesRaw};
+use scheduler::{Scheduler, SchedulerRaw};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use slabinfo::{Slabinfo, SlabinfoRaw};
+use snmp::{Snmp, SnmpRaw};
+use softirqs::{Softirqs, SoftirqsRaw};
+use std::collections::{HashMap, VecDeque};
 use std::fs::{File, OpenOptions};
+use std::io::Write;
 use std::ops::Sub;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use sysctldata::SysctlData;
 use systeminfo::SystemInfo;
+use threads::{Threads, ThreadsRaw};
 use vmstat::{Vmstat, VmstatRaw};
+use workload::Workload;
 
 #[derive(Clone, Debug)]
 pub struct CollectorParams {
     pub collection_time: u64,
     pub elapsed_time: u64,
+    pub interval: Duration,
     pub data_file_path: PathBuf,
     pub data_dir: PathBuf,
     pub run_name: String,
@@ -59,6 +99,8 @@ pub struct CollectorParams {
     pub runlog: PathBuf,
     pub pmu_config: Option<PathBuf>,
     pub perf_frequency: u32,
+    pub cgroup_root: PathBuf,
+    pub cgroup_depth: usize,
 }
 
 impl CollectorParams {
@@ -66,6 +108,7 @@ impl CollectorParams {
         CollectorParams {
             collection_time: 0,
             elapsed_time: 0,
+            interval: Duration::from_secs(1),
             data_file_path: PathBuf::new(),
             data_dir: PathBuf::new(),
             run_name: String::new(),
@@ -75,6 +118,8 @@ impl CollectorParams {
             runlog: PathBuf::new(),
             pmu_config: Option::None,
             perf_frequency: 99,
+            cgroup_root: PathBuf::from(cgroup::DEFAULT_CGROUP_ROOT),
+            cgroup_depth: cgroup::DEFAULT_CGROUP_DEPTH,
         }
     }
 }
@@ -88,6 +133,55 @@ pub struct DataType {
     pub is_static: bool,
     pub is_profile_option: bool,
     pub collector_params: CollectorParams,
+    pub ring: Option<RingBuffer>,
+}
+
+/// Serialized records of a collector, bounded to the most recent `window` of time.
+/// Used instead of the data file in flight recorder mode.
+#[derive(Clone, Debug)]
+pub struct RingBuffer {
+    window: Duration,
+    records: VecDeque<(DateTime<Utc>, Vec<u8>)>,
+}
+
+impl RingBuffer {
+    pub fn new(window: Duration) -> Self {
+        RingBuffer {
+            window,
+            records: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, record: Vec<u8>) {
+        let now = Utc::now();
+        self.records.push_back((now, record));
+        while let Some((time, _)) = self.records.front() {
+            match (now - *time).to_std() {
+                Ok(age) if age > self.window => {
+                    self.records.pop_front();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Write the records, oldest first, into a new file in the usual data file format.
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(records::RECORD_MAGIC)?;
+        for (_, record) in &self.records {
+            file.write_all(record)?;
+        }
+        Ok(())
+    }
 }
 
 impl DataType {
@@ -101,6 +195,7 @@ impl DataType {
             is_static,
             is_profile_option: false,
             collector_params: CollectorParams::new(),
+            ring: None,
         }
     }
 
@@ -129,13 +224,22 @@ impl DataType {
         self.collector_params.run_name = param.dir_name.clone();
         self.collector_params.collection_time = param.period;
         self.collector_params.elapsed_time = 0;
+        self.collector_params.interval = param.interval;
         self.collector_params.data_file_path = PathBuf::from(&self.full_path);
         self.collector_params.data_dir = PathBuf::from(param.dir_name.clone());
         self.collector_params.profile = param.profile.clone();
         self.collector_params.tmp_dir = param.tmp_dir.clone();
         self.collector_params.runlog = param.runlog.clone();
         self.collector_params.pmu_config = param.pmu_config.clone();
+        self.collector_params.cgroup_root = param.cgroup_root.clone();
+        self.collector_params.cgroup_depth = param.cgroup_depth;
 
+        if let Some(window) = param.flight_recorder {
+            if !self.is_static {
+                self.ring = Some(RingBuffer::new(window));
+                return Ok(());
+            }
+        }
         self.file_handle = Some(
             OpenOptions::new()
                 .read(true)
@@ -144,6 +248,10 @@ impl DataType {
                 .open(&self.full_path)
                 .expect("Could not create file for data"),
         );
+        let file_handle = self.file_handle.as_ref().unwrap();
+        if file_handle.metadata()?.len() == 0 {
+            (&*file_handle).write_all(records::RECORD_MAGIC)?;
+        }
 
         Ok(())
     }
@@ -161,9 +269,22 @@ impl DataType {
     }
 
     pub fn write_to_file(&mut self) -> Result<()> {
+        if let Some(ring) = self.ring.as_mut() {
+            trace!("Writing to ring buffer...");
+            ring.push(records::encode(&self.data)?);
+            return Ok(());
+        }
         trace!("Writing to file...");
         let file_handle = self.file_handle.as_ref().unwrap();
-        bincode::serialize_into(file_handle.try_clone()?, &self.data)?;
+        records::write_record(file_handle, &self.data)?;
+        Ok(())
+    }
+
+    /// Flush the data file to disk, so that a crash loses at most the records since.
+    pub fn sync_data_file(&self) -> Result<()> {
+        if let Some(file_handle) = &self.file_handle {
+            file_handle.sync_data()?;
+        }
         Ok(())
     }
 
@@ -180,6 +301,9 @@ impl DataType {
     }
 }
 
+/// A point in time, or the time elapsed since the first sample of a run.
+///
+/// TimeDiff values are in milliseconds so that sub-second collection intervals can be plotted.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, Ord, PartialEq, PartialOrd, Hash)]
 pub enum TimeEnum {
     DateTime(DateTime<Utc>),
@@ -198,9 +322,7 @@ impl Sub for TimeEnum {
             TimeEnum::DateTime(value) => value,
             _ => panic!("Cannot perform subtract op on TimeEnum::TimeDiff"),
         };
-        let time_diff = (self_time - other_time).num_milliseconds() as u64;
-        // Round up to the nearest second
-        TimeEnum::TimeDiff((time_diff + 500) / 1000)
+        TimeEnum::TimeDiff((self_time - other_time).num_milliseconds() as u64)
     }
 }
 
@@ -311,7 +433,24 @@ data!(
     NetstatRaw,
     PerfProfileRaw,
     FlamegraphRaw,
-    JavaProfileRaw
+    JavaProfileRaw,
+    MarkersRaw,
+    PressureRaw,
+    CgroupRaw,
+    NetDevRaw,
+    SnmpRaw,
+    SoftirqsRaw,
+    SchedulerRaw,
+    NumaRaw,
+    CpufreqRaw,
+    CpufreqSettings,
+    ThreadsRaw,
+    Mounts,
+    FilesystemUsageRaw,
+    SlabinfoRaw,
+    FragmentationRaw,
+    HardwareTopology,
+    KernelRuntimeData
 );
 
 processed_data!(
@@ -330,7 +469,25 @@ processed_data!(
     Flamegraph,
     AperfStat,
     AperfRunlog,
-    JavaProfile
+    JavaProfile,
+    Workload,
+    Markers,
+    Pressure,
+    Cgroup,
+    NetDev,
+    Snmp,
+    Softirqs,
+    Scheduler,
+    Numa,
+    Cpufreq,
+    CpufreqSettings,
+    Threads,
+    Mounts,
+    FilesystemUsage,
+    Slabinfo,
+    Fragmentation,
+    HardwareTopology,
+    KernelRuntimeData
 );
 
 pub trait CollectData {
@@ -352,10 +509,55 @@ pub trait CollectData {
     }
 }
 
+/// Helpers for the tests of the collectors.
+#[cfg(test)]
+pub mod test_utils {
+    use super::{Data, ProcessedData, TimeEnum};
+    use crate::utils::DataMetrics;
+    use crate::visualizer::GetData;
+    use anyhow::Result;
+    use chrono::prelude::*;
+
+    /// The time of a sample taken the given number of seconds into a run.
+    pub fn sample_time(seconds: i64) -> TimeEnum {
+        TimeEnum::DateTime(Utc.timestamp_opt(1_700_000_000 + seconds, 0).unwrap())
+    }
+
+    /// Process each raw sample with a new processor, as the report does.
+    pub fn process_samples<T: GetData>(new: fn() -> T, samples: Vec<Data>) -> Vec<ProcessedData> {
+        samples
+            .into_iter()
+            .map(|raw| new().process_raw_data(raw).unwrap())
+            .collect()
+    }
+
+    /// Run a query, e.g. "get=values&key=cpu", on the processed samples of a run.
+    pub fn get_data<T: GetData>(
+        new: fn() -> T,
+        buffer: &[ProcessedData],
+        query: &str,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        new().get_data(buffer.to_vec(), format!("run=test&{}", query), metrics)
+    }
+
+    pub fn get_keys<T: GetData>(new: fn() -> T, buffer: &[ProcessedData]) -> Vec<String> {
+        let json = get_data(
+            new,
+            buffer,
+            "get=keys",
+            &mut DataMetrics::new(String::new()),
+        )
+        .unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::cpu_utilization::CpuUtilizationRaw;
-    use super::{CollectorParams, Data, DataType, TimeEnum};
+    use super::records::{RecordReader, RECORD_MAGIC};
+    use super::{CollectorParams, Data, DataType, RingBuffer, TimeEnum};
     use crate::InitParams;
     use chrono::prelude::*;
     use std::fs;
@@ -374,6 +576,7 @@ mod tests {
             is_static: false,
             is_profile_option: false,
             collector_params: CollectorParams::new(),
+            ring: None,
         };
 
         param.dir_name = format!("./performance_data_init_test_{}", param.time_str);
@@ -402,6 +605,7 @@ mod tests {
             is_static: false,
             is_profile_option: false,
             collector_params: CollectorParams::new(),
+            ring: None,
         };
 
         param.dir_name = format!("./performance_data_print_test_{}", param.time_str);
@@ -415,24 +619,40 @@ mod tests {
         assert!(Path::new(&dt.full_path).exists());
         dt.write_to_file().unwrap();
 
-        loop {
-            match bincode::deserialize_from::<_, Data>(dt.file_handle.as_ref().unwrap()) {
-                Ok(v) => match v {
-                    Data::CpuUtilizationRaw(ref value) => assert!(value.data.is_empty()),
-                    _ => unreachable!(),
-                },
-                Err(e) => match *e {
-                    bincode::ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                        break
-                    }
-                    _ => unreachable!(),
-                },
-            };
+        let file = fs::File::open(&dt.full_path).unwrap();
+        let mut reader = RecordReader::new(&file).unwrap();
+        assert!(reader.is_framed());
+        let mut count = 0;
+        while let Some(v) = reader.read_record::<Data>().unwrap() {
+            match v {
+                Data::CpuUtilizationRaw(ref value) => assert!(value.data.is_empty()),
+                _ => unreachable!(),
+            }
+            count += 1;
         }
+        assert_eq!(count, 1);
         fs::remove_file(dt.full_path).unwrap();
         fs::remove_dir_all(dt.dir_name).unwrap();
     }
 
+    #[test]
+    fn test_ring_buffer() {
+        let mut ring = RingBuffer::new(std::time::Duration::from_millis(100));
+        ring.push(vec![1, 2]);
+        ring.push(vec![3]);
+        assert_eq!(ring.len(), 2);
+        std::thread::sleep(std::time::Duration::from_millis(150));
+        ring.push(vec![4, 5]);
+        assert_eq!(ring.len(), 1);
+
+        let path = std::env::temp_dir().join("aperf_ring_buffer_test.bin");
+        ring.write_to_file(&path).unwrap();
+        let mut expected = RECORD_MAGIC.to_vec();
+        expected.extend([4, 5]);
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn test_time_diff_second() {
         let time_zero = Utc::now();
@@ -444,7 +664,9 @@ mod tests {
 
         let time_diff = time_t1 - time_t0;
         match time_diff {
-            TimeEnum::TimeDiff(value) => assert!(value == 1, "Time diff was expected to be 1"),
+            TimeEnum::TimeDiff(value) => {
+                assert!(value == 1000, "Time diff was expected to be 1000")
+            }
             _ => unreachable!(),
         }
     }
@@ -460,7 +682,7 @@ mod tests {
 
         let time_diff = time_t1 - time_t0;
         match time_diff {
-            TimeEnum::TimeDiff(value) => assert!(value == 0, "Time diff was expected to be 0"),
+            TimeEnum::TimeDiff(value) => assert!(value == 1, "Time diff was expected to be 1"),
             _ => unreachable!(),
         }
     }
@@ -476,7 +698,7 @@ mod tests {
 
         let time_diff = time_t1 - time_t0;
         match time_diff {
-            TimeEnum::TimeDiff(value) => assert!(value == 1, "Time diff was expected to be 1"),
+            TimeEnum::TimeDiff(value) => assert!(value == 992, "Time diff was expected to be 992"),
             _ => unreachable!(),
         }
     }
@@ -492,7 +714,7 @@ mod tests {
 
         let time_diff = time_t1 - time_t0;
         match time_diff {
-            TimeEnum::TimeDiff(value) => assert!(value == 1, "Time diff was expected to be 1"),
+            TimeEnum::TimeDiff(value) => assert!(value == 500, "Time diff was expected to be 500"),
             _ => unreachable!(),
         }
     }
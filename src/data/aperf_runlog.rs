@@ -29,8 +29,7 @@ impl GetData for AperfRunlog {
 
         let file = fs::OpenOptions::new()
             .read(true)
-            .open(params.data_file_path)
-            .expect("Could not open Aperf Runlog file");
+            .open(params.data_file_path)?;
         let mut reader = BufReader::new(file);
         let mut line = String::new();
         while reader.read_line(&mut line)? > 0 {
@@ -1,5 +1,6 @@
 extern crate ctor;
 
+use crate::data::records::RecordReader;
 use crate::data::{ProcessedData, TimeEnum};
 use crate::utils::{add_metrics, DataMetrics, Metric};
 use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata, ReportParams};
@@ -89,21 +90,13 @@ impl GetData for AperfStat {
     fn custom_raw_data_parser(&mut self, params: ReportParams) -> Result<Vec<ProcessedData>> {
         let mut raw_data: Vec<ProcessedData> = Vec::new();
 
-        let file: Result<fs::File> = Ok(fs::OpenOptions::new()
+        let file = fs::OpenOptions::new()
             .read(true)
-            .open(params.data_file_path)
-            .expect("Could not open APerf Stats file"));
-        loop {
-            match bincode::deserialize_from::<_, AperfStat>(file.as_ref().unwrap()) {
-                Ok(v) => raw_data.push(ProcessedData::AperfStat(v)),
-                Err(e) => match *e {
-                    // EOF
-                    bincode::ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                        break
-                    }
-                    e => panic!("Error when Deserializing APerf Stats data: {}", e),
-                },
-            };
+            .open(params.data_file_path)?;
+        let mut reader = RecordReader::new(&file)?;
+        /* A damaged record ends the data, the visualizer reports it */
+        while let Ok(Some(v)) = reader.read_record::<AperfStat>() {
+            raw_data.push(ProcessedData::AperfStat(v));
         }
         Ok(raw_data)
     }
@@ -0,0 +1,513 @@
+extern crate ctor;
+
+use crate::data::processes::keep_top_entries;
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::DataMetrics;
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+pub static CGROUP_FILE_NAME: &str = "cgroup";
+
+pub static DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+pub const DEFAULT_CGROUP_DEPTH: usize = 2;
+
+/// Files sampled in each cgroup. Those of controllers not enabled for a cgroup are missing.
+static CGROUP_FILES: [&str; 5] = [
+    "cpu.stat",
+    "memory.current",
+    "memory.stat",
+    "io.stat",
+    "cpu.pressure",
+];
+
+/// Ways the cgroups are ranked in the report.
+static CGROUP_RANKINGS: [&str; 5] = ["cpu", "throttling", "memory", "io", "cpu_pressure"];
+
+const MIB: f64 = (1 << 20) as f64;
+
+/// The files sampled in one cgroup, by name.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CgroupFiles {
+    /// Path relative to the cgroup root.
+    pub path: String,
+    pub files: Vec<(String, String)>,
+}
+
+/// Gather the resource accounting files of the cgroups below the root, down to a depth.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CgroupRaw {
+    pub time: TimeEnum,
+    pub cgroups: Vec<CgroupFiles>,
+}
+
+impl CgroupRaw {
+    fn new() -> Self {
+        CgroupRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            cgroups: Vec::new(),
+        }
+    }
+}
+
+/// Sample the cgroups in `dir` and, while `depth` allows, the cgroups below them.
+fn sample_cgroups(dir: &Path, rel: &str, depth: usize, out: &mut Vec<CgroupFiles>) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        /* A cgroup below the root can be removed after its parent is listed */
+        Err(e) if e.kind() == ErrorKind::NotFound && !rel.is_empty() => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = format!("{}/{}", rel, entry.file_name().to_string_lossy());
+        let mut cgroup = CgroupFiles {
+            path: path.clone(),
+            files: Vec::new(),
+        };
+        for name in CGROUP_FILES {
+            /* A cgroup can go away while it is sampled */
+            if let Ok(v) = fs::read_to_string(entry.path().join(name)) {
+                cgroup.files.push((name.to_string(), v));
+            }
+        }
+        out.push(cgroup);
+        if depth > 1 {
+            sample_cgroups(&entry.path(), &path, depth - 1, out)?;
+        }
+    }
+    Ok(())
+}
+
+impl CollectData for CgroupRaw {
+    fn prepare_data_collector(&mut self, params: &CollectorParams) -> Result<()> {
+        /* Only the unified (v2) hierarchy has cgroup.controllers */
+        if !params.cgroup_root.join("cgroup.controllers").exists() {
+            warn!("No cgroup v2 hierarchy at {}", params.cgroup_root.display());
+            return Err(PDError::CollectorCgroupV2Unavailable(
+                params.cgroup_root.display().to_string(),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn collect_data(&mut self, params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.cgroups = Vec::new();
+        sample_cgroups(
+            &params.cgroup_root,
+            "",
+            params.cgroup_depth,
+            &mut self.cgroups,
+        )?;
+        trace!("{:#?}", self.cgroups);
+        Ok(())
+    }
+}
+
+/// The values of a cgroup used in the report. Those of missing files are 0.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CgroupValues {
+    pub usage_usec: u64,
+    pub nr_periods: u64,
+    pub nr_throttled: u64,
+    pub throttled_usec: u64,
+    pub memory_current: u64,
+    pub anon: u64,
+    pub file: u64,
+    /// Bytes read and written, summed over all devices.
+    pub io_bytes: u64,
+    /// Microseconds some tasks of the cgroup were stalled waiting for a CPU.
+    pub cpu_pressure_usec: u64,
+}
+
+impl CgroupValues {
+    fn set_file(&mut self, name: &str, contents: &str) -> Result<()> {
+        match name {
+            "memory.current" => self.memory_current = contents.trim().parse()?,
+            "io.stat" => {
+                /* e.g. "259:0 rbytes=4096 wbytes=0 rios=1 wios=0 dbytes=0 dios=0" */
+                for field in contents.split_whitespace() {
+                    if let Some(("rbytes" | "wbytes", v)) = field.split_once('=') {
+                        self.io_bytes += v.parse::<u64>()?;
+                    }
+                }
+            }
+            "cpu.pressure" => {
+                let some = contents.lines().find(|l| l.starts_with("some"));
+                if let Some(total) = some.and_then(|l| l.split_once("total=")) {
+                    self.cpu_pressure_usec = total.1.trim().parse()?;
+                }
+            }
+            "cpu.stat" | "memory.stat" => {
+                for line in contents.lines() {
+                    let (key, value) = match line.split_once(' ') {
+                        Some(kv) => kv,
+                        None => continue,
+                    };
+                    let field = match key {
+                        "usage_usec" => &mut self.usage_usec,
+                        "nr_periods" => &mut self.nr_periods,
+                        "nr_throttled" => &mut self.nr_throttled,
+                        "throttled_usec" => &mut self.throttled_usec,
+                        "anon" => &mut self.anon,
+                        "file" => &mut self.file,
+                        _ => continue,
+                    };
+                    *field = value.trim().parse()?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cgroup {
+    pub time: TimeEnum,
+    pub cgroups: HashMap<String, CgroupValues>,
+}
+
+impl Cgroup {
+    fn new() -> Self {
+        Cgroup {
+            time: TimeEnum::DateTime(Utc::now()),
+            cgroups: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CgroupSample {
+    pub time: TimeEnum,
+    pub value: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CgroupEntry {
+    pub name: String,
+    /// What the cgroups are ranked by, e.g. the CPU time used over the run.
+    pub total: f64,
+    pub detail: String,
+    pub samples: Vec<CgroupSample>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndCgroupData {
+    pub collection_time: TimeEnum,
+    pub entries: Vec<CgroupEntry>,
+}
+
+fn millis(time: TimeEnum) -> u64 {
+    match time {
+        TimeEnum::TimeDiff(v) => v,
+        _ => 0,
+    }
+}
+
+/// Rank the cgroups and keep the top ones with their samples. Counters are plotted as rates
+/// over each interval, memory as it was sampled.
+fn get_ranking(values: Vec<Cgroup>, ranking: &str) -> Result<String> {
+    if !CGROUP_RANKINGS.contains(&ranking) {
+        return Err(PDError::VisualizerUnsupportedAPI.into());
+    }
+    let time_zero = values[0].time;
+    let names: BTreeSet<&String> = values.iter().flat_map(|v| v.cgroups.keys()).collect();
+    let mut entries = Vec::new();
+    for name in names {
+        let mut entry = CgroupEntry {
+            name: name.clone(),
+            total: 0.0,
+            detail: String::new(),
+            samples: Vec::new(),
+        };
+        let (mut periods, mut throttled) = (0, 0);
+        let mut prev: Option<(&CgroupValues, TimeEnum)> = None;
+        for value in &values {
+            let curr = match value.cgroups.get(name) {
+                Some(c) => c,
+                None => {
+                    prev = None;
+                    continue;
+                }
+            };
+            let time = value.time - time_zero;
+            if ranking == "memory" {
+                let mib = curr.memory_current as f64 / MIB;
+                entry.total = entry.total.max(mib);
+                entry.samples.push(CgroupSample { time, value: mib });
+                entry.detail = format!(
+                    "peak {:.1} MiB, last anon {:.1} MiB, file {:.1} MiB",
+                    entry.total,
+                    curr.anon as f64 / MIB,
+                    curr.file as f64 / MIB
+                );
+                continue;
+            }
+            if let Some((p, prev_time)) = prev {
+                let ms = millis(time).saturating_sub(millis(prev_time));
+                if ms > 0 {
+                    let delta = |c: u64, p: u64| c.saturating_sub(p) as f64;
+                    let (used, value) = match ranking {
+                        /* Percent of one CPU */
+                        "cpu" => {
+                            let d = delta(curr.usage_usec, p.usage_usec);
+                            (d, d / (ms as f64 * 10.0))
+                        }
+                        "cpu_pressure" => {
+                            let d = delta(curr.cpu_pressure_usec, p.cpu_pressure_usec);
+                            (d, d / (ms as f64 * 10.0))
+                        }
+                        /* Milliseconds throttled per second */
+                        "throttling" => {
+                            periods += curr.nr_periods.saturating_sub(p.nr_periods);
+                            throttled += curr.nr_throttled.saturating_sub(p.nr_throttled);
+                            let d = delta(curr.throttled_usec, p.throttled_usec);
+                            (d, d / ms as f64)
+                        }
+                        /* MB per second */
+                        _ => {
+                            let d = delta(curr.io_bytes, p.io_bytes);
+                            (d, d / (ms as f64 * 1000.0))
+                        }
+                    };
+                    entry.total += used;
+                    entry.samples.push(CgroupSample { time, value });
+                }
+            }
+            prev = Some((curr, time));
+        }
+        entry.detail = match ranking {
+            "cpu" => format!("{:.2} s of CPU time", entry.total / 1e6),
+            "cpu_pressure" => format!("stalled for {:.2} s", entry.total / 1e6),
+            "throttling" => format!(
+                "throttled {:.2} s, in {} of {} periods",
+                entry.total / 1e6,
+                throttled,
+                periods
+            ),
+            "io" => format!("{:.1} MB read and written", entry.total / 1e6),
+            _ => entry.detail,
+        };
+        if entry.total > 0.0 {
+            entries.push(entry);
+        }
+    }
+    keep_top_entries(&mut entries, |e| e.total);
+    let end_values = EndCgroupData {
+        collection_time: values.last().unwrap().time - time_zero,
+        entries,
+    };
+    Ok(serde_json::to_string(&end_values)?)
+}
+
+impl GetData for Cgroup {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::CgroupRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut cgroup = Cgroup::new();
+        cgroup.time = raw_value.time;
+        for sampled in &raw_value.cgroups {
+            let mut values = CgroupValues::default();
+            for (name, contents) in &sampled.files {
+                values.set_file(name, contents)?;
+            }
+            cgroup.cgroups.insert(sampled.path.clone(), values);
+        }
+        Ok(ProcessedData::Cgroup(cgroup))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        _metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Cgroup(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => Ok(serde_json::to_string(&CGROUP_RANKINGS)?),
+            "values" => {
+                let (_, key) = &param[2];
+                get_ranking(values, key)
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_cgroup() {
+    let cgroup_raw = CgroupRaw::new();
+    let file_name = CGROUP_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::CgroupRaw(cgroup_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let cgroup = Cgroup::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Cgroup(cgroup.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/cgroup.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sample_cgroups, Cgroup, CgroupRaw, EndCgroupData};
+    use crate::data::test_utils::{get_data, process_samples, sample_time};
+    use crate::data::{CollectData, CollectorParams, Data};
+    use crate::utils::DataMetrics;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn write_cgroup(dir: &Path, usage_usec: u64, throttled_usec: u64, memory: u64) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("cpu.stat"),
+            format!(
+                "usage_usec {}\nuser_usec 0\nsystem_usec 0\nnr_periods 10\nnr_throttled 2\nthrottled_usec {}\n",
+                usage_usec, throttled_usec
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("memory.current"), format!("{}\n", memory)).unwrap();
+        fs::write(dir.join("memory.stat"), "anon 1048576\nfile 2097152\n").unwrap();
+        fs::write(
+            dir.join("io.stat"),
+            "259:0 rbytes=4096 wbytes=8192 rios=1 wios=2 dbytes=0 dios=0\n",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let root = TempDir::with_prefix("aperf_cgroup").unwrap();
+        let mut params = CollectorParams::new();
+        params.cgroup_root = root.path().to_path_buf();
+        params.cgroup_depth = 2;
+
+        let mut cgroup = CgroupRaw::new();
+        assert!(cgroup.prepare_data_collector(&params).is_err());
+        fs::write(root.path().join("cgroup.controllers"), "cpu memory io\n").unwrap();
+        cgroup.prepare_data_collector(&params).unwrap();
+
+        write_cgroup(&root.path().join("system.slice/sshd.service"), 0, 0, 0);
+        write_cgroup(&root.path().join("system.slice/sshd.service/deep"), 0, 0, 0);
+        fs::create_dir_all(root.path().join("user.slice")).unwrap();
+        cgroup.collect_data(&params).unwrap();
+        let paths: Vec<&str> = cgroup.cgroups.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["/system.slice", "/system.slice/sshd.service", "/user.slice"]
+        );
+        assert_eq!(cgroup.cgroups[1].files.len(), 4);
+        assert!(cgroup.cgroups[2].files.is_empty());
+    }
+
+    #[test]
+    fn test_removed_cgroup() {
+        let root = TempDir::with_prefix("aperf_cgroup").unwrap();
+        let mut out = Vec::new();
+
+        /* A child removed while the walk is below its parent is skipped, a missing root is not */
+        let gone = root.path().join("gone.slice");
+        sample_cgroups(&gone, "/gone.slice", 1, &mut out).unwrap();
+        assert!(out.is_empty());
+        assert!(sample_cgroups(&gone, "", 1, &mut out).is_err());
+    }
+
+    #[test]
+    fn test_get_ranking() {
+        let root = TempDir::with_prefix("aperf_cgroup").unwrap();
+        let mut params = CollectorParams::new();
+        params.cgroup_root = root.path().to_path_buf();
+        let busy = root.path().join("busy.slice");
+        let idle = root.path().join("idle.slice");
+
+        let mut samples = Vec::new();
+        for (seconds, usage) in [(0, 0), (1, 500_000), (2, 1_000_000)] {
+            write_cgroup(&busy, usage, usage / 10, 64 << 20);
+            write_cgroup(&idle, 0, 0, 1 << 20);
+            let mut raw = CgroupRaw::new();
+            raw.collect_data(&params).unwrap();
+            raw.time = sample_time(seconds);
+            samples.push(Data::CgroupRaw(raw));
+        }
+        let buffer = process_samples(Cgroup::new, samples);
+        let get = |key: &str| {
+            get_data(
+                Cgroup::new,
+                &buffer,
+                &format!("get=values&key={}", key),
+                &mut DataMetrics::new(String::new()),
+            )
+        };
+        let ranking =
+            |key: &str| serde_json::from_str::<EndCgroupData>(&get(key).unwrap()).unwrap();
+
+        let cpu = ranking("cpu");
+        assert_eq!(cpu.entries.len(), 1);
+        assert_eq!(cpu.entries[0].name, "/busy.slice");
+        let percent: Vec<f64> = cpu.entries[0].samples.iter().map(|s| s.value).collect();
+        assert_eq!(percent, vec![50.0, 50.0]);
+        assert_eq!(cpu.entries[0].detail, "1.00 s of CPU time");
+
+        let throttling = ranking("throttling");
+        assert_eq!(throttling.entries[0].samples[0].value, 50.0);
+
+        let memory = ranking("memory");
+        let names: Vec<&str> = memory.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["/busy.slice", "/idle.slice"]);
+        assert_eq!(memory.entries[0].samples.len(), 3);
+        assert_eq!(memory.entries[0].total, 64.0);
+
+        /* io.stat does not change, nothing to rank */
+        assert!(ranking("io").entries.is_empty());
+        assert!(get("swap").is_err());
+    }
+}
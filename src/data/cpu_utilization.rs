@@ -3,7 +3,7 @@ extern crate ctor;
 use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
 use crate::utils::{DataMetrics, Metric};
 use crate::visualizer::{DataVisualizer, GetData};
-use crate::{PERFORMANCE_DATA, VISUALIZATION_DATA};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
 use anyhow::Result;
 use chrono::prelude::*;
 use ctor::ctor;
@@ -171,13 +171,21 @@ impl CpuUtilization {
 fn process_gathered_raw_data(buffer: Data) -> Result<ProcessedData> {
     let raw_value = match buffer {
         Data::CpuUtilizationRaw(ref value) => value,
-        _ => panic!("Invalid Data type in raw file"),
+        _ => return Err(PDError::InvalidRawDataType.into()),
     };
-    let stat = KernelStats::from_reader(raw_value.data.as_bytes()).unwrap();
+    let stat = KernelStats::from_reader(raw_value.data.as_bytes()).map_err(|e| {
+        PDError::ProcessorMalformedData(CPU_UTILIZATION_FILE_NAME.to_string(), e.to_string())
+    })?;
     let mut cpu_utilization = CpuUtilization::new();
     let time_now = match raw_value.time {
         TimeEnum::DateTime(value) => value,
-        _ => panic!("Has to be datetime"),
+        _ => {
+            return Err(PDError::ProcessorMalformedData(
+                CPU_UTILIZATION_FILE_NAME.to_string(),
+                "sample time is not a date and time".to_string(),
+            )
+            .into())
+        }
     };
 
     /* Get total numbers */
@@ -0,0 +1,521 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::{add_metrics, DataMetrics, Metric};
+use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub static CPUFREQ_FILE_NAME: &str = "cpufreq";
+
+pub static CPU_SYSFS_DIR: &str = "/sys/devices/system/cpu";
+
+/// The cumulative counters of a cpuidle state of a CPU.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IdleStateRaw {
+    pub state: u64,
+    /// Microseconds spent in the state.
+    pub time: String,
+    /// Number of times the state was entered.
+    pub usage: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CpuPowerRaw {
+    pub cpu: u64,
+    /// kHz, empty if the CPU has no cpufreq driver.
+    pub cur_freq: String,
+    pub idle_states: Vec<IdleStateRaw>,
+}
+
+/// Gather the current frequency and the cpuidle counters of each CPU. Most VMs expose neither.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CpufreqRaw {
+    pub time: TimeEnum,
+    /// The names of the cpuidle states, e.g. "C1", by state number.
+    pub idle_state_names: Vec<String>,
+    pub cpus: Vec<CpuPowerRaw>,
+}
+
+impl CpufreqRaw {
+    fn new() -> Self {
+        CpufreqRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            idle_state_names: Vec::new(),
+            cpus: Vec::new(),
+        }
+    }
+}
+
+/// The numbered entries `prefix<N>` of `dir`, in order.
+pub(crate) fn numbered_entries(dir: &Path, prefix: &str) -> Vec<u64> {
+    let mut entries: Vec<u64> = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.strip_prefix(prefix)?.parse::<u64>().ok()
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    entries.sort();
+    entries
+}
+
+fn read_trimmed(path: &Path) -> String {
+    fs::read_to_string(path)
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn read_cpus(root: &Path) -> Vec<CpuPowerRaw> {
+    let mut cpus = Vec::new();
+    for cpu in numbered_entries(root, "cpu") {
+        let cpu_dir = root.join(format!("cpu{}", cpu));
+        let idle_dir = cpu_dir.join("cpuidle");
+        let idle_states = numbered_entries(&idle_dir, "state")
+            .into_iter()
+            .map(|state| {
+                let state_dir = idle_dir.join(format!("state{}", state));
+                IdleStateRaw {
+                    state,
+                    time: read_trimmed(&state_dir.join("time")),
+                    usage: read_trimmed(&state_dir.join("usage")),
+                }
+            })
+            .collect();
+        cpus.push(CpuPowerRaw {
+            cpu,
+            cur_freq: read_trimmed(&cpu_dir.join("cpufreq/scaling_cur_freq")),
+            idle_states,
+        });
+    }
+    cpus
+}
+
+fn read_idle_state_names(root: &Path, cpus: &[CpuPowerRaw]) -> Vec<String> {
+    let cpu = match cpus.iter().find(|c| !c.idle_states.is_empty()) {
+        Some(c) => c,
+        None => return Vec::new(),
+    };
+    cpu.idle_states
+        .iter()
+        .map(|s| read_trimmed(&root.join(format!("cpu{}/cpuidle/state{}/name", cpu.cpu, s.state))))
+        .collect()
+}
+
+impl CollectData for CpufreqRaw {
+    fn prepare_data_collector(&mut self, _params: &CollectorParams) -> Result<()> {
+        let root = Path::new(CPU_SYSFS_DIR);
+        let cpus = read_cpus(root);
+        if cpus
+            .iter()
+            .all(|c| c.cur_freq.is_empty() && c.idle_states.is_empty())
+        {
+            warn!("CPU frequency and idle states are not available");
+            return Err(PDError::CollectorCpufreqUnavailable.into());
+        }
+        self.idle_state_names = read_idle_state_names(root, &cpus);
+        Ok(())
+    }
+
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.cpus = read_cpus(Path::new(CPU_SYSFS_DIR));
+        trace!("{:#?}", self.cpus);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct IdleStateValues {
+    pub time: u64,
+    pub usage: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CpuPowerValues {
+    pub cpu: u64,
+    /// kHz
+    pub cur_freq: Option<u64>,
+    pub idle_states: Vec<IdleStateValues>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cpufreq {
+    pub time: TimeEnum,
+    pub idle_state_names: Vec<String>,
+    pub cpus: Vec<CpuPowerValues>,
+}
+
+impl Cpufreq {
+    fn new() -> Self {
+        Cpufreq {
+            time: TimeEnum::DateTime(Utc::now()),
+            idle_state_names: Vec::new(),
+            cpus: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct FrequencyEntry {
+    pub time: TimeEnum,
+    /// MHz
+    pub frequency: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ResidencyEntry {
+    pub time: TimeEnum,
+    /// Percentage of the interval spent in the state.
+    pub residency: f64,
+    /// Entries into the state per second.
+    pub usage: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CpuData<T> {
+    pub cpu: u64,
+    pub data: Vec<T>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndFrequencyData {
+    pub per_cpu: Vec<CpuData<FrequencyEntry>>,
+    pub metadata: GraphMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct IdleStateData {
+    pub name: String,
+    pub per_cpu: Vec<CpuData<ResidencyEntry>>,
+}
+
+fn get_frequency(values: Vec<Cpufreq>, metrics: &mut DataMetrics) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut metric = Metric::new("frequency".to_string());
+    let mut metadata = GraphMetadata::new();
+    let mut per_cpu: Vec<CpuData<FrequencyEntry>> = Vec::new();
+    for value in &values {
+        let mut total = 0;
+        let mut cpus = 0;
+        for cpu in &value.cpus {
+            let frequency = match cpu.cur_freq {
+                Some(f) => f / 1000,
+                None => continue,
+            };
+            let entry = FrequencyEntry {
+                time: value.time - time_zero,
+                frequency,
+            };
+            match per_cpu.iter_mut().find(|c| c.cpu == cpu.cpu) {
+                Some(c) => c.data.push(entry),
+                None => per_cpu.push(CpuData {
+                    cpu: cpu.cpu,
+                    data: vec![entry],
+                }),
+            }
+            metadata.update_limits(GraphLimitType::UInt64(frequency));
+            total += frequency;
+            cpus += 1;
+        }
+        if cpus > 0 {
+            metric.insert_value(total as f64 / cpus as f64);
+        }
+    }
+    if !metric.values.is_empty() {
+        add_metrics(
+            "frequency".to_string(),
+            &mut metric,
+            metrics,
+            CPUFREQ_FILE_NAME.to_string(),
+        )?;
+    }
+    Ok(serde_json::to_string(&EndFrequencyData {
+        per_cpu,
+        metadata,
+    })?)
+}
+
+/// Residency of each idle state per CPU and interval, from the time spent in the state over the
+/// time elapsed.
+fn get_idle(values: Vec<Cpufreq>) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut states: Vec<IdleStateData> = Vec::new();
+    for pair in values.windows(2) {
+        let (prev, value) = (&pair[0], &pair[1]);
+        let elapsed_ms = match value.time - prev.time {
+            TimeEnum::TimeDiff(ms) if ms > 0 => ms,
+            _ => continue,
+        };
+        for cpu in &value.cpus {
+            let prev_cpu = match prev.cpus.iter().find(|c| c.cpu == cpu.cpu) {
+                Some(c) => c,
+                None => continue,
+            };
+            for (state, (curr, prev)) in cpu
+                .idle_states
+                .iter()
+                .zip(prev_cpu.idle_states.iter())
+                .enumerate()
+            {
+                if states.len() <= state {
+                    states.push(IdleStateData {
+                        name: value
+                            .idle_state_names
+                            .get(state)
+                            .cloned()
+                            .unwrap_or_else(|| format!("state{}", state)),
+                        per_cpu: Vec::new(),
+                    });
+                }
+                let entry = ResidencyEntry {
+                    time: value.time - time_zero,
+                    residency: (curr.time.saturating_sub(prev.time) as f64
+                        / (elapsed_ms * 10) as f64)
+                        .min(100.0),
+                    usage: curr.usage.saturating_sub(prev.usage) * 1000 / elapsed_ms,
+                };
+                let per_cpu = &mut states[state].per_cpu;
+                match per_cpu.iter_mut().find(|c| c.cpu == cpu.cpu) {
+                    Some(c) => c.data.push(entry),
+                    None => per_cpu.push(CpuData {
+                        cpu: cpu.cpu,
+                        data: vec![entry],
+                    }),
+                }
+            }
+        }
+    }
+    Ok(serde_json::to_string(&states)?)
+}
+
+fn get_keys(value: &Cpufreq) -> Result<String> {
+    let mut keys = Vec::new();
+    if value.cpus.iter().any(|c| c.cur_freq.is_some()) {
+        keys.push("frequency");
+    }
+    if value.cpus.iter().any(|c| !c.idle_states.is_empty()) {
+        keys.push("idle");
+    }
+    Ok(serde_json::to_string(&keys)?)
+}
+
+impl GetData for Cpufreq {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::CpufreqRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut cpufreq = Cpufreq::new();
+        cpufreq.time = raw_value.time;
+        cpufreq.idle_state_names = raw_value.idle_state_names.clone();
+        for cpu in &raw_value.cpus {
+            let mut values = CpuPowerValues {
+                cpu: cpu.cpu,
+                cur_freq: cpu.cur_freq.parse().ok(),
+                idle_states: Vec::new(),
+            };
+            for state in &cpu.idle_states {
+                values.idle_states.push(IdleStateValues {
+                    time: state.time.parse().unwrap_or_default(),
+                    usage: state.usage.parse().unwrap_or_default(),
+                });
+            }
+            cpufreq.cpus.push(values);
+        }
+        Ok(ProcessedData::Cpufreq(cpufreq))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Cpufreq(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => get_keys(&values[0]),
+            "values" => {
+                let (_, key) = &param[2];
+                match key.as_str() {
+                    "frequency" => get_frequency(values, metrics),
+                    "idle" => get_idle(values),
+                    _ => Err(PDError::VisualizerUnsupportedAPI.into()),
+                }
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_cpufreq() {
+    let cpufreq_raw = CpufreqRaw::new();
+    let file_name = CPUFREQ_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::CpufreqRaw(cpufreq_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let cpufreq = Cpufreq::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Cpufreq(cpufreq.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/cpufreq.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_cpus, read_idle_state_names, CpuPowerRaw, Cpufreq, CpufreqRaw, EndFrequencyData,
+        IdleStateData, IdleStateRaw,
+    };
+    use crate::data::test_utils::{get_data, get_keys, process_samples, sample_time};
+    use crate::data::Data;
+    use crate::utils::{DataMetrics, ValueType};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn raw_data(seconds: i64, freq: &str, c1_time: u64) -> Data {
+        let cpus = (0..2)
+            .map(|cpu| CpuPowerRaw {
+                cpu,
+                cur_freq: freq.to_string(),
+                idle_states: vec![
+                    IdleStateRaw {
+                        state: 0,
+                        time: "0".to_string(),
+                        usage: "0".to_string(),
+                    },
+                    IdleStateRaw {
+                        state: 1,
+                        time: c1_time.to_string(),
+                        usage: (c1_time / 100).to_string(),
+                    },
+                ],
+            })
+            .collect();
+        Data::CpufreqRaw(CpufreqRaw {
+            time: sample_time(seconds),
+            idle_state_names: vec!["POLL".to_string(), "C1".to_string()],
+            cpus,
+        })
+    }
+
+    #[test]
+    fn test_read_cpus() {
+        let root = TempDir::with_prefix("aperf_cpufreq").unwrap();
+        fs::create_dir_all(root.path().join("cpu0/cpufreq")).unwrap();
+        fs::write(
+            root.path().join("cpu0/cpufreq/scaling_cur_freq"),
+            "2500000\n",
+        )
+        .unwrap();
+        for state in 0..2 {
+            let state_dir = root.path().join(format!("cpu0/cpuidle/state{}", state));
+            fs::create_dir_all(&state_dir).unwrap();
+            fs::write(state_dir.join("name"), format!("C{}\n", state)).unwrap();
+            fs::write(state_dir.join("time"), "1000\n").unwrap();
+            fs::write(state_dir.join("usage"), "10\n").unwrap();
+        }
+        /* A VM without cpufreq or cpuidle */
+        fs::create_dir_all(root.path().join("cpu1/topology")).unwrap();
+        fs::create_dir_all(root.path().join("cpufreq")).unwrap();
+
+        let cpus = read_cpus(root.path());
+        assert_eq!(cpus.len(), 2);
+        assert_eq!(cpus[0].cur_freq, "2500000");
+        assert_eq!(cpus[0].idle_states[1].time, "1000");
+        assert!(cpus[1].cur_freq.is_empty() && cpus[1].idle_states.is_empty());
+        assert_eq!(read_idle_state_names(root.path(), &cpus), vec!["C0", "C1"]);
+    }
+
+    #[test]
+    fn test_get_values() {
+        let buffer = process_samples(
+            Cpufreq::new,
+            vec![raw_data(0, "2000000", 0), raw_data(1, "3000000", 250000)],
+        );
+        assert_eq!(get_keys(Cpufreq::new, &buffer), vec!["frequency", "idle"]);
+
+        let mut metrics = DataMetrics::new(String::new());
+        let get = |key: &str, metrics: &mut DataMetrics| {
+            get_data(
+                Cpufreq::new,
+                &buffer,
+                &format!("get=values&key={}", key),
+                metrics,
+            )
+            .unwrap()
+        };
+        let frequency: EndFrequencyData =
+            serde_json::from_str(&get("frequency", &mut metrics)).unwrap();
+        assert_eq!(frequency.per_cpu[1].data[1].frequency, 3000);
+        match metrics.values["cpufreq"]["frequency"] {
+            ValueType::Stats(ref stats) => assert!((stats.mean - 2500.0).abs() < 1e-9),
+            _ => unreachable!(),
+        }
+
+        /* 250ms of C1 in 1s */
+        let idle: Vec<IdleStateData> = serde_json::from_str(&get("idle", &mut metrics)).unwrap();
+        assert_eq!(idle[1].name, "C1");
+        assert!((idle[1].per_cpu[0].data[0].residency - 25.0).abs() < 1e-9);
+        assert_eq!(idle[1].per_cpu[0].data[0].usage, 2500);
+        assert_eq!(idle[0].per_cpu[1].data[0].residency, 0.0);
+    }
+
+    #[test]
+    fn test_unavailable() {
+        let samples = (0..2)
+            .map(|seconds| {
+                Data::CpufreqRaw(CpufreqRaw {
+                    time: sample_time(seconds),
+                    idle_state_names: Vec::new(),
+                    cpus: vec![CpuPowerRaw {
+                        cpu: 0,
+                        cur_freq: String::new(),
+                        idle_states: Vec::new(),
+                    }],
+                })
+            })
+            .collect();
+        let buffer = process_samples(Cpufreq::new, samples);
+        assert!(get_keys(Cpufreq::new, &buffer).is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+extern crate ctor;
+
+use crate::data::cpufreq::{numbered_entries, CPU_SYSFS_DIR};
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::DataMetrics;
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub static CPUFREQ_SETTINGS_FILE_NAME: &str = "cpufreq_settings";
+
+/// Files relative to the CPU sysfs directory.
+static GLOBAL_FILES: [&str; 6] = [
+    "cpufreq/boost",
+    "intel_pstate/status",
+    "intel_pstate/no_turbo",
+    "cpuidle/current_driver",
+    "cpuidle/current_governor",
+    "cpuidle/current_governor_ro",
+];
+
+/// Files relative to each cpufreq policy directory.
+static POLICY_FILES: [&str; 8] = [
+    "related_cpus",
+    "scaling_driver",
+    "scaling_governor",
+    "scaling_min_freq",
+    "scaling_max_freq",
+    "cpuinfo_min_freq",
+    "cpuinfo_max_freq",
+    "energy_performance_preference",
+];
+
+/// Files relative to each cpuidle state directory of CPU 0.
+static IDLE_STATE_FILES: [&str; 3] = ["name", "latency", "disable"];
+
+/// The cpufreq and cpuidle driver and governor settings, keyed by their path under
+/// /sys/devices/system/cpu. Empty where neither is exposed, as on most VMs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CpufreqSettings {
+    pub time: TimeEnum,
+    pub settings: BTreeMap<String, String>,
+}
+
+impl CpufreqSettings {
+    fn new() -> Self {
+        CpufreqSettings {
+            time: TimeEnum::DateTime(Utc::now()),
+            settings: BTreeMap::new(),
+        }
+    }
+}
+
+fn read_settings(root: &Path) -> BTreeMap<String, String> {
+    let mut files: Vec<String> = GLOBAL_FILES.iter().map(|f| f.to_string()).collect();
+    for policy in numbered_entries(&root.join("cpufreq"), "policy") {
+        for file in POLICY_FILES {
+            files.push(format!("cpufreq/policy{}/{}", policy, file));
+        }
+    }
+    for state in numbered_entries(&root.join("cpu0/cpuidle"), "state") {
+        for file in IDLE_STATE_FILES {
+            files.push(format!("cpu0/cpuidle/state{}/{}", state, file));
+        }
+    }
+    let mut settings = BTreeMap::new();
+    for file in files {
+        if let Ok(value) = fs::read_to_string(root.join(&file)) {
+            settings.insert(file, value.trim().to_string());
+        }
+    }
+    settings
+}
+
+impl CollectData for CpufreqSettings {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.settings = read_settings(Path::new(CPU_SYSFS_DIR));
+        trace!("{:#?}", self.settings);
+        Ok(())
+    }
+}
+
+impl GetData for CpufreqSettings {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::CpufreqSettings(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        Ok(ProcessedData::CpufreqSettings((*raw_value).clone()))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        _metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::CpufreqSettings(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "values" => Ok(serde_json::to_string(&values[0].settings)?),
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_cpufreq_settings() {
+    let cpufreq_settings = CpufreqSettings::new();
+    let file_name = CPUFREQ_SETTINGS_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::CpufreqSettings(cpufreq_settings.clone()),
+        file_name.clone(),
+        true,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let dv = DataVisualizer::new(
+        ProcessedData::CpufreqSettings(cpufreq_settings),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/cpufreq_settings.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_settings, CpufreqSettings};
+    use crate::data::{CollectData, CollectorParams};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_settings() {
+        let root = TempDir::with_prefix("aperf_cpufreq_settings").unwrap();
+        let policy_dir = root.path().join("cpufreq/policy0");
+        fs::create_dir_all(&policy_dir).unwrap();
+        fs::write(policy_dir.join("scaling_governor"), "performance\n").unwrap();
+        fs::write(policy_dir.join("related_cpus"), "0 1\n").unwrap();
+        fs::create_dir_all(root.path().join("cpu0/cpuidle/state1")).unwrap();
+        fs::write(root.path().join("cpu0/cpuidle/state1/name"), "C1\n").unwrap();
+
+        let settings = read_settings(root.path());
+        assert_eq!(settings.len(), 3);
+        assert_eq!(settings["cpufreq/policy0/scaling_governor"], "performance");
+        assert_eq!(settings["cpufreq/policy0/related_cpus"], "0 1");
+        assert_eq!(settings["cpu0/cpuidle/state1/name"], "C1");
+    }
+
+    #[test]
+    fn test_collect_data() {
+        /* Succeeds with no settings where sysfs does not expose cpufreq */
+        let mut cpufreq_settings = CpufreqSettings::new();
+        let params = CollectorParams::new();
+
+        cpufreq_settings.collect_data(&params).unwrap();
+    }
+}
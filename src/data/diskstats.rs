@@ -2,9 +2,10 @@ extern crate ctor;
 
 use crate::data::constants::*;
 use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::overhead::sample_every;
 use crate::utils::{add_metrics, DataMetrics, Metric};
 use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
-use crate::{PERFORMANCE_DATA, VISUALIZATION_DATA};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
 use anyhow::Result;
 use chrono::prelude::*;
 use ctor::ctor;
@@ -121,7 +122,7 @@ pub enum DiskstatKeys {
 fn process_collected_raw_data(buffer: Data) -> Result<ProcessedData> {
     let raw_value = match buffer {
         Data::DiskstatsRaw(ref value) => value,
-        _ => panic!("Invalid Data type in raw file"),
+        _ => return Err(PDError::InvalidRawDataType.into()),
     };
     let mut diskstats = Diskstats::new();
     diskstats.set_time(raw_value.time);
@@ -237,7 +238,12 @@ pub struct EndDiskValues {
     pub metadata: GraphMetadata,
 }
 
-fn get_values(values: Vec<Diskstats>, key: String, metrics: &mut DataMetrics) -> Result<String> {
+fn get_values(
+    values: Vec<Diskstats>,
+    run: &str,
+    key: String,
+    metrics: &mut DataMetrics,
+) -> Result<String> {
     let mut ev: BTreeMap<String, DiskValues> = BTreeMap::new();
     let mut metric = Metric::new(key.clone());
     let disk_names = get_disk_names(values[0].clone());
@@ -259,6 +265,7 @@ fn get_values(values: Vec<Diskstats>, key: String, metrics: &mut DataMetrics) ->
     let time_zero = values[0].time;
     let mut prev_data = values[0].clone();
     for v in values {
+        let every = sample_every(run, DISKSTATS_FILE_NAME, v.time) as f64;
         let mut prev_value: HashMap<String, u64> = HashMap::new();
         for disk in &prev_data.disk_stats {
             prev_value.insert(disk.name.clone(), *disk.stat.get(&key.clone()).unwrap());
@@ -275,6 +282,7 @@ fn get_values(values: Vec<Diskstats>, key: String, metrics: &mut DataMetrics) ->
                     - *prev_value.get(&disk.name).unwrap() as i64) as f64
                     * mult_factor as f64
                     / factor as f64
+                    / every
             };
             let dv = DiskValue {
                 time: (v.time - time_zero),
@@ -326,13 +334,14 @@ impl GetData for Diskstats {
             }
         }
         let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, run) = &param[0];
         let (_, req_str) = &param[1];
 
         match req_str.as_str() {
             "keys" => get_keys(),
             "values" => {
                 let (_, key) = &param[2];
-                get_values(values, key.to_string(), metrics)
+                get_values(values, run, key.to_string(), metrics)
             }
             _ => panic!("Unsupported API"),
         }
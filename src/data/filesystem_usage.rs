@@ -0,0 +1,457 @@
+extern crate ctor;
+
+use crate::data::mounts::{parse_mountinfo, MOUNTINFO_PATH};
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::{add_metrics, DataMetrics, Metric, ValueType};
+use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use nix::sys::statvfs::statvfs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+pub static FILESYSTEM_USAGE_FILE_NAME: &str = "filesystem_usage";
+
+/// The statvfs counters of a mounted filesystem.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct FsUsageRaw {
+    pub mount_point: String,
+    /// Bytes per block.
+    pub block_size: u64,
+    pub blocks: u64,
+    pub blocks_free: u64,
+    /// Free blocks available to unprivileged users.
+    pub blocks_available: u64,
+    pub files: u64,
+    pub files_free: u64,
+}
+
+/// Gather the capacity and inode usage of each mounted filesystem with a size.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FilesystemUsageRaw {
+    pub time: TimeEnum,
+    pub filesystems: Vec<FsUsageRaw>,
+}
+
+impl FilesystemUsageRaw {
+    fn new() -> Self {
+        FilesystemUsageRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            filesystems: Vec::new(),
+        }
+    }
+}
+
+/// Network and FUSE filesystems, whose statvfs can block for as long as the server does not
+/// answer. Collection runs on one thread, so they are not stat'ed.
+const SKIPPED_FS_TYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "afs",
+    "9p",
+    "fuse",
+];
+
+fn is_skipped_fs_type(fs_type: &str) -> bool {
+    /* FUSE types are "fuse" or "fuse.<name>", e.g. fuse.sshfs, and "fuseblk" */
+    let base = fs_type.split('.').next().unwrap_or_default();
+    SKIPPED_FS_TYPES.contains(&base) || base == "fuseblk"
+}
+
+/// The mount points to stat: the visible mount of each mount point, and only the first mount of
+/// each filesystem so that bind mounts are not repeated. Network and FUSE mounts are skipped.
+fn mount_points(mountinfo: &str) -> Vec<String> {
+    let mut visible: BTreeMap<String, usize> = BTreeMap::new();
+    let mounts = parse_mountinfo(mountinfo);
+    for (i, mount) in mounts.iter().enumerate() {
+        visible.insert(mount.mount_point.clone(), i);
+    }
+    let mut seen = Vec::new();
+    let mut points = Vec::new();
+    for (i, mount) in mounts.iter().enumerate() {
+        if visible[&mount.mount_point] != i
+            || seen.contains(&mount.major_minor)
+            || is_skipped_fs_type(&mount.fs_type)
+        {
+            continue;
+        }
+        seen.push(mount.major_minor.clone());
+        points.push(mount.mount_point.clone());
+    }
+    points
+}
+
+impl CollectData for FilesystemUsageRaw {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.filesystems = Vec::new();
+        let mountinfo = fs::read_to_string(MOUNTINFO_PATH)?;
+        for mount_point in mount_points(&mountinfo) {
+            let stat = match statvfs(mount_point.as_str()) {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            /* Pseudo filesystems such as proc and sysfs have no size */
+            if stat.blocks() == 0 {
+                continue;
+            }
+            self.filesystems.push(FsUsageRaw {
+                mount_point,
+                block_size: stat.fragment_size() as u64,
+                blocks: stat.blocks() as u64,
+                blocks_free: stat.blocks_free() as u64,
+                blocks_available: stat.blocks_available() as u64,
+                files: stat.files() as u64,
+                files_free: stat.files_free() as u64,
+            });
+        }
+        trace!("{:#?}", self.filesystems);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FsUsage {
+    pub mount_point: String,
+    /// Bytes
+    pub size: u64,
+    /// Bytes
+    pub used: u64,
+    /// Used over used and available, as reported by df.
+    pub used_percent: f64,
+    pub inodes: u64,
+    /// None if the filesystem has no fixed number of inodes.
+    pub inodes_used_percent: Option<f64>,
+}
+
+impl FsUsage {
+    fn from_raw(raw: &FsUsageRaw) -> Self {
+        let used_blocks = raw.blocks.saturating_sub(raw.blocks_free);
+        let usable_blocks = used_blocks + raw.blocks_available;
+        let used_percent = if usable_blocks == 0 {
+            0.0
+        } else {
+            used_blocks as f64 * 100.0 / usable_blocks as f64
+        };
+        let inodes_used_percent = if raw.files == 0 {
+            None
+        } else {
+            Some(raw.files.saturating_sub(raw.files_free) as f64 * 100.0 / raw.files as f64)
+        };
+        FsUsage {
+            mount_point: raw.mount_point.clone(),
+            size: raw.blocks * raw.block_size,
+            used: used_blocks * raw.block_size,
+            used_percent,
+            inodes: raw.files,
+            inodes_used_percent,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FilesystemUsage {
+    pub time: TimeEnum,
+    pub filesystems: Vec<FsUsage>,
+}
+
+impl FilesystemUsage {
+    fn new() -> Self {
+        FilesystemUsage {
+            time: TimeEnum::DateTime(Utc::now()),
+            filesystems: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct UsageEntry {
+    pub time: TimeEnum,
+    pub used_percent: f64,
+    /// GiB for capacity, inodes for inodes.
+    pub used: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct MountUsage {
+    pub mount_point: String,
+    /// GiB for capacity, inodes for inodes, from the last sample.
+    pub size: f64,
+    pub data: Vec<UsageEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndUsageData {
+    pub per_mount: Vec<MountUsage>,
+    pub metadata: GraphMetadata,
+}
+
+/// The usage of each mount over time, for "capacity" or "inodes". The metric is the usage of the
+/// fullest mount, which is also recorded by name.
+fn get_usage(values: Vec<FilesystemUsage>, key: &str, metrics: &mut DataMetrics) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut metric = Metric::new(format!("{}_used_percent", key));
+    let mut metadata = GraphMetadata::new();
+    let mut per_mount: Vec<MountUsage> = Vec::new();
+    let mut fullest: Option<(String, f64)> = None;
+    for value in &values {
+        let mut max_percent: Option<f64> = None;
+        for fs in &value.filesystems {
+            let (used_percent, used, size) = match key {
+                "capacity" => (
+                    fs.used_percent,
+                    fs.used as f64 / (1024 * 1024 * 1024) as f64,
+                    fs.size as f64 / (1024 * 1024 * 1024) as f64,
+                ),
+                _ => match fs.inodes_used_percent {
+                    Some(p) => (p, fs.inodes as f64 * p / 100.0, fs.inodes as f64),
+                    None => continue,
+                },
+            };
+            let entry = UsageEntry {
+                time: value.time - time_zero,
+                used_percent,
+                used,
+            };
+            match per_mount
+                .iter_mut()
+                .find(|m| m.mount_point == fs.mount_point)
+            {
+                Some(m) => {
+                    m.size = size;
+                    m.data.push(entry);
+                }
+                None => per_mount.push(MountUsage {
+                    mount_point: fs.mount_point.clone(),
+                    size,
+                    data: vec![entry],
+                }),
+            }
+            metadata.update_limits(GraphLimitType::F64(used_percent));
+            max_percent = Some(max_percent.map_or(used_percent, |m: f64| m.max(used_percent)));
+            if fullest.as_ref().is_none_or(|(_, p)| used_percent > *p) {
+                fullest = Some((fs.mount_point.clone(), used_percent));
+            }
+        }
+        if let Some(p) = max_percent {
+            metric.insert_value(p);
+        }
+    }
+    if !metric.values.is_empty() {
+        add_metrics(
+            format!("{}_used_percent", key),
+            &mut metric,
+            metrics,
+            FILESYSTEM_USAGE_FILE_NAME.to_string(),
+        )?;
+    }
+    if let Some((mount_point, _)) = fullest {
+        metrics
+            .values
+            .entry(FILESYSTEM_USAGE_FILE_NAME.to_string())
+            .or_default()
+            .insert(
+                format!("{}_fullest_mount", key),
+                ValueType::String(mount_point),
+            );
+    }
+    Ok(serde_json::to_string(&EndUsageData {
+        per_mount,
+        metadata,
+    })?)
+}
+
+impl GetData for FilesystemUsage {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::FilesystemUsageRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut filesystem_usage = FilesystemUsage::new();
+        filesystem_usage.time = raw_value.time;
+        filesystem_usage.filesystems = raw_value
+            .filesystems
+            .iter()
+            .map(FsUsage::from_raw)
+            .collect();
+        Ok(ProcessedData::FilesystemUsage(filesystem_usage))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::FilesystemUsage(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => Ok(serde_json::to_string(&["capacity", "inodes"])?),
+            "values" => {
+                let (_, key) = &param[2];
+                match key.as_str() {
+                    "capacity" | "inodes" => get_usage(values, key, metrics),
+                    _ => Err(PDError::VisualizerUnsupportedAPI.into()),
+                }
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_filesystem_usage() {
+    let filesystem_usage_raw = FilesystemUsageRaw::new();
+    let file_name = FILESYSTEM_USAGE_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::FilesystemUsageRaw(filesystem_usage_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let filesystem_usage = FilesystemUsage::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::FilesystemUsage(filesystem_usage),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/filesystem_usage.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mount_points, FilesystemUsage, FilesystemUsageRaw, FsUsage, FsUsageRaw};
+    use crate::data::test_utils::{get_data, process_samples, sample_time};
+    use crate::data::{CollectData, CollectorParams, Data};
+    use crate::utils::{DataMetrics, ValueType};
+
+    #[test]
+    fn test_mount_points() {
+        let mountinfo = "\
+28 1 254:0 / / rw,relatime - ext4 /dev/vda rw
+26 25 0:24 / /dev/shm rw,relatime - tmpfs tmpfs rw
+31 26 0:27 / /dev/shm rw,relatime - tmpfs tmpfs rw
+40 28 254:0 /home /srv rw,relatime - ext4 /dev/vda rw
+41 28 0:50 / /mnt/nfs rw,relatime - nfs4 server:/export rw
+42 28 0:51 / /mnt/ssh rw,relatime - fuse.sshfs user@host: rw
+";
+        assert_eq!(mount_points(mountinfo), vec!["/", "/dev/shm"]);
+    }
+
+    #[test]
+    fn test_from_raw() {
+        let raw = FsUsageRaw {
+            mount_point: "/".to_string(),
+            block_size: 4096,
+            blocks: 1000,
+            blocks_free: 100,
+            blocks_available: 50,
+            files: 200,
+            files_free: 150,
+        };
+        let usage = FsUsage::from_raw(&raw);
+        assert_eq!(usage.size, 4096000);
+        assert_eq!(usage.used, 900 * 4096);
+        assert!((usage.used_percent - 900.0 * 100.0 / 950.0).abs() < 1e-9);
+        assert_eq!(usage.inodes_used_percent, Some(25.0));
+
+        let raw = FsUsageRaw { files: 0, ..raw };
+        assert_eq!(FsUsage::from_raw(&raw).inodes_used_percent, None);
+    }
+
+    #[test]
+    fn test_get_values() {
+        let mut samples = Vec::new();
+        for (seconds, blocks_free) in [(0, 500), (1, 20)] {
+            let raw = FilesystemUsageRaw {
+                time: sample_time(seconds),
+                filesystems: vec![
+                    FsUsageRaw {
+                        mount_point: "/".to_string(),
+                        block_size: 4096,
+                        blocks: 1000,
+                        blocks_free: 800,
+                        blocks_available: 800,
+                        files: 0,
+                        files_free: 0,
+                    },
+                    FsUsageRaw {
+                        mount_point: "/data".to_string(),
+                        block_size: 4096,
+                        blocks: 1000,
+                        blocks_free,
+                        blocks_available: blocks_free,
+                        files: 100,
+                        files_free: 50,
+                    },
+                ],
+            };
+            samples.push(Data::FilesystemUsageRaw(raw));
+        }
+        let buffer = process_samples(FilesystemUsage::new, samples);
+        let mut metrics = DataMetrics::new(String::new());
+        let json = get_data(
+            FilesystemUsage::new,
+            &buffer,
+            "get=values&key=capacity",
+            &mut metrics,
+        )
+        .unwrap();
+        let data: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(data["per_mount"].as_array().unwrap().len(), 2);
+        assert_eq!(data["per_mount"][1]["data"][1]["used_percent"], 98.0);
+        match metrics.values["filesystem_usage"]["capacity_used_percent"] {
+            ValueType::Stats(ref stats) => assert_eq!(stats.mean, 74.0),
+            _ => unreachable!(),
+        }
+        match metrics.values["filesystem_usage"]["capacity_fullest_mount"] {
+            ValueType::String(ref mount_point) => assert_eq!(mount_point, "/data"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let mut filesystem_usage = FilesystemUsageRaw::new();
+        let params = CollectorParams::new();
+
+        filesystem_usage.collect_data(&params).unwrap();
+        assert!(filesystem_usage
+            .filesystems
+            .iter()
+            .any(|f| f.mount_point == "/"));
+    }
+}
@@ -0,0 +1,670 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::DataMetrics;
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+pub static FRAGMENTATION_FILE_NAME: &str = "fragmentation";
+
+/// Order of a huge page with 4 KiB pages, used if /proc/meminfo has no Hugepagesize.
+static DEFAULT_HUGE_PAGE_ORDER: u64 = 9;
+
+/// The free blocks per zone and order from /proc/buddyinfo, the same per migrate type from
+/// /proc/pagetypeinfo, which only root can read, and the free pages and watermarks per zone from
+/// /proc/zoneinfo. Only the lines used are kept of the last two.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FragmentationRaw {
+    pub time: TimeEnum,
+    /// The order of a huge page, which the fragmentation is computed for.
+    pub huge_page_order: u64,
+    pub buddyinfo: String,
+    pub pagetypeinfo: String,
+    pub zoneinfo: String,
+}
+
+impl FragmentationRaw {
+    fn new() -> Self {
+        FragmentationRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            huge_page_order: DEFAULT_HUGE_PAGE_ORDER,
+            buddyinfo: String::new(),
+            pagetypeinfo: String::new(),
+            zoneinfo: String::new(),
+        }
+    }
+}
+
+fn huge_page_order() -> Result<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo")?;
+    let huge_page_kb = meminfo
+        .lines()
+        .find_map(|l| l.strip_prefix("Hugepagesize:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<u64>().ok());
+    let page_kb = procfs::page_size()? as u64 / 1024;
+    match huge_page_kb {
+        Some(kb) if page_kb > 0 && kb >= page_kb => Ok((kb / page_kb).ilog2() as u64),
+        _ => Ok(DEFAULT_HUGE_PAGE_ORDER),
+    }
+}
+
+/// The per migrate type free block lines of /proc/pagetypeinfo.
+fn filter_pagetypeinfo(data: &str) -> String {
+    data.lines()
+        .filter(|l| l.starts_with("Node") && l.contains(", type "))
+        .map(|l| format!("{}\n", l))
+        .collect()
+}
+
+/// The zone, free pages, watermark and managed pages lines of /proc/zoneinfo, without the per
+/// CPU page sets which are most of the file on large systems.
+fn filter_zoneinfo(data: &str) -> String {
+    data.lines()
+        .filter(|l| {
+            let mut tokens = l.split_whitespace();
+            match tokens.next() {
+                Some("Node") => true,
+                Some("pages") => tokens.next() == Some("free"),
+                Some("min") | Some("low") | Some("high") | Some("managed") => true,
+                _ => false,
+            }
+        })
+        .map(|l| format!("{}\n", l.trim()))
+        .collect()
+}
+
+impl CollectData for FragmentationRaw {
+    fn prepare_data_collector(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.huge_page_order = huge_page_order()?;
+        Ok(())
+    }
+
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.buddyinfo = fs::read_to_string("/proc/buddyinfo")?;
+        self.pagetypeinfo = fs::read_to_string("/proc/pagetypeinfo")
+            .map(|d| filter_pagetypeinfo(&d))
+            .unwrap_or_default();
+        self.zoneinfo = fs::read_to_string("/proc/zoneinfo")
+            .map(|d| filter_zoneinfo(&d))
+            .unwrap_or_default();
+        trace!("{:#?}", self.buddyinfo);
+        Ok(())
+    }
+}
+
+/// Free, minimum, low and high watermark pages of a zone.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Watermarks {
+    pub free: u64,
+    pub min: u64,
+    pub low: u64,
+    pub high: u64,
+    pub managed: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ZoneFree {
+    pub node: u64,
+    pub zone: String,
+    /// Free blocks of each order.
+    pub free_blocks: Vec<u64>,
+    /// Free blocks of each order per migrate type, if /proc/pagetypeinfo was readable.
+    pub migrate_types: BTreeMap<String, Vec<u64>>,
+    pub watermarks: Option<Watermarks>,
+}
+
+impl ZoneFree {
+    /// Free pages in blocks of any order.
+    pub fn free_pages(&self) -> u64 {
+        free_pages(&self.free_blocks)
+    }
+
+    /// The fraction of free memory in blocks too small for an allocation of `order`, as in
+    /// /sys/kernel/debug/extfrag/unusable_index.
+    pub fn unusable_index(&self, order: u64) -> f64 {
+        let free_pages = self.free_pages();
+        if free_pages == 0 {
+            return 0.0;
+        }
+        let usable: u64 = self
+            .free_blocks
+            .iter()
+            .enumerate()
+            .skip(order as usize)
+            .map(|(o, blocks)| blocks << o)
+            .sum();
+        (free_pages - usable) as f64 / free_pages as f64
+    }
+
+    /// The kernel's fragmentation index for an allocation of `order`, as in
+    /// /sys/kernel/debug/extfrag/extfrag_index: -1 if a free block is large enough, otherwise
+    /// towards 0 if the allocation fails for lack of memory and towards 1 if for fragmentation.
+    pub fn fragmentation_index(&self, order: u64) -> f64 {
+        let free_blocks_total: u64 = self.free_blocks.iter().sum();
+        if free_blocks_total == 0 {
+            return 0.0;
+        }
+        if self.free_blocks.iter().skip(order as usize).any(|b| *b > 0) {
+            return -1.0;
+        }
+        let requested = (1u64 << order) as f64;
+        1.0 - (1.0 + self.free_pages() as f64 / requested) / free_blocks_total as f64
+    }
+}
+
+fn free_pages(blocks: &[u64]) -> u64 {
+    blocks.iter().enumerate().map(|(o, b)| b << o).sum()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Fragmentation {
+    pub time: TimeEnum,
+    pub huge_page_order: u64,
+    pub zones: Vec<ZoneFree>,
+}
+
+impl Fragmentation {
+    fn new() -> Self {
+        Fragmentation {
+            time: TimeEnum::DateTime(Utc::now()),
+            huge_page_order: DEFAULT_HUGE_PAGE_ORDER,
+            zones: Vec::new(),
+        }
+    }
+}
+
+/// Parse the "Node 0, zone   Normal" start of a line, returning the node, zone and the rest.
+fn parse_zone(line: &str) -> Option<(u64, String, &str)> {
+    let (node, rest) = line.strip_prefix("Node")?.split_once(',')?;
+    let rest = rest.trim_start().strip_prefix("zone")?.trim_start();
+    let end = rest
+        .find(|c: char| c.is_whitespace() || c == ',')
+        .unwrap_or(rest.len());
+    Some((
+        node.trim().parse().ok()?,
+        rest[..end].to_string(),
+        &rest[end..],
+    ))
+}
+
+fn parse_counts(data: &str) -> Result<Vec<u64>> {
+    data.split_whitespace()
+        .map(|v| Ok(v.parse::<u64>()?))
+        .collect()
+}
+
+fn process_raw(raw: &FragmentationRaw) -> Result<Fragmentation> {
+    let mut fragmentation = Fragmentation::new();
+    fragmentation.time = raw.time;
+    fragmentation.huge_page_order = raw.huge_page_order;
+    for line in raw.buddyinfo.lines() {
+        if let Some((node, zone, rest)) = parse_zone(line) {
+            fragmentation.zones.push(ZoneFree {
+                node,
+                zone,
+                free_blocks: parse_counts(rest)?,
+                ..Default::default()
+            });
+        }
+    }
+    for line in raw.pagetypeinfo.lines() {
+        let (node, zone, rest) = match parse_zone(line) {
+            Some(z) => z,
+            None => continue,
+        };
+        let mut tokens = rest.trim_start_matches(',').split_whitespace();
+        let migrate_type = match (tokens.next(), tokens.next()) {
+            (Some("type"), Some(t)) => t.to_string(),
+            _ => continue,
+        };
+        let counts = parse_counts(&tokens.collect::<Vec<&str>>().join(" "))?;
+        if let Some(z) = fragmentation
+            .zones
+            .iter_mut()
+            .find(|z| z.node == node && z.zone == zone)
+        {
+            z.migrate_types.insert(migrate_type, counts);
+        }
+    }
+    let mut current: Option<usize> = None;
+    for line in raw.zoneinfo.lines() {
+        if let Some((node, zone, _)) = parse_zone(line) {
+            current = fragmentation
+                .zones
+                .iter()
+                .position(|z| z.node == node && z.zone == zone);
+            continue;
+        }
+        let zone = match current {
+            Some(i) => &mut fragmentation.zones[i],
+            None => continue,
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let value = match tokens.last().and_then(|v| v.parse::<u64>().ok()) {
+            Some(v) => v,
+            None => continue,
+        };
+        let watermarks = zone.watermarks.get_or_insert_with(Watermarks::default);
+        match tokens[0] {
+            "pages" => watermarks.free = value,
+            "min" => watermarks.min = value,
+            "low" => watermarks.low = value,
+            "high" => watermarks.high = value,
+            "managed" => watermarks.managed = value,
+            _ => {}
+        }
+    }
+    Ok(fragmentation)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct FreePagesHeatmap {
+    pub node: u64,
+    pub zone: String,
+    pub times: Vec<TimeEnum>,
+    /// Percentage of the free pages in blocks of each order, by order then sample.
+    pub percent: Vec<Vec<f64>>,
+    /// Free blocks of each order, by order then sample.
+    pub blocks: Vec<Vec<u64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct FragmentationEntry {
+    pub time: TimeEnum,
+    pub unusable_index: f64,
+    pub fragmentation_index: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WatermarksEntry {
+    pub time: TimeEnum,
+    pub watermarks: Watermarks,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct MigrateTypesEntry {
+    pub time: TimeEnum,
+    /// Free pages per migrate type.
+    pub free: BTreeMap<String, u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ZoneData<T> {
+    pub node: u64,
+    pub zone: String,
+    pub data: Vec<T>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndFragmentationData {
+    pub order: u64,
+    pub per_zone: Vec<ZoneData<FragmentationEntry>>,
+}
+
+/// Zones that had free pages at some point, leaving out empty zones such as Movable.
+fn zones_with_memory(values: &[Fragmentation]) -> Vec<(u64, String)> {
+    let mut zones = Vec::new();
+    for value in values {
+        for zone in &value.zones {
+            let key = (zone.node, zone.zone.clone());
+            if zone.free_pages() > 0 && !zones.contains(&key) {
+                zones.push(key);
+            }
+        }
+    }
+    zones
+}
+
+/// The entry of each zone in each sample it is in, given the time since the first sample.
+fn per_zone<T, F>(values: &[Fragmentation], entry: F) -> Vec<ZoneData<T>>
+where
+    F: Fn(TimeEnum, &ZoneFree) -> Option<T>,
+{
+    let time_zero = values[0].time;
+    let mut zones: Vec<ZoneData<T>> = zones_with_memory(values)
+        .into_iter()
+        .map(|(node, zone)| ZoneData {
+            node,
+            zone,
+            data: Vec::new(),
+        })
+        .collect();
+    for value in values {
+        for zone in &value.zones {
+            let data = match zones
+                .iter_mut()
+                .find(|z| z.node == zone.node && z.zone == zone.zone)
+            {
+                Some(z) => &mut z.data,
+                None => continue,
+            };
+            if let Some(e) = entry(value.time - time_zero, zone) {
+                data.push(e);
+            }
+        }
+    }
+    zones
+}
+
+fn get_free_pages(values: Vec<Fragmentation>) -> Result<String> {
+    let heatmaps: Vec<FreePagesHeatmap> =
+        per_zone(&values, |time, zone| Some((time, zone.free_blocks.clone())))
+            .into_iter()
+            .map(|zone| {
+                let orders = zone.data.iter().map(|(_, b)| b.len()).max().unwrap_or(0);
+                let mut heatmap = FreePagesHeatmap {
+                    node: zone.node,
+                    zone: zone.zone,
+                    times: Vec::new(),
+                    percent: vec![Vec::new(); orders],
+                    blocks: vec![Vec::new(); orders],
+                };
+                for (time, blocks) in zone.data {
+                    let free = free_pages(&blocks).max(1) as f64;
+                    heatmap.times.push(time);
+                    for order in 0..orders {
+                        let b = *blocks.get(order).unwrap_or(&0);
+                        heatmap.blocks[order].push(b);
+                        heatmap.percent[order].push((b << order) as f64 * 100.0 / free);
+                    }
+                }
+                heatmap
+            })
+            .collect();
+    Ok(serde_json::to_string(&heatmaps)?)
+}
+
+fn get_fragmentation(values: Vec<Fragmentation>) -> Result<String> {
+    let order = values[0].huge_page_order;
+    Ok(serde_json::to_string(&EndFragmentationData {
+        order,
+        per_zone: per_zone(&values, |time, zone| {
+            Some(FragmentationEntry {
+                time,
+                unusable_index: zone.unusable_index(order),
+                fragmentation_index: zone.fragmentation_index(order),
+            })
+        }),
+    })?)
+}
+
+fn get_watermarks(values: Vec<Fragmentation>) -> Result<String> {
+    Ok(serde_json::to_string(&per_zone(&values, |time, zone| {
+        Some(WatermarksEntry {
+            time,
+            watermarks: zone.watermarks.clone()?,
+        })
+    }))?)
+}
+
+fn get_migrate_types(values: Vec<Fragmentation>) -> Result<String> {
+    Ok(serde_json::to_string(&per_zone(&values, |time, zone| {
+        if zone.migrate_types.is_empty() {
+            return None;
+        }
+        Some(MigrateTypesEntry {
+            time,
+            free: zone
+                .migrate_types
+                .iter()
+                .map(|(t, blocks)| (t.clone(), free_pages(blocks)))
+                .collect(),
+        })
+    }))?)
+}
+
+fn get_keys(value: &Fragmentation) -> Result<String> {
+    let mut keys = vec!["free_pages", "fragmentation"];
+    if value.zones.iter().any(|z| z.watermarks.is_some()) {
+        keys.push("watermarks");
+    }
+    if value.zones.iter().any(|z| !z.migrate_types.is_empty()) {
+        keys.push("migrate_types");
+    }
+    Ok(serde_json::to_string(&keys)?)
+}
+
+impl GetData for Fragmentation {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::FragmentationRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        Ok(ProcessedData::Fragmentation(process_raw(raw_value)?))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        _metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Fragmentation(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => get_keys(&values[0]),
+            "values" => {
+                let (_, key) = &param[2];
+                match key.as_str() {
+                    "free_pages" => get_free_pages(values),
+                    "fragmentation" => get_fragmentation(values),
+                    "watermarks" => get_watermarks(values),
+                    "migrate_types" => get_migrate_types(values),
+                    _ => Err(PDError::VisualizerUnsupportedAPI.into()),
+                }
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_fragmentation() {
+    let fragmentation_raw = FragmentationRaw::new();
+    let file_name = FRAGMENTATION_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::FragmentationRaw(fragmentation_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let fragmentation = Fragmentation::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Fragmentation(fragmentation),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/fragmentation.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        filter_pagetypeinfo, filter_zoneinfo, process_raw, Fragmentation, FragmentationRaw,
+        Watermarks, ZoneFree,
+    };
+    use crate::data::test_utils::{get_data, get_keys, process_samples, sample_time};
+    use crate::data::{CollectData, CollectorParams, Data};
+    use crate::utils::DataMetrics;
+
+    const BUDDYINFO: &str = "\
+Node 0, zone      DMA      0      0      0      0      0      0      0      0      1      1      3
+Node 0, zone    DMA32  14206   7264   5109   1641    381     94     33      7      4      1      0
+Node 0, zone  Movable      0      0      0      0      0      0      0      0      0      0      0
+";
+
+    const PAGETYPEINFO: &str = "\
+Page block order: 9
+Pages per block:  512
+
+Free pages count per migrate type at order       0      1      2      3      4      5      6      7      8      9     10
+Node    0, zone    DMA32, type    Unmovable    234    147    111    102     68     32      6      5      0      0      0
+Node    0, zone    DMA32, type      Movable  13739   7017   4910   1477    279     58     27      2      4      1      0
+
+Number of blocks type     Unmovable      Movable  Reclaimable   HighAtomic      Isolate
+Node 0, zone      DMA            3            5            0            0            0
+";
+
+    const ZONEINFO: &str = "\
+Node 0, zone    DMA32
+  per-node stats
+      nr_free_pages 97997
+  pages free     75946
+        boost    0
+        min      8498
+        low      10622
+        high     12746
+        spanned  1044480
+        managed  774334
+  pagesets
+    cpu: 0
+              count: 26
+              high:  378
+Node 0, zone  Movable
+  pages free     0
+        min      32
+";
+
+    fn raw(seconds: i64) -> FragmentationRaw {
+        FragmentationRaw {
+            time: sample_time(seconds),
+            huge_page_order: 9,
+            buddyinfo: BUDDYINFO.to_string(),
+            pagetypeinfo: filter_pagetypeinfo(PAGETYPEINFO),
+            zoneinfo: filter_zoneinfo(ZONEINFO),
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        assert_eq!(filter_pagetypeinfo(PAGETYPEINFO).lines().count(), 2);
+        assert_eq!(
+            filter_zoneinfo(ZONEINFO),
+            "Node 0, zone    DMA32\npages free     75946\nmin      8498\nlow      10622\n\
+             high     12746\nmanaged  774334\nNode 0, zone  Movable\npages free     0\nmin      32\n"
+        );
+    }
+
+    #[test]
+    fn test_process_raw() {
+        let fragmentation = process_raw(&raw(0)).unwrap();
+        assert_eq!(fragmentation.zones.len(), 3);
+        let dma32 = &fragmentation.zones[1];
+        assert_eq!(dma32.zone, "DMA32");
+        assert_eq!(dma32.free_blocks.len(), 11);
+        assert_eq!(dma32.free_blocks[0], 14206);
+        assert_eq!(dma32.migrate_types["Movable"][9], 1);
+        assert_eq!(
+            dma32.watermarks,
+            Some(Watermarks {
+                free: 75946,
+                min: 8498,
+                low: 10622,
+                high: 12746,
+                managed: 774334,
+            })
+        );
+        assert!(fragmentation.zones[0].watermarks.is_none());
+        assert!(fragmentation.zones[0].migrate_types.is_empty());
+    }
+
+    #[test]
+    fn test_fragmentation_index() {
+        /* 8 free pages as order 0 blocks and one order 2 block */
+        let zone = ZoneFree {
+            free_blocks: vec![4, 0, 1],
+            ..Default::default()
+        };
+        assert_eq!(zone.free_pages(), 8);
+        assert_eq!(zone.unusable_index(0), 0.0);
+        assert_eq!(zone.unusable_index(2), 0.5);
+        assert_eq!(zone.unusable_index(3), 1.0);
+        assert_eq!(zone.fragmentation_index(2), -1.0);
+        /* 1 - (1 + 8 / 8) / 5 */
+        assert!((zone.fragmentation_index(3) - 0.6).abs() < 1e-9);
+
+        let empty = ZoneFree::default();
+        assert_eq!(empty.unusable_index(9), 0.0);
+        assert_eq!(empty.fragmentation_index(9), 0.0);
+    }
+
+    #[test]
+    fn test_get_values() {
+        let buffer = process_samples(
+            Fragmentation::new,
+            (0..2)
+                .map(|seconds| Data::FragmentationRaw(raw(seconds)))
+                .collect(),
+        );
+        assert_eq!(
+            get_keys(Fragmentation::new, &buffer),
+            vec!["free_pages", "fragmentation", "watermarks", "migrate_types"]
+        );
+
+        let mut metrics = DataMetrics::new(String::new());
+        let json = get_data(
+            Fragmentation::new,
+            &buffer,
+            "get=values&key=free_pages",
+            &mut metrics,
+        )
+        .unwrap();
+        let heatmaps: serde_json::Value = serde_json::from_str(&json).unwrap();
+        /* The empty Movable zone is left out */
+        assert_eq!(heatmaps.as_array().unwrap().len(), 2);
+        assert_eq!(heatmaps[0]["percent"][10][1], 3.0 * 1024.0 * 100.0 / 3840.0);
+        assert_eq!(heatmaps[1]["blocks"][0][0], 14206);
+
+        let json = get_data(
+            Fragmentation::new,
+            &buffer,
+            "get=values&key=watermarks",
+            &mut metrics,
+        )
+        .unwrap();
+        let watermarks: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(watermarks[0]["data"].as_array().unwrap().len(), 0);
+        assert_eq!(watermarks[1]["data"][1]["watermarks"]["min"], 8498);
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let mut fragmentation = FragmentationRaw::new();
+        let params = CollectorParams::new();
+
+        fragmentation.prepare_data_collector(&params).unwrap();
+        fragmentation.collect_data(&params).unwrap();
+        assert!(!fragmentation.buddyinfo.is_empty());
+    }
+}
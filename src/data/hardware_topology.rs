@@ -0,0 +1,410 @@
+extern crate ctor;
+
+use crate::data::cpufreq::{numbered_entries, CPU_SYSFS_DIR};
+use crate::data::numa::NUMA_NODE_DIR;
+use crate::data::utils::get_cpu_info;
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::{DataMetrics, ValueType};
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+pub static HARDWARE_TOPOLOGY_FILE_NAME: &str = "hardware_topology";
+
+/// The caches of one level and type, e.g. the L1 data caches.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CacheInfo {
+    pub level: u64,
+    /// "Data", "Instruction" or "Unified".
+    pub cache_type: String,
+    /// As in sysfs, e.g. "48K".
+    pub size: String,
+    pub instances: u64,
+    pub cpus_per_instance: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NodeDistances {
+    pub node: u64,
+    /// The distance to each node, in node order.
+    pub distances: Vec<u64>,
+}
+
+/// The CPU, cache and NUMA layout of the system.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HardwareTopology {
+    pub time: TimeEnum,
+    pub model_name: String,
+    pub online_cpus: String,
+    pub sockets: u64,
+    pub cores_per_socket: u64,
+    pub threads_per_core: u64,
+    /// From /sys/devices/system/cpu/smt/control: "on", "off", "forceoff" or "notsupported".
+    pub smt_control: String,
+    pub caches: Vec<CacheInfo>,
+    pub numa_distances: Vec<NodeDistances>,
+    pub microcode: String,
+    pub flags: Vec<String>,
+    pub isolated_cpus: String,
+    pub nohz_full_cpus: String,
+}
+
+impl HardwareTopology {
+    fn new() -> Self {
+        HardwareTopology {
+            time: TimeEnum::DateTime(Utc::now()),
+            model_name: String::new(),
+            online_cpus: String::new(),
+            sockets: 0,
+            cores_per_socket: 0,
+            threads_per_core: 0,
+            smt_control: String::new(),
+            caches: Vec::new(),
+            numa_distances: Vec::new(),
+            microcode: String::new(),
+            flags: Vec::new(),
+            isolated_cpus: String::new(),
+            nohz_full_cpus: String::new(),
+        }
+    }
+}
+
+fn read_trimmed(path: &Path) -> String {
+    fs::read_to_string(path)
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// The number of CPUs in a CPU list such as "0-3,8-11".
+fn cpulist_len(list: &str) -> u64 {
+    list.split(',')
+        .filter(|r| !r.is_empty())
+        .map(|r| match r.split_once('-') {
+            Some((start, end)) => match (start.parse::<u64>(), end.parse::<u64>()) {
+                (Ok(s), Ok(e)) if e >= s => e - s + 1,
+                _ => 0,
+            },
+            None => 1,
+        })
+        .sum()
+}
+
+/// Sockets, cores and caches from the topology and cache directories of each online CPU.
+fn read_cpus(topology: &mut HardwareTopology, root: &Path) {
+    let mut packages = BTreeSet::new();
+    let mut cores = BTreeSet::new();
+    let mut threads = 0;
+    /* The CPUs sharing each cache, per level and type */
+    let mut caches: BTreeMap<(u64, String), (String, BTreeSet<String>)> = BTreeMap::new();
+    for cpu in numbered_entries(root, "cpu") {
+        let cpu_dir = root.join(format!("cpu{}", cpu));
+        let package = read_trimmed(&cpu_dir.join("topology/physical_package_id"));
+        /* Offline CPUs have no topology */
+        if package.is_empty() {
+            continue;
+        }
+        let core = read_trimmed(&cpu_dir.join("topology/core_id"));
+        packages.insert(package.clone());
+        cores.insert((package, core));
+        threads += 1;
+        for index in numbered_entries(&cpu_dir.join("cache"), "index") {
+            let index_dir = cpu_dir.join(format!("cache/index{}", index));
+            let level = match read_trimmed(&index_dir.join("level")).parse::<u64>() {
+                Ok(l) => l,
+                Err(_) => continue,
+            };
+            let cache = caches
+                .entry((level, read_trimmed(&index_dir.join("type"))))
+                .or_insert((read_trimmed(&index_dir.join("size")), BTreeSet::new()));
+            cache
+                .1
+                .insert(read_trimmed(&index_dir.join("shared_cpu_list")));
+        }
+    }
+    topology.sockets = packages.len() as u64;
+    if !packages.is_empty() {
+        topology.cores_per_socket = cores.len() as u64 / packages.len() as u64;
+    }
+    if !cores.is_empty() {
+        topology.threads_per_core = threads / cores.len() as u64;
+    }
+    topology.caches = caches
+        .into_iter()
+        .map(|((level, cache_type), (size, shared))| CacheInfo {
+            level,
+            cache_type,
+            size,
+            instances: shared.len() as u64,
+            cpus_per_instance: shared.iter().map(|s| cpulist_len(s)).max().unwrap_or(0),
+        })
+        .collect();
+    topology.online_cpus = read_trimmed(&root.join("online"));
+    topology.smt_control = read_trimmed(&root.join("smt/control"));
+    topology.isolated_cpus = read_trimmed(&root.join("isolated"));
+    topology.nohz_full_cpus = read_trimmed(&root.join("nohz_full"));
+}
+
+fn read_numa_distances(root: &Path) -> Vec<NodeDistances> {
+    numbered_entries(root, "node")
+        .into_iter()
+        .map(|node| NodeDistances {
+            node,
+            distances: read_trimmed(&root.join(format!("node{}/distance", node)))
+                .split_whitespace()
+                .filter_map(|d| d.parse().ok())
+                .collect(),
+        })
+        .collect()
+}
+
+impl CollectData for HardwareTopology {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        read_cpus(self, Path::new(CPU_SYSFS_DIR));
+        self.numa_distances = read_numa_distances(Path::new(NUMA_NODE_DIR));
+        if let Ok(cpu_info) = get_cpu_info() {
+            self.model_name = cpu_info.model_name;
+            self.microcode = cpu_info.microcode;
+            self.flags = cpu_info.flags;
+        }
+        trace!("{:#?}", self);
+        Ok(())
+    }
+}
+
+/// The topology as display names and values, which are compared across runs.
+fn get_entries(value: &HardwareTopology) -> BTreeMap<String, String> {
+    let mut entries = BTreeMap::new();
+    let mut insert = |key: &str, value: String| {
+        if !value.is_empty() {
+            entries.insert(key.to_string(), value);
+        }
+    };
+    insert("CPU model", value.model_name.clone());
+    insert("Online CPUs", value.online_cpus.clone());
+    insert("Sockets", value.sockets.to_string());
+    insert("Cores per socket", value.cores_per_socket.to_string());
+    insert("Threads per core", value.threads_per_core.to_string());
+    insert("SMT control", value.smt_control.clone());
+    for cache in &value.caches {
+        let name = match cache.cache_type.as_str() {
+            "Data" => format!("L{}d cache", cache.level),
+            "Instruction" => format!("L{}i cache", cache.level),
+            _ => format!("L{} cache", cache.level),
+        };
+        insert(
+            &name,
+            format!(
+                "{} x {}, shared by {} CPU(s)",
+                cache.size, cache.instances, cache.cpus_per_instance
+            ),
+        );
+    }
+    for node in &value.numa_distances {
+        insert(
+            &format!("NUMA node{} distances", node.node),
+            node.distances
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<String>>()
+                .join(" "),
+        );
+    }
+    insert("Microcode", value.microcode.clone());
+    insert("CPU flags", value.flags.join(" "));
+    insert("Isolated CPUs", value.isolated_cpus.clone());
+    insert("nohz_full CPUs", value.nohz_full_cpus.clone());
+    entries
+}
+
+fn get_values(value: &HardwareTopology, metrics: &mut DataMetrics) -> Result<String> {
+    let entries = get_entries(value);
+    let mut my_metrics = HashMap::new();
+    my_metrics.insert(
+        "topology".to_string(),
+        ValueType::String(serde_json::to_string(&entries)?),
+    );
+    metrics
+        .values
+        .insert(HARDWARE_TOPOLOGY_FILE_NAME.to_string(), my_metrics);
+    Ok(serde_json::to_string(&entries)?)
+}
+
+impl GetData for HardwareTopology {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::HardwareTopology(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        Ok(ProcessedData::HardwareTopology((*raw_value).clone()))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::HardwareTopology(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "values" => get_values(&values[0], metrics),
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_hardware_topology() {
+    let hardware_topology = HardwareTopology::new();
+    let file_name = HARDWARE_TOPOLOGY_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::HardwareTopology(hardware_topology.clone()),
+        file_name.clone(),
+        true,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let dv = DataVisualizer::new(
+        ProcessedData::HardwareTopology(hardware_topology),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/hardware_topology.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cpulist_len, get_entries, read_cpus, read_numa_distances, CacheInfo, HardwareTopology,
+    };
+    use crate::data::{CollectData, CollectorParams};
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, file: &str, value: &str) {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("{}\n", value)).unwrap();
+    }
+
+    #[test]
+    fn test_cpulist_len() {
+        assert_eq!(cpulist_len("0-3,8-11"), 8);
+        assert_eq!(cpulist_len("0,2"), 2);
+        assert_eq!(cpulist_len(""), 0);
+    }
+
+    #[test]
+    fn test_read_cpus() {
+        let root = TempDir::with_prefix("aperf_hardware_topology").unwrap();
+        /* 2 sockets of 2 cores with 2 threads each, and an offline CPU 8 */
+        for cpu in 0..8 {
+            let package = cpu / 4;
+            let core = (cpu % 4) / 2;
+            let dir = format!("cpu{}", cpu);
+            write(
+                root.path(),
+                &format!("{}/topology/physical_package_id", dir),
+                &package.to_string(),
+            );
+            write(
+                root.path(),
+                &format!("{}/topology/core_id", dir),
+                &core.to_string(),
+            );
+            let l1 = format!("{}-{}", cpu - cpu % 2, cpu - cpu % 2 + 1);
+            let l3 = format!("{}-{}", package * 4, package * 4 + 3);
+            for (index, level, cache_type, size, shared) in [
+                (0, "1", "Data", "48K", &l1),
+                (1, "1", "Instruction", "32K", &l1),
+                (2, "3", "Unified", "32768K", &l3),
+            ] {
+                let index_dir = format!("{}/cache/index{}", dir, index);
+                write(root.path(), &format!("{}/level", index_dir), level);
+                write(root.path(), &format!("{}/type", index_dir), cache_type);
+                write(root.path(), &format!("{}/size", index_dir), size);
+                write(
+                    root.path(),
+                    &format!("{}/shared_cpu_list", index_dir),
+                    shared,
+                );
+            }
+        }
+        fs::create_dir_all(root.path().join("cpu8")).unwrap();
+        write(root.path(), "online", "0-7");
+        write(root.path(), "smt/control", "on");
+        write(root.path(), "isolated", "");
+
+        let mut topology = HardwareTopology::new();
+        read_cpus(&mut topology, root.path());
+        assert_eq!(topology.sockets, 2);
+        assert_eq!(topology.cores_per_socket, 2);
+        assert_eq!(topology.threads_per_core, 2);
+        assert_eq!(topology.online_cpus, "0-7");
+        assert_eq!(topology.smt_control, "on");
+        assert_eq!(topology.nohz_full_cpus, "");
+        assert_eq!(topology.caches.len(), 3);
+        assert_eq!(
+            topology.caches[2],
+            CacheInfo {
+                level: 3,
+                cache_type: "Unified".to_string(),
+                size: "32768K".to_string(),
+                instances: 2,
+                cpus_per_instance: 4,
+            }
+        );
+
+        write(root.path(), "node0/distance", "10 32");
+        write(root.path(), "node1/distance", "32 10");
+        topology.numa_distances = read_numa_distances(root.path());
+        assert_eq!(topology.numa_distances[1].distances, vec![32, 10]);
+
+        let entries = get_entries(&topology);
+        assert_eq!(entries["L1d cache"], "48K x 4, shared by 2 CPU(s)");
+        assert_eq!(entries["L3 cache"], "32768K x 2, shared by 4 CPU(s)");
+        assert_eq!(entries["NUMA node0 distances"], "10 32");
+        assert!(!entries.contains_key("Isolated CPUs"));
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let mut topology = HardwareTopology::new();
+        let params = CollectorParams::new();
+
+        topology.collect_data(&params).unwrap();
+        assert!(topology.sockets > 0);
+        assert!(!topology.caches.is_empty());
+    }
+}
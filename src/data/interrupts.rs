@@ -1,6 +1,7 @@
 extern crate ctor;
 
 use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::overhead::sample_every;
 use crate::utils::DataMetrics;
 use crate::visualizer::{DataVisualizer, GetData};
 use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
@@ -137,7 +138,7 @@ fn process_collected_raw_data(buffer: Data) -> Result<ProcessedData> {
     let mut interrupt_data = InterruptData::new();
     let raw_value = match buffer {
         Data::InterruptDataRaw(ref value) => value,
-        _ => panic!("Invalid Data type in raw file"),
+        _ => return Err(PDError::InvalidRawDataType.into()),
     };
     let mut reader = BufReader::new(raw_value.data.as_bytes());
 
@@ -149,8 +150,13 @@ fn process_collected_raw_data(buffer: Data) -> Result<ProcessedData> {
     /* Create a vec to hold CPU # to be use later */
     let cpus_nr: Vec<u64> = cpus
         .into_iter()
-        .map(|string| string[3..].parse::<u64>().unwrap())
-        .collect();
+        .map(|string| {
+            string
+                .strip_prefix("CPU")
+                .and_then(|nr| nr.parse::<u64>().ok())
+                .ok_or(PDError::CollectorLineNameError)
+        })
+        .collect::<Result<Vec<u64>, PDError>>()?;
     let cpu_count = cpus_nr.len() as u64;
 
     let mut interrupt_line_datas = Vec::new();
@@ -164,22 +170,25 @@ fn process_collected_raw_data(buffer: Data) -> Result<ProcessedData> {
             .filter(|s| !s.is_empty());
 
         /* Get type of interrupt line */
-        let intr_line = get_intr_line(split.next().unwrap())?;
+        let intr_line = get_intr_line(split.next().ok_or(PDError::CollectorLineNameError)?)?;
         interrupt_line_data.set_interrupt_line(intr_line.clone());
 
         match intr_line {
             InterruptLine::InterruptStr(ref value) => {
                 /* Interrupts of type MIS/ERR are not per cpu */
                 if value.to_uppercase() == "MIS" || value.to_uppercase() == "ERR" {
-                    let interrupt_cpu_data = get_interrupt_cpu_data(split.next().unwrap(), 0)?;
+                    let interrupt_cpu_data = get_interrupt_cpu_data(
+                        split.next().ok_or(PDError::CollectorLineValueError)?,
+                        0,
+                    )?;
                     interrupt_line_data.push_to_per_cpu(interrupt_cpu_data);
                     interrupt_line_data.set_type(value.to_string());
                 } else {
                     /* Other named INTRs are per-cpu */
                     for cpu in 0..cpu_count {
                         let interrupt_cpu_data = get_interrupt_cpu_data(
-                            split.next().unwrap(),
-                            *cpus_nr.get(cpu as usize).unwrap(),
+                            split.next().ok_or(PDError::CollectorLineValueError)?,
+                            cpus_nr[cpu as usize],
                         )?;
                         interrupt_line_data.push_to_per_cpu(interrupt_cpu_data);
                     }
@@ -205,14 +214,14 @@ fn process_collected_raw_data(buffer: Data) -> Result<ProcessedData> {
                 /* Numbered interrupt lines are per-cpu */
                 for cpu in 0..cpu_count {
                     let interrupt_cpu_data = get_interrupt_cpu_data(
-                        split.next().unwrap(),
-                        *cpus_nr.get(cpu as usize).unwrap(),
+                        split.next().ok_or(PDError::CollectorLineValueError)?,
+                        cpus_nr[cpu as usize],
                     )?;
                     interrupt_line_data.push_to_per_cpu(interrupt_cpu_data);
                 }
                 /* They also contain additional information about type, edge and device name */
-                let intr_type = split.next().unwrap();
-                let device_name = split.last().unwrap();
+                let intr_type = split.next().unwrap_or_default();
+                let device_name = split.last().unwrap_or_default();
                 interrupt_line_data.set_type(intr_type.to_string());
                 interrupt_line_data.set_device(device_name.to_string());
             }
@@ -232,45 +241,65 @@ fn get_lines(value: InterruptData) -> Result<String> {
         let line_name = match line_data.interrupt_line {
             InterruptLine::InterruptStr(v) => v,
             InterruptLine::InterruptNr(v) => v.to_string(),
-            _ => panic!("Line Nr cannot be None"),
+            InterruptLine::None => return Err(PDError::CollectorLineNameError.into()),
         };
         lines.push(line_name);
     }
     Ok(serde_json::to_string(&lines)?)
 }
 
-fn get_key_data(values: Vec<InterruptData>, key: String) -> Vec<InterruptLineData> {
+fn get_key_data(values: Vec<InterruptData>, key: String) -> Result<Vec<InterruptLineData>> {
     let mut key_values = Vec::new();
     for value in values {
         for line_data in value.interrupt_data {
             let line_name = match line_data.interrupt_line.clone() {
                 InterruptLine::InterruptStr(v) => v,
                 InterruptLine::InterruptNr(v) => v.to_string(),
-                _ => panic!("Can't be None"),
+                InterruptLine::None => return Err(PDError::CollectorLineNameError.into()),
             };
             if line_name == key {
                 key_values.push(line_data);
             }
         }
     }
-    key_values
+    Ok(key_values)
+}
+
+/// The increase of a per CPU count since the previous sample. The kernel keeps these counts in
+/// 32 bits, so they wrap around on busy systems.
+fn count_delta(count: u64, prev: u64) -> u64 {
+    if count >= prev {
+        count - prev
+    } else {
+        (count + (1 << 32)).saturating_sub(prev)
+    }
 }
 
-fn get_line_data(values: Vec<InterruptData>, key: String) -> Result<String> {
-    let key_values = get_key_data(values, key);
+/// Counts of each sample of a line since the sample before it, per CPU. Softirqs share the per
+/// CPU layout of interrupt lines and use this too.
+pub(crate) fn get_per_cpu_deltas(
+    key_values: Vec<InterruptLineData>,
+    run: &str,
+    file_name: &str,
+) -> Result<Vec<InterruptLineData>> {
     let mut end_values = Vec::new();
     let mut prev_data_map = HashMap::new();
-    let time_zero = key_values[0].time;
-    for cpu_data in &key_values[0].per_cpu {
+    let first = key_values
+        .first()
+        .ok_or(PDError::VisualizerUnsupportedAPI)?;
+    let time_zero = first.time;
+    for cpu_data in &first.per_cpu {
         prev_data_map.insert(cpu_data.cpu, cpu_data.count);
     }
     for data in key_values {
         let mut end_value = data.clone();
         end_value.set_time(data.time - time_zero);
+        let every = sample_every(run, file_name, data.time);
         for cpu_data in &mut end_value.per_cpu {
-            cpu_data.count -= prev_data_map.get(&cpu_data.cpu).ok_or(
+            let prev = prev_data_map.get(&cpu_data.cpu).ok_or(
                 PDError::VisualizerInterruptLineCPUCountError(format!("{}", cpu_data.cpu)),
             )?;
+            cpu_data.count = count_delta(cpu_data.count, *prev) / every;
         }
         end_values.push(end_value);
         prev_data_map.clear();
@@ -278,6 +307,12 @@ fn get_line_data(values: Vec<InterruptData>, key: String) -> Result<String> {
             prev_data_map.insert(cpu_data.cpu, cpu_data.count);
         }
     }
+    Ok(end_values)
+}
+
+fn get_line_data(values: Vec<InterruptData>, run: &str, key: String) -> Result<String> {
+    let key_values = get_key_data(values, key)?;
+    let end_values = get_per_cpu_deltas(key_values, run, INTERRUPTS_FILE_NAME)?;
     Ok(serde_json::to_string(&end_values)?)
 }
 
@@ -304,13 +339,14 @@ impl GetData for InterruptData {
             }
         }
         let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, run) = &param[0];
         let (_, req_str) = &param[1];
 
         match req_str.as_str() {
             "keys" => get_lines(values[0].clone()),
             "values" => {
                 let (_, key) = &param[2];
-                get_line_data(values.clone(), key.to_string())
+                get_line_data(values.clone(), run, key.to_string())
             }
             _ => panic!("Unsupported API"),
         }
@@ -353,6 +389,7 @@ mod tests {
     use crate::get_file;
     use crate::utils::DataMetrics;
     use crate::visualizer::{DataVisualizer, GetData};
+    use crate::PDError;
 
     #[test]
     fn test_collect_data() {
@@ -363,6 +400,19 @@ mod tests {
         assert!(!id.data.is_empty());
     }
 
+    #[test]
+    fn test_process_truncated_data() {
+        let mut id_raw = InterruptDataRaw::new();
+        id_raw.data = "           CPU0       CPU1\n  0:         12".to_string();
+        let err = InterruptData::new()
+            .process_raw_data(Data::InterruptDataRaw(id_raw))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PDError>(),
+            Some(PDError::CollectorLineValueError)
+        ));
+    }
+
     #[test]
     fn test_get_data_interrupt_line_values() {
         let mut buffer: Vec<Data> = Vec::<Data>::new();
@@ -195,7 +195,7 @@ impl GetData for KernelConfig {
     fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
         let raw_value = match buffer {
             Data::KernelConfig(ref value) => value,
-            _ => panic!("Invalid Data type in raw file"),
+            _ => return Err(PDError::InvalidRawDataType.into()),
         };
         let processed_data = ProcessedData::KernelConfig((*raw_value).clone());
         Ok(processed_data)
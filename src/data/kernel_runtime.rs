@@ -0,0 +1,281 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::DataMetrics;
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub static KERNEL_RUNTIME_FILE_NAME: &str = "kernel_runtime";
+pub static CMDLINE_PATH: &str = "/proc/cmdline";
+pub static MODULES_PATH: &str = "/proc/modules";
+pub static VULNERABILITIES_DIR: &str = "/sys/devices/system/cpu/vulnerabilities";
+
+/// The boot parameters, loaded modules and CPU vulnerability mitigations of the running kernel.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KernelRuntimeData {
+    pub time: TimeEnum,
+    pub cmdline: String,
+    /// Module name to size in bytes.
+    pub modules: BTreeMap<String, u64>,
+    /// Vulnerability name to mitigation status, e.g. "Mitigation: PTI".
+    pub vulnerabilities: BTreeMap<String, String>,
+}
+
+impl KernelRuntimeData {
+    fn new() -> Self {
+        KernelRuntimeData {
+            time: TimeEnum::DateTime(Utc::now()),
+            cmdline: String::new(),
+            modules: BTreeMap::new(),
+            vulnerabilities: BTreeMap::new(),
+        }
+    }
+}
+
+/// The kernel parameters in a command line, up to the "--" which starts the init arguments.
+/// Parameters without a value are "set" and repeated parameters keep every value.
+fn parse_cmdline(cmdline: &str) -> BTreeMap<String, String> {
+    let mut params: BTreeMap<String, String> = BTreeMap::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_quotes = false;
+    for c in cmdline.trim().chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    for word in words {
+        if word == "--" {
+            break;
+        }
+        let (name, value) = match word.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => (word, "set".to_string()),
+        };
+        params
+            .entry(name)
+            .and_modify(|v| {
+                v.push(' ');
+                v.push_str(&value);
+            })
+            .or_insert(value);
+    }
+    params
+}
+
+fn parse_modules(modules: &str) -> BTreeMap<String, u64> {
+    modules
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let size = fields.next()?.parse::<u64>().ok()?;
+            Some((name.to_string(), size))
+        })
+        .collect()
+}
+
+fn read_vulnerabilities(dir: &Path) -> BTreeMap<String, String> {
+    let mut vulnerabilities = BTreeMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return vulnerabilities,
+    };
+    for entry in entries.flatten() {
+        if let Ok(value) = fs::read_to_string(entry.path()) {
+            vulnerabilities.insert(
+                entry.file_name().to_string_lossy().to_string(),
+                value.trim().to_string(),
+            );
+        }
+    }
+    vulnerabilities
+}
+
+impl CollectData for KernelRuntimeData {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.cmdline = fs::read_to_string(CMDLINE_PATH).unwrap_or_default();
+        /* Kernels built without module support have no /proc/modules */
+        self.modules = parse_modules(&fs::read_to_string(MODULES_PATH).unwrap_or_default());
+        self.vulnerabilities = read_vulnerabilities(Path::new(VULNERABILITIES_DIR));
+        trace!("{:#?}", self);
+        Ok(())
+    }
+}
+
+/// All the settings as one map, with the keys prefixed by where they come from.
+fn get_kernel_runtime_data(value: KernelRuntimeData) -> Result<String> {
+    let mut entries = BTreeMap::new();
+    for (name, param) in parse_cmdline(&value.cmdline) {
+        entries.insert(format!("cmdline.{}", name), param);
+    }
+    for (name, size) in value.modules {
+        entries.insert(format!("module.{}", name), format!("{} bytes", size));
+    }
+    for (name, status) in value.vulnerabilities {
+        entries.insert(format!("vulnerability.{}", name), status);
+    }
+    Ok(serde_json::to_string(&entries)?)
+}
+
+impl GetData for KernelRuntimeData {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::KernelRuntimeData(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let processed_data = ProcessedData::KernelRuntimeData((*raw_value).clone());
+        Ok(processed_data)
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        _metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::KernelRuntimeData(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        if param.len() < 2 {
+            return Ok("Not enough parameters".to_string());
+        }
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "values" => get_kernel_runtime_data(values[0].clone()),
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_kernel_runtime() {
+    let kernel_runtime_data = KernelRuntimeData::new();
+    let file_name = KERNEL_RUNTIME_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::KernelRuntimeData(kernel_runtime_data.clone()),
+        file_name.clone(),
+        true,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let dv = DataVisualizer::new(
+        ProcessedData::KernelRuntimeData(kernel_runtime_data),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/kernel_runtime.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cmdline, parse_modules, read_vulnerabilities, KernelRuntimeData};
+    use crate::data::test_utils::{get_data, process_samples};
+    use crate::data::{CollectData, CollectorParams, Data};
+    use crate::utils::DataMetrics;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_cmdline() {
+        let params = parse_cmdline(
+            "BOOT_IMAGE=/vmlinuz ro console=tty0 console=ttyS0 quiet dyndbg=\"file a.c +p\" -- --init-arg\n",
+        );
+        assert_eq!(params.get("BOOT_IMAGE").unwrap(), "/vmlinuz");
+        assert_eq!(params.get("ro").unwrap(), "set");
+        assert_eq!(params.get("console").unwrap(), "tty0 ttyS0");
+        assert_eq!(params.get("dyndbg").unwrap(), "file a.c +p");
+        assert!(!params.contains_key("--init-arg"));
+        assert_eq!(params.len(), 5);
+    }
+
+    #[test]
+    fn test_parse_modules() {
+        let modules = parse_modules(
+            "nvme 57344 2 - Live 0x0000000000000000\n\
+             ena 151552 0 - Live 0x0000000000000000\n\
+             bad_line\n",
+        );
+        assert_eq!(modules.len(), 2);
+        assert_eq!(*modules.get("nvme").unwrap(), 57344);
+        assert_eq!(*modules.get("ena").unwrap(), 151552);
+    }
+
+    #[test]
+    fn test_read_vulnerabilities() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("meltdown"), "Not affected\n").unwrap();
+        fs::write(dir.path().join("spectre_v2"), "Mitigation: Retpolines\n").unwrap();
+        let vulnerabilities = read_vulnerabilities(dir.path());
+        assert_eq!(vulnerabilities.get("meltdown").unwrap(), "Not affected");
+        assert_eq!(
+            vulnerabilities.get("spectre_v2").unwrap(),
+            "Mitigation: Retpolines"
+        );
+        assert!(read_vulnerabilities(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_get_values() {
+        let mut kernel_runtime = KernelRuntimeData::new();
+        let params = CollectorParams::new();
+
+        kernel_runtime.collect_data(&params).unwrap();
+        kernel_runtime.cmdline = "quiet mitigations=off".to_string();
+        kernel_runtime.modules.insert("nvme".to_string(), 57344);
+        let buffer = process_samples(
+            KernelRuntimeData::new,
+            vec![Data::KernelRuntimeData(kernel_runtime)],
+        );
+        let json = get_data(
+            KernelRuntimeData::new,
+            &buffer,
+            "get=values",
+            &mut DataMetrics::new(String::new()),
+        )
+        .unwrap();
+        let values: BTreeMap<String, String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(values.get("cmdline.quiet").unwrap(), "set");
+        assert_eq!(values.get("cmdline.mitigations").unwrap(), "off");
+        assert_eq!(values.get("module.nvme").unwrap(), "57344 bytes");
+    }
+}
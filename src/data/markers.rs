@@ -0,0 +1,208 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::DataMetrics;
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::{info, trace};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+pub static MARKERS_FILE_NAME: &str = "aperf_markers";
+
+lazy_static! {
+    /// Markers received since the last time they were written out.
+    static ref PENDING_MARKERS: Mutex<Vec<Marker>> = Mutex::new(Vec::new());
+}
+
+/// A labelled point in time during a recording, such as one added with `aperf mark`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub time: TimeEnum,
+    pub label: String,
+}
+
+/// Queue a marker for the current time. It is written with the next sample of the markers
+/// collector.
+pub fn add_marker(label: &str) {
+    info!("Marker: {}", label);
+    PENDING_MARKERS.lock().unwrap().push(Marker {
+        time: TimeEnum::DateTime(Utc::now()),
+        label: label.to_string(),
+    });
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MarkersRaw {
+    pub time: TimeEnum,
+    pub markers: Vec<Marker>,
+}
+
+impl MarkersRaw {
+    fn new() -> Self {
+        MarkersRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            markers: Vec::new(),
+        }
+    }
+}
+
+impl CollectData for MarkersRaw {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.markers = std::mem::take(&mut *PENDING_MARKERS.lock().unwrap());
+        trace!("{:#?}", self.markers);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Markers {
+    pub time: TimeEnum,
+    pub markers: Vec<Marker>,
+}
+
+impl Markers {
+    fn new() -> Self {
+        Markers {
+            time: TimeEnum::DateTime(Utc::now()),
+            markers: Vec::new(),
+        }
+    }
+}
+
+impl GetData for Markers {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::MarkersRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let markers = Markers {
+            time: raw_value.time,
+            markers: raw_value.markers.clone(),
+        };
+        Ok(ProcessedData::Markers(markers))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        _query: String,
+        _metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Markers(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        /* Markers received before the first sample are shown at its time */
+        let time_zero = values[0].time;
+        let mut end_values = Vec::new();
+        for value in values {
+            for marker in value.markers {
+                let time = match marker.time > time_zero {
+                    true => marker.time - time_zero,
+                    false => TimeEnum::TimeDiff(0),
+                };
+                end_values.push(Marker {
+                    time,
+                    label: marker.label,
+                });
+            }
+        }
+        Ok(serde_json::to_string(&end_values)?)
+    }
+}
+
+#[ctor]
+fn init_markers() {
+    let markers_raw = MarkersRaw::new();
+    let file_name = MARKERS_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::MarkersRaw(markers_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let markers = Markers::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Markers(markers.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/aperf_markers.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_marker, Marker, Markers, MarkersRaw};
+    use crate::data::{CollectData, CollectorParams, Data, TimeEnum};
+    use crate::utils::DataMetrics;
+    use crate::visualizer::GetData;
+    use chrono::prelude::*;
+
+    #[test]
+    fn test_get_markers() {
+        let mut raw = MarkersRaw::new();
+        let params = CollectorParams::new();
+        add_marker("warmup done");
+        raw.collect_data(&params).unwrap();
+        assert_eq!(raw.markers.len(), 1);
+        assert_eq!(raw.markers[0].label, "warmup done");
+        raw.collect_data(&params).unwrap();
+        assert!(raw.markers.is_empty());
+
+        let start = Utc::now();
+        let first = MarkersRaw {
+            time: TimeEnum::DateTime(start),
+            markers: vec![Marker {
+                time: TimeEnum::DateTime(start - chrono::Duration::milliseconds(100)),
+                label: "early".to_string(),
+            }],
+        };
+        let second = MarkersRaw {
+            time: TimeEnum::DateTime(start + chrono::Duration::seconds(2)),
+            markers: vec![Marker {
+                time: TimeEnum::DateTime(start + chrono::Duration::milliseconds(1500)),
+                label: "load step 3".to_string(),
+            }],
+        };
+        let mut markers = Markers::new();
+        let mut processed = Vec::new();
+        for raw in [first, second] {
+            processed.push(markers.process_raw_data(Data::MarkersRaw(raw)).unwrap());
+        }
+        let ret = markers
+            .get_data(
+                processed,
+                String::new(),
+                &mut DataMetrics::new(String::new()),
+            )
+            .unwrap();
+        let values: Vec<Marker> = serde_json::from_str(&ret).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].time, TimeEnum::TimeDiff(0));
+        assert_eq!(values[1].time, TimeEnum::TimeDiff(1500));
+        assert_eq!(values[1].label, "load step 3");
+    }
+}
@@ -233,7 +233,7 @@ impl GetData for MeminfoData {
     fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
         let raw_value = match buffer {
             Data::MeminfoDataRaw(ref value) => value,
-            _ => panic!("Invalid Data type in raw file"),
+            _ => return Err(PDError::InvalidRawDataType.into()),
         };
         let reader = BufReader::new(raw_value.data.as_bytes());
         let meminfo = Meminfo::from_reader(reader)?;
@@ -0,0 +1,291 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::{DataMetrics, ValueType};
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+pub static MOUNTS_FILE_NAME: &str = "mounts";
+
+pub static MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// Mount options which change how writes and access times reach the disk.
+static ATIME_SYNC_OPTIONS: [&str; 7] = [
+    "sync",
+    "dirsync",
+    "noatime",
+    "nodiratime",
+    "relatime",
+    "strictatime",
+    "lazytime",
+];
+
+/// A line of /proc/self/mountinfo.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MountEntry {
+    pub mount_point: String,
+    /// The directory of the filesystem mounted, "/" unless it is a bind mount.
+    pub root: String,
+    pub source: String,
+    pub fs_type: String,
+    pub major_minor: String,
+    /// The /proc/diskstats name of the backing block device, empty if there is none.
+    pub device: String,
+    /// Per mount options.
+    pub options: String,
+    /// Per superblock options.
+    pub super_options: String,
+}
+
+impl MountEntry {
+    /// The atime and sync options of the mount, from both the mount and superblock options.
+    pub fn atime_sync_options(&self) -> Vec<String> {
+        let mut found = Vec::new();
+        for option in self.options.split(',').chain(self.super_options.split(',')) {
+            if ATIME_SYNC_OPTIONS.contains(&option) && !found.contains(&option.to_string()) {
+                found.push(option.to_string());
+            }
+        }
+        found
+    }
+}
+
+/// The mounts of the system at the start of the recording.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Mounts {
+    pub time: TimeEnum,
+    pub mounts: Vec<MountEntry>,
+}
+
+impl Mounts {
+    fn new() -> Self {
+        Mounts {
+            time: TimeEnum::DateTime(Utc::now()),
+            mounts: Vec::new(),
+        }
+    }
+}
+
+/// Undo the octal escaping of spaces, tabs, newlines and backslashes in paths.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or_default();
+            if let Ok(c) = u8::from_str_radix(digits, 8) {
+                out.push(c);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Parse the contents of a mountinfo file, skipping malformed lines.
+pub(crate) fn parse_mountinfo(data: &str) -> Vec<MountEntry> {
+    let mut mounts = Vec::new();
+    for line in data.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let separator = match fields.iter().position(|f| *f == "-") {
+            Some(s) if s >= 6 && fields.len() >= s + 3 => s,
+            _ => continue,
+        };
+        mounts.push(MountEntry {
+            mount_point: unescape(fields[4]),
+            root: unescape(fields[3]),
+            source: unescape(fields[separator + 2]),
+            fs_type: fields[separator + 1].to_string(),
+            major_minor: fields[2].to_string(),
+            device: String::new(),
+            options: fields[5].to_string(),
+            super_options: fields.get(separator + 3).unwrap_or(&"").to_string(),
+        });
+    }
+    mounts
+}
+
+/// Map "major:minor" to the device names used in /proc/diskstats.
+pub(crate) fn diskstats_devices(data: &str) -> HashMap<String, String> {
+    let mut devices = HashMap::new();
+    for line in data.lines() {
+        let mut s = line.split_whitespace();
+        if let (Some(major), Some(minor), Some(name)) = (s.next(), s.next(), s.next()) {
+            devices.insert(format!("{}:{}", major, minor), name.to_string());
+        }
+    }
+    devices
+}
+
+/// Set the backing device of each mount, by device number or else by the /dev path mounted.
+fn link_devices(mounts: &mut [MountEntry], devices: &HashMap<String, String>) {
+    for mount in mounts {
+        if let Some(name) = devices.get(&mount.major_minor) {
+            mount.device = name.clone();
+        } else if let Some(name) = mount.source.strip_prefix("/dev/") {
+            if devices.values().any(|d| d == name) {
+                mount.device = name.to_string();
+            }
+        }
+    }
+}
+
+impl CollectData for Mounts {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.mounts = parse_mountinfo(&fs::read_to_string(MOUNTINFO_PATH).unwrap_or_default());
+        let devices = diskstats_devices(&fs::read_to_string("/proc/diskstats").unwrap_or_default());
+        link_devices(&mut self.mounts, &devices);
+        trace!("{:#?}", self.mounts);
+        Ok(())
+    }
+}
+
+/// Record the atime and sync options of each mount point so that runs can be compared.
+fn get_values(value: &Mounts, metrics: &mut DataMetrics) -> Result<String> {
+    let mut options: BTreeMap<String, String> = BTreeMap::new();
+    for mount in &value.mounts {
+        options.insert(
+            mount.mount_point.clone(),
+            mount.atime_sync_options().join(","),
+        );
+    }
+    let mut my_metrics = HashMap::new();
+    my_metrics.insert(
+        "atime_sync_options".to_string(),
+        ValueType::String(serde_json::to_string(&options)?),
+    );
+    metrics
+        .values
+        .insert(MOUNTS_FILE_NAME.to_string(), my_metrics);
+    Ok(serde_json::to_string(&value.mounts)?)
+}
+
+impl GetData for Mounts {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::Mounts(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        Ok(ProcessedData::Mounts((*raw_value).clone()))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Mounts(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "values" => get_values(&values[0], metrics),
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_mounts() {
+    let mounts = Mounts::new();
+    let file_name = MOUNTS_FILE_NAME.to_string();
+    let dt = DataType::new(Data::Mounts(mounts.clone()), file_name.clone(), true);
+    let js_file_name = file_name.clone() + ".js";
+    let dv = DataVisualizer::new(
+        ProcessedData::Mounts(mounts),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/mounts.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diskstats_devices, link_devices, parse_mountinfo, MountEntry, Mounts};
+    use crate::data::{CollectData, CollectorParams};
+
+    const MOUNTINFO: &str = "\
+23 28 0:22 / /proc rw,relatime - proc proc rw
+28 1 254:0 / / rw,relatime - ext4 /dev/vda rw,discard
+29 28 259:3 / /data\\040dir rw,noatime shared:1 - xfs /dev/nvme1n1 rw,sync,attr2
+30 28 0:45 / /var/lib/docker rw,relatime - btrfs /dev/nvme2n1 rw,space_cache
+31 28 259:3 /export /srv rw,noatime - xfs /dev/nvme1n1 rw
+";
+
+    const DISKSTATS: &str = "\
+ 254       0 vda 100 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+ 259       3 nvme1n1 100 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+ 259       4 nvme2n1 100 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+";
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let mut mounts = parse_mountinfo(MOUNTINFO);
+        assert_eq!(mounts.len(), 5);
+        link_devices(&mut mounts, &diskstats_devices(DISKSTATS));
+
+        assert_eq!(
+            mounts[2],
+            MountEntry {
+                mount_point: "/data dir".to_string(),
+                root: "/".to_string(),
+                source: "/dev/nvme1n1".to_string(),
+                fs_type: "xfs".to_string(),
+                major_minor: "259:3".to_string(),
+                device: "nvme1n1".to_string(),
+                options: "rw,noatime".to_string(),
+                super_options: "rw,sync,attr2".to_string(),
+            }
+        );
+        assert_eq!(mounts[0].device, "");
+        assert_eq!(mounts[1].device, "vda");
+        /* btrfs reports an anonymous device number, the source names the disk */
+        assert_eq!(mounts[3].device, "nvme2n1");
+        assert_eq!(mounts[4].root, "/export");
+        assert_eq!(mounts[2].atime_sync_options(), vec!["noatime", "sync"]);
+        assert_eq!(mounts[1].atime_sync_options(), vec!["relatime"]);
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let mut mounts = Mounts::new();
+        let params = CollectorParams::new();
+
+        mounts.collect_data(&params).unwrap();
+        assert!(mounts.mounts.iter().any(|m| m.mount_point == "/"));
+    }
+}
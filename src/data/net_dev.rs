@@ -0,0 +1,431 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::{add_metrics, DataMetrics, Metric};
+use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+pub static NET_DEV_FILE_NAME: &str = "net_dev";
+
+/// Key of the series summed over all interfaces but the loopback.
+pub static NET_DEV_AGGREGATE: &str = "aggregate";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetDevRaw {
+    pub time: TimeEnum,
+    pub data: String,
+}
+
+impl NetDevRaw {
+    fn new() -> Self {
+        NetDevRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            data: String::new(),
+        }
+    }
+}
+
+impl CollectData for NetDevRaw {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.data = String::new();
+        self.data = std::fs::read_to_string("/proc/net/dev")?;
+        trace!("{:#?}", self.data);
+        Ok(())
+    }
+}
+
+/// The counters of an interface used in the report.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NetDevValues {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errs: u64,
+    pub rx_drop: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errs: u64,
+    pub tx_drop: u64,
+}
+
+impl NetDevValues {
+    /// Counters since `prev`. Counters that went back, e.g. because the interface was recreated
+    /// under the same name, count as 0.
+    fn since(&self, prev: &NetDevValues) -> NetDevValues {
+        let delta = |c: u64, p: u64| c.saturating_sub(p);
+        NetDevValues {
+            rx_bytes: delta(self.rx_bytes, prev.rx_bytes),
+            rx_packets: delta(self.rx_packets, prev.rx_packets),
+            rx_errs: delta(self.rx_errs, prev.rx_errs),
+            rx_drop: delta(self.rx_drop, prev.rx_drop),
+            tx_bytes: delta(self.tx_bytes, prev.tx_bytes),
+            tx_packets: delta(self.tx_packets, prev.tx_packets),
+            tx_errs: delta(self.tx_errs, prev.tx_errs),
+            tx_drop: delta(self.tx_drop, prev.tx_drop),
+        }
+    }
+
+    fn add(&mut self, other: &NetDevValues) {
+        self.rx_bytes += other.rx_bytes;
+        self.rx_packets += other.rx_packets;
+        self.rx_errs += other.rx_errs;
+        self.rx_drop += other.rx_drop;
+        self.tx_bytes += other.tx_bytes;
+        self.tx_packets += other.tx_packets;
+        self.tx_errs += other.tx_errs;
+        self.tx_drop += other.tx_drop;
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetDev {
+    pub time: TimeEnum,
+    pub interfaces: HashMap<String, NetDevValues>,
+}
+
+impl NetDev {
+    fn new() -> Self {
+        NetDev {
+            time: TimeEnum::DateTime(Utc::now()),
+            interfaces: HashMap::new(),
+        }
+    }
+}
+
+/// Rates per second over the interval before `time`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct NetDevEntry {
+    pub time: TimeEnum,
+    pub rx_bytes: f64,
+    pub tx_bytes: f64,
+    pub rx_packets: f64,
+    pub tx_packets: f64,
+    pub drops: f64,
+    pub errors: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndNetDevData {
+    pub data: Vec<NetDevEntry>,
+    pub metadata: GraphMetadata,
+}
+
+/// Parse a line such as "  eth0: 1310 9743 0 0 0 0 0 0 7343 8401 0 0 0 0 0 0" into the name
+/// of the interface and its counters.
+fn parse_line(line: &str) -> Result<(String, NetDevValues)> {
+    let (name, counters) = line
+        .split_once(':')
+        .ok_or(PDError::ProcessorOptionExtractError)?;
+    let counters = counters
+        .split_whitespace()
+        .map(|v| v.parse::<u64>())
+        .collect::<Result<Vec<u64>, _>>()?;
+    if counters.len() < 16 {
+        return Err(PDError::ProcessorOptionExtractError.into());
+    }
+    let values = NetDevValues {
+        rx_bytes: counters[0],
+        rx_packets: counters[1],
+        rx_errs: counters[2],
+        rx_drop: counters[3],
+        tx_bytes: counters[8],
+        tx_packets: counters[9],
+        tx_errs: counters[10],
+        tx_drop: counters[11],
+    };
+    Ok((name.trim().to_string(), values))
+}
+
+/// Counters of `key` since the previous sample, summed over the interfaces for the aggregate.
+/// Only interfaces in both samples are counted.
+fn get_values(value: &NetDev, prev: &NetDev, key: &str) -> Option<NetDevValues> {
+    if key != NET_DEV_AGGREGATE {
+        let curr = value.interfaces.get(key)?;
+        /* An interface that appeared since the last sample is its own baseline, its counters
+         * may have been carried over from a rename */
+        return Some(curr.since(prev.interfaces.get(key).unwrap_or(curr)));
+    }
+    let mut total = NetDevValues::default();
+    for (name, curr) in &value.interfaces {
+        if name == "lo" {
+            continue;
+        }
+        if let Some(p) = prev.interfaces.get(name) {
+            total.add(&curr.since(p));
+        }
+    }
+    Some(total)
+}
+
+fn get_entry(values: Vec<NetDev>, key: String, metrics: &mut DataMetrics) -> Result<String> {
+    let mut end_values = Vec::new();
+    let mut drops = Metric::new(format!("{} drops", key));
+    let mut errors = Metric::new(format!("{} errors", key));
+    let mut metadata = GraphMetadata::new();
+    let time_zero = values[0].time;
+    let mut prev: Option<&NetDev> = None;
+    for value in &values {
+        let time = value.time - time_zero;
+        let mut entry = NetDevEntry {
+            time,
+            rx_bytes: 0.0,
+            tx_bytes: 0.0,
+            rx_packets: 0.0,
+            tx_packets: 0.0,
+            drops: 0.0,
+            errors: 0.0,
+        };
+        if let Some(p) = prev {
+            /* Rates are over the time between samples, which covers throttled collection */
+            let seconds = match (time, p.time - time_zero) {
+                (TimeEnum::TimeDiff(t), TimeEnum::TimeDiff(pt)) => t.saturating_sub(pt) as f64,
+                _ => 0.0,
+            } / 1000.0;
+            /* The interface is not there in this sample, e.g. it was removed or renamed */
+            let counters = match get_values(value, p, &key) {
+                Some(c) => c,
+                None => {
+                    prev = Some(value);
+                    continue;
+                }
+            };
+            if seconds > 0.0 {
+                entry.rx_bytes = counters.rx_bytes as f64 / seconds;
+                entry.tx_bytes = counters.tx_bytes as f64 / seconds;
+                entry.rx_packets = counters.rx_packets as f64 / seconds;
+                entry.tx_packets = counters.tx_packets as f64 / seconds;
+                entry.drops = (counters.rx_drop + counters.tx_drop) as f64 / seconds;
+                entry.errors = (counters.rx_errs + counters.tx_errs) as f64 / seconds;
+            }
+        } else if key != NET_DEV_AGGREGATE && !value.interfaces.contains_key(&key) {
+            prev = Some(value);
+            continue;
+        }
+        metadata.update_limits(GraphLimitType::F64(entry.rx_bytes.max(entry.tx_bytes)));
+        drops.insert_value(entry.drops);
+        errors.insert_value(entry.errors);
+        end_values.push(entry);
+        prev = Some(value);
+    }
+    let net_dev_data = EndNetDevData {
+        data: end_values,
+        metadata,
+    };
+    add_metrics(
+        drops.name.clone(),
+        &mut drops,
+        metrics,
+        NET_DEV_FILE_NAME.to_string(),
+    )?;
+    add_metrics(
+        errors.name.clone(),
+        &mut errors,
+        metrics,
+        NET_DEV_FILE_NAME.to_string(),
+    )?;
+    Ok(serde_json::to_string(&net_dev_data)?)
+}
+
+/// The aggregate followed by every interface seen during the run, including those that came
+/// and went.
+fn get_entries(values: Vec<NetDev>) -> Result<String> {
+    let names: BTreeSet<String> = values
+        .into_iter()
+        .flat_map(|v| v.interfaces.into_keys())
+        .collect();
+    let mut keys = vec![NET_DEV_AGGREGATE.to_string()];
+    keys.extend(names);
+    Ok(serde_json::to_string(&keys)?)
+}
+
+impl GetData for NetDev {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::NetDevRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut net_dev = NetDev::new();
+        /* The first two lines are headers */
+        for line in raw_value.data.lines().skip(2) {
+            let (name, values) = parse_line(line)?;
+            net_dev.interfaces.insert(name, values);
+        }
+        net_dev.time = raw_value.time;
+        Ok(ProcessedData::NetDev(net_dev))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::NetDev(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => get_entries(values),
+            "values" => {
+                let (_, key) = &param[2];
+                get_entry(values, key.to_string(), metrics)
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_net_dev() {
+    let net_dev_raw = NetDevRaw::new();
+    let file_name = NET_DEV_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::NetDevRaw(net_dev_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let net_dev = NetDev::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::NetDev(net_dev.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/net_dev.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, EndNetDevData, NetDev, NetDevRaw, NetDevValues};
+    use crate::data::test_utils::{get_data, get_keys, process_samples, sample_time};
+    use crate::data::{CollectData, CollectorParams, Data, ProcessedData};
+    use crate::utils::DataMetrics;
+    use crate::visualizer::GetData;
+
+    static HEADER: &str = "Inter-|   Receive                                                |  Transmit\n \
+        face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n";
+
+    fn raw_data(seconds: i64, interfaces: &[(&str, u64)]) -> Data {
+        let mut data = HEADER.to_string();
+        for (name, bytes) in interfaces {
+            data.push_str(&format!(
+                "{:>6}: {} 10 0 {} 0 0 0 0 {} 20 1 0 0 0 0 0\n",
+                name,
+                bytes,
+                bytes / 1000,
+                bytes / 2
+            ));
+        }
+        Data::NetDevRaw(NetDevRaw {
+            time: sample_time(seconds),
+            data,
+        })
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let mut net_dev = NetDevRaw::new();
+        let params = CollectorParams::new();
+        net_dev.collect_data(&params).unwrap();
+        let processed = NetDev::new()
+            .process_raw_data(Data::NetDevRaw(net_dev))
+            .unwrap();
+        match processed {
+            ProcessedData::NetDev(value) => assert!(value.interfaces.contains_key("lo")),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let (name, values) = parse_line(
+            "  eth0: 131090515    9743    0    2    0     0          0         0   734368    8401    1    0    0     0       0          0",
+        )
+        .unwrap();
+        assert_eq!(name, "eth0");
+        assert_eq!(
+            values,
+            NetDevValues {
+                rx_bytes: 131090515,
+                rx_packets: 9743,
+                rx_errs: 0,
+                rx_drop: 2,
+                tx_bytes: 734368,
+                tx_packets: 8401,
+                tx_errs: 1,
+                tx_drop: 0,
+            }
+        );
+        assert!(parse_line("eth0 1 2 3").is_err());
+        assert!(parse_line("eth0: 1 2 3").is_err());
+    }
+
+    #[test]
+    fn test_get_values() {
+        let samples: [&[(&str, u64)]; 4] = [
+            &[("lo", 1000), ("eth0", 10000)],
+            &[("lo", 2000), ("eth0", 30000), ("veth1", 5000)],
+            /* eth0 renamed, veth1 recreated */
+            &[("lo", 3000), ("ens5", 40000), ("veth1", 1000)],
+            &[("lo", 4000), ("ens5", 42000), ("veth1", 3000)],
+        ];
+        let buffer = process_samples(
+            NetDev::new,
+            samples
+                .iter()
+                .enumerate()
+                .map(|(seconds, interfaces)| raw_data(seconds as i64 * 2, interfaces))
+                .collect(),
+        );
+        assert_eq!(
+            get_keys(NetDev::new, &buffer),
+            vec!["aggregate", "ens5", "eth0", "lo", "veth1"]
+        );
+
+        let mut metrics = DataMetrics::new(String::new());
+        let rx = |key: &str, metrics: &mut DataMetrics| {
+            let query = format!("get=values&key={}", key);
+            let json = get_data(NetDev::new, &buffer, &query, metrics).unwrap();
+            let data: EndNetDevData = serde_json::from_str(&json).unwrap();
+            data.data.iter().map(|e| e.rx_bytes).collect::<Vec<f64>>()
+        };
+        assert_eq!(rx("eth0", &mut metrics), vec![0.0, 10000.0]);
+        assert_eq!(rx("ens5", &mut metrics), vec![0.0, 1000.0]);
+        assert_eq!(rx("veth1", &mut metrics), vec![0.0, 0.0, 1000.0]);
+        assert_eq!(
+            rx("aggregate", &mut metrics),
+            vec![0.0, 10000.0, 0.0, 2000.0]
+        );
+
+        let net_dev = metrics.values.get("net_dev").unwrap();
+        assert!(net_dev.contains_key("aggregate drops"));
+        assert!(net_dev.contains_key("veth1 errors"));
+    }
+}
@@ -1,6 +1,7 @@
 extern crate ctor;
 
 use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::overhead::sample_every;
 use crate::utils::{add_metrics, DataMetrics, Metric};
 use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
 use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
@@ -74,7 +75,12 @@ struct EndNetData {
     pub metadata: GraphMetadata,
 }
 
-fn get_entry(values: Vec<Netstat>, key: String, metrics: &mut DataMetrics) -> Result<String> {
+fn get_entry(
+    values: Vec<Netstat>,
+    run: &str,
+    key: String,
+    metrics: &mut DataMetrics,
+) -> Result<String> {
     let mut end_values = Vec::new();
     let mut metric = Metric::new(key.clone());
     let mut metadata = GraphMetadata::new();
@@ -95,7 +101,7 @@ fn get_entry(values: Vec<Netstat>, key: String, metrics: &mut DataMetrics) -> Re
 
         let netstat_entry = NetstatEntry {
             time: (current_time - time_zero),
-            value: *curr_value - *prev_value,
+            value: (*curr_value - *prev_value) / sample_every(run, NETSTAT_FILE_NAME, current_time),
         };
         metric.insert_value(netstat_entry.value as f64);
         metadata.update_limits(GraphLimitType::UInt64(netstat_entry.value));
@@ -120,7 +126,7 @@ impl GetData for Netstat {
     fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
         let raw_value = match buffer {
             Data::NetstatRaw(ref value) => value,
-            _ => panic!("Invalid Data type in raw file"),
+            _ => return Err(PDError::InvalidRawDataType.into()),
         };
         let mut netstat = Netstat::new();
         let reader = BufReader::new(raw_value.data.as_bytes());
@@ -128,20 +134,27 @@ impl GetData for Netstat {
         let mut lines = reader.lines();
 
         while let (Some(line1), Some(line2)) = (lines.next(), lines.next()) {
-            let binding = line1.unwrap();
+            let binding = line1?;
             let params: Vec<&str> = binding.split_whitespace().collect();
 
-            let binding = line2.unwrap();
+            let binding = line2?;
             let values: Vec<&str> = binding.split_whitespace().collect();
 
             if params.len() != values.len() {
-                panic!("Parameter count should match value count!")
+                return Err(PDError::ProcessorMalformedData(
+                    NETSTAT_FILE_NAME.to_string(),
+                    format!("{} parameters but {} values", params.len(), values.len()),
+                )
+                .into());
             }
 
             let mut param_itr = params.iter();
             let mut val_itr = values.iter();
 
-            let tag = param_itr.next().unwrap().to_owned();
+            let tag = param_itr
+                .next()
+                .ok_or(PDError::ProcessorOptionExtractError)?
+                .to_owned();
             val_itr.next();
 
             for param in param_itr {
@@ -174,13 +187,14 @@ impl GetData for Netstat {
             }
         }
         let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, run) = &param[0];
         let (_, req_str) = &param[1];
 
         match req_str.as_str() {
             "keys" => get_entries(values[0].clone()),
             "values" => {
                 let (_, key) = &param[2];
-                get_entry(values, key.to_string(), metrics)
+                get_entry(values, run, key.to_string(), metrics)
             }
             _ => panic!("Unsupported API"),
         }
@@ -223,6 +237,7 @@ mod tests {
     use crate::data::{CollectData, CollectorParams, Data, ProcessedData, TimeEnum};
     use crate::utils::DataMetrics;
     use crate::visualizer::GetData;
+    use crate::PDError;
 
     #[test]
     fn test_collect_data() {
@@ -233,6 +248,19 @@ mod tests {
         assert!(!netstat.data.is_empty());
     }
 
+    #[test]
+    fn test_process_truncated_data() {
+        let mut netstat = NetstatRaw::new();
+        netstat.data = "TcpExt: SyncookiesSent SyncookiesRecv\nTcpExt: 0\n".to_string();
+        let err = Netstat::new()
+            .process_raw_data(Data::NetstatRaw(netstat))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PDError>(),
+            Some(PDError::ProcessorMalformedData(..))
+        ));
+    }
+
     #[test]
     fn test_get_entries() {
         let mut buffer: Vec<Data> = Vec::<Data>::new();
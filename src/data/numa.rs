@@ -0,0 +1,482 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::overhead::sample_every;
+use crate::utils::{add_metrics, DataMetrics, Metric};
+use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub static NUMA_FILE_NAME: &str = "numa";
+
+pub static NUMA_NODE_DIR: &str = "/sys/devices/system/node";
+
+/// The files of a NUMA node, read as is.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NumaNodeRaw {
+    pub node: u64,
+    pub cpulist: String,
+    pub meminfo: String,
+    pub numastat: String,
+}
+
+/// Gather the cpulist, meminfo and numastat of each NUMA node.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NumaRaw {
+    pub time: TimeEnum,
+    pub nodes: Vec<NumaNodeRaw>,
+}
+
+impl NumaRaw {
+    fn new() -> Self {
+        NumaRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+/// The nodes under `dir`, in order.
+fn get_nodes(dir: &Path) -> Result<Vec<u64>> {
+    let mut nodes = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(Ok(node)) = name.strip_prefix("node").map(|n| n.parse::<u64>()) {
+            nodes.push(node);
+        }
+    }
+    nodes.sort();
+    Ok(nodes)
+}
+
+fn read_nodes(dir: &Path) -> Result<Vec<NumaNodeRaw>> {
+    let mut nodes = Vec::new();
+    for node in get_nodes(dir)? {
+        let node_dir = dir.join(format!("node{}", node));
+        nodes.push(NumaNodeRaw {
+            node,
+            cpulist: fs::read_to_string(node_dir.join("cpulist"))?,
+            meminfo: fs::read_to_string(node_dir.join("meminfo"))?,
+            numastat: fs::read_to_string(node_dir.join("numastat"))?,
+        });
+    }
+    Ok(nodes)
+}
+
+impl CollectData for NumaRaw {
+    fn prepare_data_collector(&mut self, _params: &CollectorParams) -> Result<()> {
+        /* Kernels built without CONFIG_NUMA have no nodes */
+        match get_nodes(Path::new(NUMA_NODE_DIR)) {
+            Ok(nodes) if !nodes.is_empty() => Ok(()),
+            _ => {
+                warn!("No NUMA nodes in {}", NUMA_NODE_DIR);
+                Err(PDError::CollectorNumaUnavailable.into())
+            }
+        }
+    }
+
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.nodes = read_nodes(Path::new(NUMA_NODE_DIR))?;
+        trace!("{:#?}", self.nodes);
+        Ok(())
+    }
+}
+
+/// The values of a NUMA node used in the report. Memory is in kB.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NumaNode {
+    pub node: u64,
+    pub cpulist: String,
+    pub mem_total: u64,
+    pub mem_free: u64,
+    pub mem_used: u64,
+    pub numa_hit: u64,
+    pub numa_miss: u64,
+    pub numa_foreign: u64,
+    pub interleave_hit: u64,
+    pub local_node: u64,
+    pub other_node: u64,
+}
+
+impl NumaNode {
+    fn from_raw(raw: &NumaNodeRaw) -> Result<Self> {
+        let mut node = NumaNode {
+            node: raw.node,
+            cpulist: raw.cpulist.trim().to_string(),
+            ..Default::default()
+        };
+        /* e.g. "Node 0 MemFree:          594444 kB" */
+        for line in raw.meminfo.lines() {
+            let mut fields = line.split_whitespace().skip(2);
+            let field = match fields.next() {
+                Some("MemTotal:") => &mut node.mem_total,
+                Some("MemFree:") => &mut node.mem_free,
+                Some("MemUsed:") => &mut node.mem_used,
+                _ => continue,
+            };
+            *field = fields
+                .next()
+                .ok_or(PDError::ProcessorOptionExtractError)?
+                .parse()?;
+        }
+        for line in raw.numastat.lines() {
+            let (name, value) = line
+                .split_once(' ')
+                .ok_or(PDError::ProcessorOptionExtractError)?;
+            let field = match name {
+                "numa_hit" => &mut node.numa_hit,
+                "numa_miss" => &mut node.numa_miss,
+                "numa_foreign" => &mut node.numa_foreign,
+                "interleave_hit" => &mut node.interleave_hit,
+                "local_node" => &mut node.local_node,
+                "other_node" => &mut node.other_node,
+                _ => continue,
+            };
+            *field = value.trim().parse()?;
+        }
+        Ok(node)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Numa {
+    pub time: TimeEnum,
+    pub nodes: Vec<NumaNode>,
+}
+
+impl Numa {
+    fn new() -> Self {
+        Numa {
+            time: TimeEnum::DateTime(Utc::now()),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct NumaMemoryEntry {
+    pub time: TimeEnum,
+    pub free: u64,
+    pub used: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct NumaStatEntry {
+    pub time: TimeEnum,
+    pub numa_hit: u64,
+    pub numa_miss: u64,
+    pub numa_foreign: u64,
+    pub interleave_hit: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct NodeData<T> {
+    pub node: u64,
+    pub data: Vec<T>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndNumaData<T> {
+    pub per_node: Vec<NodeData<T>>,
+    pub metadata: GraphMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct NumaTopologyEntry {
+    pub node: u64,
+    pub cpulist: String,
+    /// kB
+    pub mem_total: u64,
+}
+
+/// The nodes of the first sample with an empty series each.
+fn new_node_data<T>(value: &Numa) -> Vec<NodeData<T>> {
+    value
+        .nodes
+        .iter()
+        .map(|n| NodeData {
+            node: n.node,
+            data: Vec::new(),
+        })
+        .collect()
+}
+
+fn get_memory(values: Vec<Numa>) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut metadata = GraphMetadata::new();
+    let mut per_node = new_node_data(&values[0]);
+    for value in &values {
+        for node in &value.nodes {
+            let node_data = match per_node.iter_mut().find(|n| n.node == node.node) {
+                Some(n) => n,
+                None => continue,
+            };
+            metadata.update_limits(GraphLimitType::UInt64(node.mem_total));
+            node_data.data.push(NumaMemoryEntry {
+                time: value.time - time_zero,
+                free: node.mem_free,
+                used: node.mem_used,
+            });
+        }
+    }
+    Ok(serde_json::to_string(&EndNumaData { per_node, metadata })?)
+}
+
+/// numastat counters per interval. The allocations that missed their preferred node, as a
+/// percentage of all allocations, feed the remote allocation finding.
+fn get_numastat(values: Vec<Numa>, run: &str, metrics: &mut DataMetrics) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut metric = Metric::new("numa_miss_percent".to_string());
+    let mut metadata = GraphMetadata::new();
+    let mut per_node = new_node_data(&values[0]);
+    for pair in values.windows(2) {
+        let (prev, value) = (&pair[0], &pair[1]);
+        let every = sample_every(run, NUMA_FILE_NAME, value.time);
+        let (mut hit, mut miss) = (0, 0);
+        for node in &value.nodes {
+            let prev_node = prev.nodes.iter().find(|n| n.node == node.node);
+            let node_data = per_node.iter_mut().find(|n| n.node == node.node);
+            let (prev_node, node_data) = match (prev_node, node_data) {
+                (Some(p), Some(n)) => (p, n),
+                _ => continue,
+            };
+            let entry = NumaStatEntry {
+                time: value.time - time_zero,
+                numa_hit: node.numa_hit.saturating_sub(prev_node.numa_hit) / every,
+                numa_miss: node.numa_miss.saturating_sub(prev_node.numa_miss) / every,
+                numa_foreign: node.numa_foreign.saturating_sub(prev_node.numa_foreign) / every,
+                interleave_hit: node.interleave_hit.saturating_sub(prev_node.interleave_hit)
+                    / every,
+            };
+            hit += entry.numa_hit;
+            miss += entry.numa_miss;
+            metadata.update_limits(GraphLimitType::UInt64(entry.numa_hit.max(entry.numa_miss)));
+            node_data.data.push(entry);
+        }
+        if hit + miss > 0 {
+            metric.insert_value(miss as f64 * 100.0 / (hit + miss) as f64);
+        }
+    }
+    if !metric.values.is_empty() {
+        add_metrics(
+            "numa_miss_percent".to_string(),
+            &mut metric,
+            metrics,
+            NUMA_FILE_NAME.to_string(),
+        )?;
+    }
+    Ok(serde_json::to_string(&EndNumaData { per_node, metadata })?)
+}
+
+fn get_topology(value: &Numa) -> Result<String> {
+    let topology: Vec<NumaTopologyEntry> = value
+        .nodes
+        .iter()
+        .map(|n| NumaTopologyEntry {
+            node: n.node,
+            cpulist: n.cpulist.clone(),
+            mem_total: n.mem_total,
+        })
+        .collect();
+    Ok(serde_json::to_string(&topology)?)
+}
+
+impl GetData for Numa {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::NumaRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut numa = Numa::new();
+        numa.time = raw_value.time;
+        for node in &raw_value.nodes {
+            numa.nodes.push(NumaNode::from_raw(node)?);
+        }
+        Ok(ProcessedData::Numa(numa))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Numa(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, run) = &param[0];
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => Ok(serde_json::to_string(&["topology", "memory", "numastat"])?),
+            "values" => {
+                let (_, key) = &param[2];
+                match key.as_str() {
+                    "topology" => get_topology(&values[0]),
+                    "memory" => get_memory(values),
+                    "numastat" => get_numastat(values, run, metrics),
+                    _ => Err(PDError::VisualizerUnsupportedAPI.into()),
+                }
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_numa() {
+    let numa_raw = NumaRaw::new();
+    let file_name = NUMA_FILE_NAME.to_string();
+    let dt = DataType::new(Data::NumaRaw(numa_raw.clone()), file_name.clone(), false);
+    let js_file_name = file_name.clone() + ".js";
+    let numa = Numa::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Numa(numa.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/numa.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_nodes, EndNumaData, Numa, NumaNode, NumaNodeRaw, NumaRaw, NumaStatEntry,
+        NumaTopologyEntry,
+    };
+    use crate::data::test_utils::{get_data, process_samples, sample_time};
+    use crate::data::Data;
+    use crate::utils::{DataMetrics, ValueType};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn node_raw(node: u64, hit: u64, miss: u64) -> NumaNodeRaw {
+        NumaNodeRaw {
+            node,
+            cpulist: format!("{}-{}\n", node * 4, node * 4 + 3),
+            meminfo: format!(
+                "Node {0} MemTotal:        8000000 kB\nNode {0} MemFree:         6000000 kB\n\
+                 Node {0} MemUsed:         2000000 kB\nNode {0} Active:           100000 kB\n",
+                node
+            ),
+            numastat: format!(
+                "numa_hit {}\nnuma_miss {}\nnuma_foreign 0\ninterleave_hit 10\n\
+                 local_node {}\nother_node 0\n",
+                hit, miss, hit
+            ),
+        }
+    }
+
+    fn raw_data(seconds: i64, nodes: Vec<NumaNodeRaw>) -> Data {
+        Data::NumaRaw(NumaRaw {
+            time: sample_time(seconds),
+            nodes,
+        })
+    }
+
+    #[test]
+    fn test_read_nodes() {
+        let dir = TempDir::with_prefix("aperf_numa").unwrap();
+        for node in [1, 0] {
+            let node_dir = dir.path().join(format!("node{}", node));
+            fs::create_dir(&node_dir).unwrap();
+            let raw = node_raw(node, 0, 0);
+            fs::write(node_dir.join("cpulist"), raw.cpulist).unwrap();
+            fs::write(node_dir.join("meminfo"), raw.meminfo).unwrap();
+            fs::write(node_dir.join("numastat"), raw.numastat).unwrap();
+        }
+        fs::write(dir.path().join("possible"), "0-1\n").unwrap();
+        let nodes = read_nodes(dir.path()).unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].node, 0);
+        assert_eq!(nodes[1].cpulist, "4-7\n");
+    }
+
+    #[test]
+    fn test_from_raw() {
+        let node = NumaNode::from_raw(&node_raw(1, 500, 20)).unwrap();
+        assert_eq!(
+            node,
+            NumaNode {
+                node: 1,
+                cpulist: "4-7".to_string(),
+                mem_total: 8000000,
+                mem_free: 6000000,
+                mem_used: 2000000,
+                numa_hit: 500,
+                numa_miss: 20,
+                numa_foreign: 0,
+                interleave_hit: 10,
+                local_node: 500,
+                other_node: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_values() {
+        let samples = [(0, 0), (1, 100), (2, 100)]
+            .into_iter()
+            .map(|(seconds, miss)| {
+                let nodes = vec![node_raw(0, seconds as u64 * 900, 0), node_raw(1, 0, miss)];
+                raw_data(seconds, nodes)
+            })
+            .collect();
+        let buffer = process_samples(Numa::new, samples);
+        let mut metrics = DataMetrics::new(String::new());
+        let get = |key: &str, metrics: &mut DataMetrics| {
+            get_data(
+                Numa::new,
+                &buffer,
+                &format!("get=values&key={}", key),
+                metrics,
+            )
+            .unwrap()
+        };
+        let topology: Vec<NumaTopologyEntry> =
+            serde_json::from_str(&get("topology", &mut metrics)).unwrap();
+        assert_eq!(topology[1].cpulist, "4-7");
+
+        let numastat: EndNumaData<NumaStatEntry> =
+            serde_json::from_str(&get("numastat", &mut metrics)).unwrap();
+        let misses: Vec<u64> = numastat.per_node[1]
+            .data
+            .iter()
+            .map(|e| e.numa_miss)
+            .collect();
+        assert_eq!(misses, vec![100, 0]);
+        assert_eq!(numastat.per_node[0].data[1].numa_hit, 900);
+        /* 10% of the allocations missed in the 1st interval, none in the 2nd */
+        match metrics.values["numa"]["numa_miss_percent"] {
+            ValueType::Stats(ref stats) => assert!((stats.mean - 5.0).abs() < 1e-9),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -3,7 +3,7 @@ extern crate ctor;
 use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
 use crate::utils::{add_metrics, DataMetrics, Metric};
 use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
-use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use crate::{PDError, PERFORMANCE_DATA, PMU_CONFIG_FILE_NAME, VISUALIZATION_DATA};
 use anyhow::Result;
 use chrono::prelude::*;
 use ctor::ctor;
@@ -210,7 +210,7 @@ impl CollectData for PerfStatRaw {
             }
         }
         /* Write the pmu_config being used to the recorded data */
-        let perf_list_pathbuf = PathBuf::from(&params.data_dir).join("pmu_config.json");
+        let perf_list_pathbuf = PathBuf::from(&params.data_dir).join(PMU_CONFIG_FILE_NAME);
         let f = File::create(&perf_list_pathbuf)?;
         serde_json::to_writer_pretty(f, &perf_list)?;
         for cpu in 0..num_cpus {
@@ -310,7 +310,8 @@ impl CollectData for PerfStatRaw {
             cpu_group.group.reset()?;
             cpu_group.group.enable()?;
         }
-        CPU_CTR_GROUPS.lock().unwrap().append(&mut cpu_groups);
+        /* Replace the groups of an earlier prepare, such as on a daemon SIGHUP */
+        *CPU_CTR_GROUPS.lock().unwrap() = cpu_groups;
         Ok(())
     }
 
@@ -493,7 +494,7 @@ impl GetData for PerfStat {
         let mut perf_stat = PerfStat::new();
         let raw_value = match buffer {
             Data::PerfStatRaw(ref value) => value,
-            _ => panic!("Invalid Data type in raw file"),
+            _ => return Err(PDError::InvalidRawDataType.into()),
         };
         let reader = BufReader::new(raw_value.data.as_bytes());
         for line in reader.lines() {
@@ -0,0 +1,318 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::overhead::sample_every;
+use crate::utils::{add_metrics, DataMetrics, Metric};
+use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub static PRESSURE_FILE_NAME: &str = "pressure";
+
+/// Resources with Pressure Stall Information, each in /proc/pressure/<resource>.
+static PRESSURE_RESOURCES: [&str; 3] = ["cpu", "memory", "io"];
+
+/// Gather the Pressure Stall Information of each resource. Each line of /proc/pressure/<resource>
+/// is kept prefixed with the name of its resource.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PressureRaw {
+    pub time: TimeEnum,
+    pub data: String,
+}
+
+impl PressureRaw {
+    fn new() -> Self {
+        PressureRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            data: String::new(),
+        }
+    }
+}
+
+impl CollectData for PressureRaw {
+    fn prepare_data_collector(&mut self, _params: &CollectorParams) -> Result<()> {
+        /* Kernels before 4.20, or booted with psi=0, have no PSI */
+        if let Err(e) = std::fs::read_to_string("/proc/pressure/cpu") {
+            warn!("Kernel does not expose Pressure Stall Information: {}", e);
+            return Err(PDError::CollectorPressureUnavailable.into());
+        }
+        Ok(())
+    }
+
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.data = String::new();
+        for resource in PRESSURE_RESOURCES {
+            let data = std::fs::read_to_string(format!("/proc/pressure/{}", resource))?;
+            for line in data.lines() {
+                self.data.push_str(&format!("{} {}\n", resource, line));
+            }
+        }
+        trace!("{:#?}", self.data);
+        Ok(())
+    }
+}
+
+/// One line of /proc/pressure/<resource>. The averages are percentages of time stalled, the
+/// total is the time stalled in microseconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PressureValues {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    pub total: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Pressure {
+    pub time: TimeEnum,
+    pub pressure_data: HashMap<String, PressureValues>,
+}
+
+impl Pressure {
+    fn new() -> Self {
+        Pressure {
+            time: TimeEnum::DateTime(Utc::now()),
+            pressure_data: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndPressureData {
+    pub data: Vec<PressureEntry>,
+    pub metadata: GraphMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PressureEntry {
+    pub time: TimeEnum,
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    /// Microseconds stalled in the interval.
+    pub stall: u64,
+}
+
+/// Parse a line such as "cpu some avg10=0.00 avg60=0.00 avg300=0.00 total=0" into its key,
+/// e.g. "cpu_some", and values.
+fn parse_line(line: &str) -> Result<(String, PressureValues)> {
+    let mut split = line.split_whitespace();
+    let resource = split.next().ok_or(PDError::ProcessorOptionExtractError)?;
+    let kind = split.next().ok_or(PDError::ProcessorOptionExtractError)?;
+    let mut values = PressureValues::default();
+    for field in split {
+        let (name, value) = field
+            .split_once('=')
+            .ok_or(PDError::ProcessorOptionExtractError)?;
+        match name {
+            "avg10" => values.avg10 = value.parse()?,
+            "avg60" => values.avg60 = value.parse()?,
+            "avg300" => values.avg300 = value.parse()?,
+            "total" => values.total = value.parse()?,
+            _ => continue,
+        }
+    }
+    Ok((format!("{}_{}", resource, kind), values))
+}
+
+fn get_entry(
+    values: Vec<Pressure>,
+    run: &str,
+    key: String,
+    metrics: &mut DataMetrics,
+) -> Result<String> {
+    let mut end_values = Vec::new();
+    let mut metric = Metric::new(key.clone());
+    /* The share of time stalled, which the PSI findings check */
+    let mut avg10_metric = Metric::new(format!("{} avg10", key));
+    let mut metadata = GraphMetadata::new();
+    let time_zero = values[0].time;
+    let mut prev_total = None;
+    for value in values {
+        let curr = value
+            .pressure_data
+            .get(&key)
+            .ok_or(PDError::VisualizerPressureValueGetError(key.to_string()))?;
+        /* The total is a counter, plot the time stalled in each interval */
+        let stall = curr.total.saturating_sub(prev_total.unwrap_or(curr.total))
+            / sample_every(run, PRESSURE_FILE_NAME, value.time);
+        metadata.update_limits(GraphLimitType::UInt64(stall));
+        metric.insert_value(stall as f64);
+        avg10_metric.insert_value(curr.avg10);
+        end_values.push(PressureEntry {
+            time: (value.time - time_zero),
+            avg10: curr.avg10,
+            avg60: curr.avg60,
+            avg300: curr.avg300,
+            stall,
+        });
+        prev_total = Some(curr.total);
+    }
+    let pressure_data = EndPressureData {
+        data: end_values,
+        metadata,
+    };
+    add_metrics(
+        avg10_metric.name.clone(),
+        &mut avg10_metric,
+        metrics,
+        PRESSURE_FILE_NAME.to_string(),
+    )?;
+    add_metrics(key, &mut metric, metrics, PRESSURE_FILE_NAME.to_string())?;
+    Ok(serde_json::to_string(&pressure_data)?)
+}
+
+fn get_entries(value: Pressure) -> Result<String> {
+    let mut keys: Vec<String> = value.pressure_data.into_keys().collect();
+    keys.sort();
+    Ok(serde_json::to_string(&keys)?)
+}
+
+impl GetData for Pressure {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::PressureRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut pressure = Pressure::new();
+        for line in raw_value.data.lines() {
+            let (key, values) = parse_line(line)?;
+            pressure.pressure_data.insert(key, values);
+        }
+        pressure.time = raw_value.time;
+        Ok(ProcessedData::Pressure(pressure))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Pressure(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, run) = &param[0];
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => get_entries(values[0].clone()),
+            "values" => {
+                let (_, key) = &param[2];
+                get_entry(values, run, key.to_string(), metrics)
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_pressure() {
+    let pressure_raw = PressureRaw::new();
+    let file_name = PRESSURE_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::PressureRaw(pressure_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let pressure = Pressure::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Pressure(pressure.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/pressure.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, EndPressureData, Pressure, PressureRaw, PressureValues};
+    use crate::data::test_utils::{get_data, get_keys, process_samples, sample_time};
+    use crate::data::Data;
+    use crate::utils::DataMetrics;
+
+    fn raw_data(seconds: i64, cpu_total: u64) -> Data {
+        Data::PressureRaw(PressureRaw {
+            time: sample_time(seconds),
+            data: format!(
+                "cpu some avg10=1.32 avg60=2.97 avg300=2.37 total={}\n\
+                 memory some avg10=0.00 avg60=0.00 avg300=0.00 total=0\n\
+                 memory full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n",
+                cpu_total
+            ),
+        })
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let (key, values) =
+            parse_line("io full avg10=0.75 avg60=0.24 avg300=0.09 total=10508774").unwrap();
+        assert_eq!(key, "io_full");
+        assert_eq!(
+            values,
+            PressureValues {
+                avg10: 0.75,
+                avg60: 0.24,
+                avg300: 0.09,
+                total: 10508774,
+            }
+        );
+        assert!(parse_line("cpu").is_err());
+        assert!(parse_line("cpu some avg10").is_err());
+    }
+
+    #[test]
+    fn test_get_values() {
+        let buffer = process_samples(
+            Pressure::new,
+            vec![raw_data(0, 1000), raw_data(1, 251000), raw_data(2, 251000)],
+        );
+        assert_eq!(
+            get_keys(Pressure::new, &buffer),
+            vec!["cpu_some", "memory_full", "memory_some"]
+        );
+
+        let mut metrics = DataMetrics::new(String::new());
+        let json = get_data(
+            Pressure::new,
+            &buffer,
+            "get=values&key=cpu_some",
+            &mut metrics,
+        )
+        .unwrap();
+        let data: EndPressureData = serde_json::from_str(&json).unwrap();
+        let stalls: Vec<u64> = data.data.iter().map(|e| e.stall).collect();
+        assert_eq!(stalls, vec![0, 250000, 0]);
+        assert_eq!(data.data[1].avg10, 1.32);
+        assert_eq!(data.metadata.limits.high, 250000);
+        assert!(metrics.values["pressure"].contains_key("cpu_some"));
+        assert!(metrics.values["pressure"].contains_key("cpu_some avg10"));
+    }
+}
@@ -4,25 +4,53 @@ extern crate lazy_static;
 use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
 use crate::utils::DataMetrics;
 use crate::visualizer::{DataVisualizer, GetData};
-use crate::{PERFORMANCE_DATA, VISUALIZATION_DATA};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
 use anyhow::Result;
 use chrono::prelude::*;
 use ctor::ctor;
 use log::trace;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::{BufRead, BufReader};
+use std::path::Path;
 use std::sync::Mutex;
 
 pub static PROCESS_FILE_NAME: &str = "processes";
 pub static PROC_PID_STAT_USERSPACE_TIME_POS: usize = 11;
 pub static PROC_PID_STAT_KERNELSPACE_TIME_POS: usize = 12;
+pub static PROC_PID_STAT_FLAGS_POS: usize = 6;
+pub static PROC_PID_STAT_MINOR_FAULTS_POS: usize = 7;
+pub static PROC_PID_STAT_MAJOR_FAULTS_POS: usize = 9;
+pub static PROC_PID_STAT_THREADS_POS: usize = 17;
+pub static PROC_PID_STAT_VSZ_POS: usize = 20;
+
+/// PF_KTHREAD in the flags of /proc/<pid>/stat.
+static PF_KTHREAD: u64 = 0x00200000;
+
+pub static PROCESS_RANKINGS: [&str; 10] = [
+    "cpu",
+    "rss",
+    "vsz",
+    "minor_faults",
+    "major_faults",
+    "threads",
+    "voluntary_ctxt",
+    "involuntary_ctxt",
+    "read_bytes",
+    "write_bytes",
+];
+
+/// Number of entries kept when ranking, e.g. the processes using the most CPU time.
+pub const TOP_ENTRIES: usize = 15;
 
 lazy_static! {
     pub static ref TICKS_PER_SECOND: Mutex<u64> = Mutex::new(0);
 }
 
+/// The /proc/<pid>/stat of every process. Each user process stat line is followed by a line of
+/// details from /proc/<pid>/status and /proc/<pid>/io, see `read_details`. The details lines
+/// have no parenthesis, so readers that only know stat lines skip them.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProcessesRaw {
     pub time: TimeEnum,
@@ -46,6 +74,56 @@ impl Default for ProcessesRaw {
     }
 }
 
+/// Whether a stat line is of a kernel thread, which has no memory or IO of its own.
+fn is_kernel_thread(stat: &str) -> bool {
+    let values = match stat.rfind(')') {
+        Some(pos) => stat[pos + 1..].split_whitespace().collect::<Vec<&str>>(),
+        None => return false,
+    };
+    match values
+        .get(PROC_PID_STAT_FLAGS_POS)
+        .map(|f| f.parse::<u64>())
+    {
+        Some(Ok(flags)) => flags & PF_KTHREAD != 0,
+        _ => false,
+    }
+}
+
+/// The details line of a process: "<pid> <VmRSS kB> <voluntary_ctxt_switches>
+/// <nonvoluntary_ctxt_switches> <read_bytes> <write_bytes>". /proc/<pid>/io can only be read
+/// for the processes aperf may trace, its values are "-" otherwise.
+fn read_details(pid_dir: &Path, pid: &str) -> Option<String> {
+    let status = fs::read_to_string(pid_dir.join("status")).ok()?;
+    let mut rss = "0";
+    let (mut voluntary, mut involuntary) = ("-", "-");
+    for line in status.lines() {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value.split_whitespace().next().unwrap_or("-")),
+            None => continue,
+        };
+        match name {
+            "VmRSS" => rss = value,
+            "voluntary_ctxt_switches" => voluntary = value,
+            "nonvoluntary_ctxt_switches" => involuntary = value,
+            _ => continue,
+        }
+    }
+    let (mut read_bytes, mut write_bytes) = ("-".to_string(), "-".to_string());
+    if let Ok(io) = fs::read_to_string(pid_dir.join("io")) {
+        for line in io.lines() {
+            match line.split_once(": ") {
+                Some(("read_bytes", v)) => read_bytes = v.trim().to_string(),
+                Some(("write_bytes", v)) => write_bytes = v.trim().to_string(),
+                _ => continue,
+            }
+        }
+    }
+    Some(format!(
+        "{} {} {} {} {} {}\n",
+        pid, rss, voluntary, involuntary, read_bytes, write_bytes
+    ))
+}
+
 impl CollectData for ProcessesRaw {
     fn prepare_data_collector(&mut self, _params: &CollectorParams) -> Result<()> {
         *TICKS_PER_SECOND.lock().unwrap() = procfs::ticks_per_second()? as u64;
@@ -60,10 +138,17 @@ impl CollectData for ProcessesRaw {
             let entry = entry?;
             let file_name = entry.file_name().to_str().unwrap().to_string();
             if file_name.chars().all(char::is_numeric) {
-                let mut path = entry.path();
-                path.push("stat");
-                if let Ok(v) = fs::read_to_string(path) {
-                    self.data.push_str(&v)
+                let path = entry.path();
+                if let Ok(v) = fs::read_to_string(path.join("stat")) {
+                    /* Kernel threads are only ranked by CPU, skip their other files */
+                    let details = match is_kernel_thread(&v) {
+                        true => None,
+                        false => read_details(&path, &file_name),
+                    };
+                    self.data.push_str(&v);
+                    if let Some(details) = details {
+                        self.data.push_str(&details);
+                    }
                 }
             }
         }
@@ -95,24 +180,59 @@ impl Processes {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// The values of a process in a sample. Those read from /proc/<pid>/status and /proc/<pid>/io
+/// are None for kernel threads, for the processes whose io aperf may not read, and in runs
+/// recorded before they were collected.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct SampleEntry {
     pub name: String,
     pub pid: u64,
     pub cpu_time: u64,
+    pub minor_faults: u64,
+    pub major_faults: u64,
+    pub threads: u64,
+    /// Bytes
+    pub vsz: u64,
+    /// kB
+    pub rss: Option<u64>,
+    pub voluntary_ctxt: Option<u64>,
+    pub involuntary_ctxt: Option<u64>,
+    pub read_bytes: Option<u64>,
+    pub write_bytes: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ProcessEntry {
-    pub name: String,
-    pub total_cpu_time: u64,
-    pub samples: HashMap<TimeEnum, u64>,
+impl SampleEntry {
+    fn set_details(&mut self, values: &[&str]) {
+        let value = |i: usize| values.get(i).and_then(|v| v.parse::<u64>().ok());
+        self.rss = value(1);
+        self.voluntary_ctxt = value(2);
+        self.involuntary_ctxt = value(3);
+        self.read_bytes = value(4);
+        self.write_bytes = value(5);
+    }
+
+    /// The value ranked, None if it was not collected.
+    fn get(&self, ranking: &str) -> Option<u64> {
+        match ranking {
+            "cpu" => Some(self.cpu_time),
+            "rss" => self.rss,
+            "vsz" => Some(self.vsz),
+            "minor_faults" => Some(self.minor_faults),
+            "major_faults" => Some(self.major_faults),
+            "threads" => Some(self.threads),
+            "voluntary_ctxt" => self.voluntary_ctxt,
+            "involuntary_ctxt" => self.involuntary_ctxt,
+            "read_bytes" => self.read_bytes,
+            "write_bytes" => self.write_bytes,
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EndEntry {
     pub name: String,
-    pub total_cpu_time: f64,
+    pub total: f64,
     pub entries: Vec<Sample>,
 }
 
@@ -124,15 +244,26 @@ pub struct EndEntries {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Sample {
-    pub cpu_time: f64,
+    pub value: f64,
     pub time: TimeEnum,
 }
 
-pub fn get_values(values: Vec<Processes>) -> Result<String> {
+/// Whether a ranking is of a value sampled as is, ranked by its peak, rather than of a counter.
+fn is_gauge(ranking: &str) -> bool {
+    matches!(ranking, "rss" | "vsz" | "threads")
+}
+
+/// Rank the processes, grouped by name, by one of PROCESS_RANKINGS and keep the top ones with
+/// their samples. CPU time is plotted as a percentage of one CPU, the other counters per second,
+/// memory in MiB.
+pub fn get_ranking(values: Vec<Processes>, ranking: &str) -> Result<String> {
+    if !PROCESS_RANKINGS.contains(&ranking) {
+        return Err(PDError::VisualizerUnsupportedAPI.into());
+    }
     let value_zero = values[0].clone();
     let time_zero = value_zero.time;
     let ticks_per_second: u64 = *TICKS_PER_SECOND.lock().unwrap();
-    let mut process_map: HashMap<String, ProcessEntry> = HashMap::new();
+    let mut process_map: HashMap<String, BTreeMap<TimeEnum, u64>> = HashMap::new();
     let mut total_time: u64 = 1;
     if let TimeEnum::TimeDiff(v) = values.last().unwrap().time - values[0].time {
         if v > 0 {
@@ -141,108 +272,99 @@ pub fn get_values(values: Vec<Processes>) -> Result<String> {
     }
 
     for value in values {
+        let time = value.time - time_zero;
         for entry in value.entries {
-            let time = value.time - time_zero;
-            match process_map.get_mut(&entry.name) {
-                Some(pe) => {
-                    let mut sample_cpu_time: u64 = entry.cpu_time;
-                    if let Some(v) = pe.samples.get(&time) {
-                        sample_cpu_time += v;
-                    }
-                    pe.samples.insert(time, sample_cpu_time);
-                }
-                None => {
-                    let mut process_entry = ProcessEntry {
-                        name: entry.name.clone(),
-                        total_cpu_time: 0,
-                        samples: HashMap::new(),
-                    };
-                    process_entry.samples.insert(time, entry.cpu_time);
-                    process_map.insert(entry.name, process_entry);
-                }
-            }
+            let sample_value = match entry.get(ranking) {
+                Some(v) => v,
+                None => continue,
+            };
+            *process_map
+                .entry(entry.name)
+                .or_default()
+                .entry(time)
+                .or_insert(0) += sample_value;
         }
     }
     let mut end_values: EndEntries = EndEntries {
         collection_time: TimeEnum::TimeDiff(total_time),
         end_entries: Vec::new(),
     };
+    let scale = match ranking {
+        "rss" => 1024.0,
+        "vsz" => 1024.0 * 1024.0,
+        _ => 1.0,
+    };
 
-    for (_, process) in process_map.iter_mut() {
+    for (name, samples) in process_map {
         let mut end_entry = EndEntry {
-            name: process.name.clone(),
-            total_cpu_time: 0.0,
+            name,
+            total: 0.0,
             entries: Vec::new(),
         };
-        let mut entries: Vec<(TimeEnum, u64)> = process.samples.clone().into_iter().collect();
-        entries.sort_by(|(a, _), (c, _)| a.cmp(c));
-        let entry_zero: (TimeEnum, u64) = entries[0];
-        let mut prev_sample = Sample {
-            time: entry_zero.0,
-            cpu_time: entry_zero.1 as f64,
-        };
-        let mut prev_time: u64 = 0;
-        let mut time_now;
-        if let TimeEnum::TimeDiff(v) = prev_sample.time {
-            prev_time = v;
-        }
-        for (time, cpu_time) in &entries {
-            let sample = Sample {
-                time: *time,
-                cpu_time: *cpu_time as f64,
-            };
-            /* End sample */
-            let mut end_sample = sample.clone();
-
-            if end_sample.cpu_time as i64 - prev_sample.cpu_time as i64 >= 0 {
-                /* Update sample based on previous sample */
-                end_sample.cpu_time -= prev_sample.cpu_time;
-            } else {
-                end_sample.cpu_time = 0.0;
+        if is_gauge(ranking) {
+            for (time, value) in samples {
+                let value = value as f64 / scale;
+                end_entry.total = end_entry.total.max(value);
+                end_entry.entries.push(Sample { time, value });
             }
-            /* Add to total_cpu_time */
-            end_entry.total_cpu_time += end_sample.cpu_time;
-
-            match *time {
-                TimeEnum::TimeDiff(v) => {
-                    time_now = v;
-                    if time_now - prev_time == 0 {
-                        continue;
-                    }
-                }
-                _ => continue,
+            end_values.end_entries.push(end_entry);
+            continue;
+        }
+        let mut prev: Option<(TimeEnum, u64)> = None;
+        for (time, value) in samples {
+            if let (Some((TimeEnum::TimeDiff(prev_ms), prev_value)), TimeEnum::TimeDiff(ms)) =
+                (prev, time)
+            {
+                /* Processes of the name that exited can make the sum go down */
+                let delta = value.saturating_sub(prev_value) as f64;
+                end_entry.total += delta;
+                let seconds = (ms - prev_ms) as f64 / 1000.0;
+                let rate = match ranking {
+                    /* Percentage utilization */
+                    "cpu" => delta / (ticks_per_second as f64 * seconds) * 100.0,
+                    _ => delta / seconds,
+                };
+                end_entry.entries.push(Sample { time, value: rate });
             }
-
-            /* Percentage utilization */
-            end_sample.cpu_time /= (ticks_per_second * (time_now - prev_time)) as f64;
-            end_sample.cpu_time *= 100.0;
-
-            prev_time = time_now;
-            end_entry.entries.push(end_sample);
-
-            /* Copy to prev_sample */
-            prev_sample = sample.clone();
+            prev = Some((time, value));
         }
         end_values.end_entries.push(end_entry);
     }
-    /* Order the processes by Total CPU Time per collection time */
-    end_values
-        .end_entries
-        .sort_by(|a, b| (b.total_cpu_time).total_cmp(&(a.total_cpu_time)));
-
-    if end_values.end_entries.len() > 16 {
-        end_values.end_entries = end_values.end_entries[0..15].to_vec();
-    }
+    /* Order the processes by their total per collection time */
+    keep_top_entries(&mut end_values.end_entries, |e| e.total);
 
     Ok(serde_json::to_string(&end_values)?)
 }
 
+/// The rankings with values in a sample. Runs recorded before /proc/<pid>/status and
+/// /proc/<pid>/io were collected only have the ones from /proc/<pid>/stat.
+fn get_rankings(value: &Processes) -> Result<String> {
+    let rankings: Vec<&str> = PROCESS_RANKINGS
+        .iter()
+        .filter(|r| value.entries.iter().any(|e| e.get(r).is_some()))
+        .cloned()
+        .collect();
+    Ok(serde_json::to_string(&rankings)?)
+}
+
+/// Order entries by their total, highest first, and keep the top TOP_ENTRIES. Up to
+/// TOP_ENTRIES + 1 entries are all kept, as the Processes tab always has.
+pub fn keep_top_entries<T, F>(entries: &mut Vec<T>, total: F)
+where
+    F: Fn(&T) -> f64,
+{
+    entries.sort_by(|a, b| total(b).total_cmp(&total(a)));
+    if entries.len() > TOP_ENTRIES + 1 {
+        entries.truncate(TOP_ENTRIES);
+    }
+}
+
 impl GetData for Processes {
     fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
         let mut processes = Processes::new();
         let raw_value = match buffer {
             Data::ProcessesRaw(ref value) => value,
-            _ => panic!("Invalid Data type in raw file"),
+            _ => return Err(PDError::InvalidRawDataType.into()),
         };
         *TICKS_PER_SECOND.lock().unwrap() = raw_value.ticks_per_second;
         let reader = BufReader::new(raw_value.data.as_bytes());
@@ -253,7 +375,16 @@ impl GetData for Processes {
             let open_parenthesis = line.find('(');
             let open_pos = match open_parenthesis {
                 Some(v) => v,
-                None => continue,
+                None => {
+                    /* The details line of the process of the previous stat line */
+                    let values: Vec<&str> = line.split_whitespace().collect();
+                    if let Some(entry) = processes.entries.last_mut() {
+                        if values.first() == Some(&entry.pid.to_string().as_str()) {
+                            entry.set_details(&values);
+                        }
+                    }
+                    continue;
+                }
             };
             let close_parenthesis = line.find(')');
             let close_pos = match close_parenthesis {
@@ -264,7 +395,7 @@ impl GetData for Processes {
             let name = line[open_pos + 1..close_pos].to_string();
             let values: Vec<&str> = line[close_pos + 2..].split_whitespace().collect();
 
-            if values.len() < PROC_PID_STAT_KERNELSPACE_TIME_POS + 1 {
+            if values.len() < PROC_PID_STAT_VSZ_POS + 1 {
                 continue;
             }
             let user_time = values[PROC_PID_STAT_USERSPACE_TIME_POS].parse::<u64>()?;
@@ -275,6 +406,11 @@ impl GetData for Processes {
                 name,
                 pid,
                 cpu_time,
+                minor_faults: values[PROC_PID_STAT_MINOR_FAULTS_POS].parse::<u64>()?,
+                major_faults: values[PROC_PID_STAT_MAJOR_FAULTS_POS].parse::<u64>()?,
+                threads: values[PROC_PID_STAT_THREADS_POS].parse::<u64>()?,
+                vsz: values[PROC_PID_STAT_VSZ_POS].parse::<u64>()?,
+                ..Default::default()
             });
         }
         let processed_data = ProcessedData::Processes(processes);
@@ -282,7 +418,7 @@ impl GetData for Processes {
     }
 
     fn get_calls(&mut self) -> Result<Vec<String>> {
-        Ok(vec!["values".to_string()])
+        Ok(vec!["keys".to_string(), "values".to_string()])
     }
 
     fn get_data(
@@ -305,7 +441,11 @@ impl GetData for Processes {
         let (_, req_str) = &param[1];
 
         match req_str.as_str() {
-            "values" => get_values(values.clone()),
+            "keys" => get_rankings(&values[0]),
+            "values" => {
+                let (_, key) = &param[2];
+                get_ranking(values, key)
+            }
             _ => panic!("Unsupported API"),
         }
     }
@@ -343,10 +483,33 @@ fn init_system_processes() {
 
 #[cfg(test)]
 mod process_test {
-    use super::{Processes, ProcessesRaw};
+    use super::{is_kernel_thread, keep_top_entries, EndEntries, Processes, ProcessesRaw};
+    use crate::data::test_utils::{get_data, get_keys, process_samples, sample_time};
     use crate::data::{CollectData, CollectorParams, Data, ProcessedData};
+    use crate::utils::DataMetrics;
     use crate::visualizer::GetData;
 
+    /// A /proc/<pid>/stat line, followed by a details line if `details` is not empty.
+    fn process_lines(pid: u64, name: &str, cpu_time: u64, faults: u64, details: &str) -> String {
+        let mut lines = format!(
+            "{} ({}) S 1 {} {} 0 -1 4194560 {} 0 0 0 {} 0 0 0 20 0 4 0 100 104857600 2560 \
+             18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 0 0 0 0 0 0\n",
+            pid, name, pid, pid, faults, cpu_time
+        );
+        if !details.is_empty() {
+            lines.push_str(&format!("{} {}\n", pid, details));
+        }
+        lines
+    }
+
+    fn raw_data(seconds: i64, data: String) -> Data {
+        Data::ProcessesRaw(ProcessesRaw {
+            time: sample_time(seconds),
+            ticks_per_second: 100,
+            data,
+        })
+    }
+
     #[test]
     fn test_collect_data() {
         let mut processes = ProcessesRaw::new();
@@ -376,4 +539,76 @@ mod process_test {
         }
         assert!(!processed_buffer.is_empty(), "{:#?}", processed_buffer);
     }
+
+    #[test]
+    fn test_is_kernel_thread() {
+        let kthread = "2 (kthreadd) S 0 0 0 0 -1 2129984 0 0 0 0 0 0 0 0 20 0 1 0 0 0 0";
+        assert!(is_kernel_thread(kthread));
+        assert!(!is_kernel_thread(&process_lines(1, "init (1)", 0, 0, "")));
+    }
+
+    #[test]
+    fn test_keep_top_entries() {
+        let mut entries: Vec<f64> = (0..16).map(f64::from).collect();
+        keep_top_entries(&mut entries, |e| *e);
+        assert_eq!(entries.len(), 16);
+        assert_eq!(entries[0], 15.0);
+
+        let mut entries: Vec<f64> = (0..17).map(f64::from).collect();
+        keep_top_entries(&mut entries, |e| *e);
+        assert_eq!(entries.len(), 15);
+        assert_eq!(entries[14], 2.0);
+    }
+
+    #[test]
+    fn test_get_rankings() {
+        let mut samples = Vec::new();
+        for (seconds, details) in [
+            (0, ["1000 10 1 0 4096", "500 0 0 - -"]),
+            (1, ["3000 30 2 8192 4096", "700 5 0 - -"]),
+        ] {
+            let data = process_lines(100, "db", seconds as u64 * 50, 10, details[0])
+                + &process_lines(200, "web", seconds as u64 * 20, 10, details[1])
+                + &process_lines(201, "web", seconds as u64 * 20, seconds as u64 * 8, "")
+                /* Kernel threads have no details line */
+                + &process_lines(2, "kthreadd", 0, 0, "");
+            samples.push(raw_data(seconds, data));
+        }
+        let buffer = process_samples(Processes::new, samples);
+        assert_eq!(get_keys(Processes::new, &buffer).len(), 10);
+
+        let query = |ranking: &str| {
+            get_data(
+                Processes::new,
+                &buffer,
+                &format!("get=values&key={}", ranking),
+                &mut DataMetrics::new(String::new()),
+            )
+        };
+        let get = |ranking: &str| -> EndEntries {
+            serde_json::from_str(&query(ranking).unwrap()).unwrap()
+        };
+        /* 50 ticks of 100 per second in 1s */
+        let cpu = get("cpu");
+        assert_eq!(cpu.end_entries[0].name, "db");
+        assert_eq!(cpu.end_entries[0].entries[0].value, 50.0);
+        assert_eq!(cpu.end_entries[1].entries[0].value, 40.0);
+
+        /* Peak RSS, in MiB */
+        let rss = get("rss");
+        assert_eq!(rss.end_entries[0].name, "db");
+        assert!((rss.end_entries[0].total - 3000.0 / 1024.0).abs() < 1e-9);
+        assert_eq!(rss.end_entries.len(), 2);
+
+        let minor_faults = get("minor_faults");
+        assert_eq!(minor_faults.end_entries[0].name, "web");
+        assert_eq!(minor_faults.end_entries[0].entries[0].value, 8.0);
+
+        /* web's io could not be read */
+        let read_bytes = get("read_bytes");
+        assert_eq!(read_bytes.end_entries.len(), 1);
+        assert_eq!(read_bytes.end_entries[0].entries[0].value, 8192.0);
+
+        assert!(query("stack").is_err());
+    }
 }
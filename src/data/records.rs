@@ -0,0 +1,206 @@
+use crate::PDError;
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+
+/// Start of a data file of framed records. Each record is made of its length and the CRC32 of its
+/// payload (both u32, little endian) followed by the bincode payload. A record cut short by a
+/// crash, or one that fails its checksum, marks the end of the good data. Files without the magic
+/// were written by older versions of aperf as plain bincode streams.
+pub const RECORD_MAGIC: &[u8; 8] = b"APERFRC1";
+
+const FRAME_HEADER_LEN: usize = 8;
+
+/// Serialize a value into a framed record.
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let payload = bincode::serialize(value)?;
+    let mut record = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+/// Append a framed record. It is written with a single write so that a crash leaves at most
+/// one partial record at the end of the file.
+pub fn write_record<T: Serialize, W: Write>(mut writer: W, value: &T) -> Result<()> {
+    writer.write_all(&encode(value)?)?;
+    Ok(())
+}
+
+/// Read as much of `buf` as is available. Returns the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(read)
+}
+
+/// Reads the records of a data file, framed or legacy.
+pub struct RecordReader<R> {
+    reader: R,
+    framed: bool,
+    offset: u64,
+}
+
+impl<R: Read + Seek> RecordReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; RECORD_MAGIC.len()];
+        let framed = read_full(&mut reader, &mut magic)? == magic.len() && &magic == RECORD_MAGIC;
+        if !framed {
+            reader.seek(SeekFrom::Start(0))?;
+        }
+        Ok(RecordReader {
+            reader,
+            framed,
+            offset: if framed { magic.len() as u64 } else { 0 },
+        })
+    }
+}
+
+impl<R: Read> RecordReader<R> {
+    pub fn is_framed(&self) -> bool {
+        self.framed
+    }
+
+    /// Byte offset of the end of the last record read.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Estimate how many records a file of `len` bytes holds from the size of the `read` records
+    /// read so far. Counts at least one record in the bytes left over.
+    pub fn estimate_total(&self, read: usize, len: u64) -> usize {
+        let start = if self.framed {
+            RECORD_MAGIC.len() as u64
+        } else {
+            0
+        };
+        let left = len.saturating_sub(self.offset);
+        if left == 0 {
+            return read;
+        }
+        if read == 0 || self.offset <= start {
+            return read + 1;
+        }
+        let average = (self.offset - start) / read as u64;
+        read + ((left + average / 2) / average.max(1)).max(1) as usize
+    }
+
+    /// Read the payload of the next framed record without decoding it. Returns None at the end
+    /// of the file.
+    fn next_payload(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        match read_full(&mut self.reader, &mut header)? {
+            0 => return Ok(None),
+            FRAME_HEADER_LEN => {}
+            _ => return Err(PDError::RecordTorn(self.offset).into()),
+        }
+        let len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let mut payload = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut payload)?;
+        if payload.len() < len {
+            return Err(PDError::RecordTorn(self.offset).into());
+        }
+        if crc32fast::hash(&payload) != crc {
+            return Err(PDError::RecordChecksumMismatch(self.offset).into());
+        }
+        self.offset += (FRAME_HEADER_LEN + len) as u64;
+        Ok(Some(payload))
+    }
+
+    /// Read the next record. Returns None at the end of the file.
+    pub fn read_record<T: DeserializeOwned + Serialize>(&mut self) -> Result<Option<T>> {
+        if !self.framed {
+            return match bincode::deserialize_from::<_, T>(&mut self.reader) {
+                Ok(v) => {
+                    self.offset += bincode::serialized_size(&v)?;
+                    Ok(Some(v))
+                }
+                Err(e) => match *e {
+                    // EOF
+                    bincode::ErrorKind::Io(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+                    e => Err(e.into()),
+                },
+            };
+        }
+        match self.next_payload()? {
+            Some(payload) => Ok(Some(bincode::deserialize(&payload)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Skip over the records that are intact. Returns how many there were, and the error that
+    /// ended them if the file does not end cleanly.
+    pub fn skip_valid(&mut self) -> (usize, Option<anyhow::Error>) {
+        let mut count = 0;
+        loop {
+            match self.next_payload() {
+                Ok(Some(_)) => count += 1,
+                Ok(None) => return (count, None),
+                Err(e) => return (count, Some(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, write_record, RecordReader, RECORD_MAGIC};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_records() {
+        let mut file = RECORD_MAGIC.to_vec();
+        write_record(&mut file, &"first".to_string()).unwrap();
+        write_record(&mut file, &"second".to_string()).unwrap();
+        let good_len = file.len() as u64;
+
+        let mut reader = RecordReader::new(Cursor::new(file.clone())).unwrap();
+        assert!(reader.is_framed());
+        assert_eq!(reader.read_record::<String>().unwrap().unwrap(), "first");
+        assert_eq!(reader.read_record::<String>().unwrap().unwrap(), "second");
+        assert!(reader.read_record::<String>().unwrap().is_none());
+        assert_eq!(reader.offset(), good_len);
+
+        /* A torn tail ends the records */
+        let third = encode(&"third".to_string()).unwrap();
+        let mut torn = file.clone();
+        torn.extend_from_slice(&third[..third.len() - 2]);
+        let mut reader = RecordReader::new(Cursor::new(torn)).unwrap();
+        let (count, err) = reader.skip_valid();
+        assert_eq!(count, 2);
+        assert!(err.unwrap().to_string().contains("incomplete"));
+        assert_eq!(reader.offset(), good_len);
+
+        /* So does a record that fails its checksum */
+        let mut corrupt = file.clone();
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0xff;
+        let mut reader = RecordReader::new(Cursor::new(corrupt)).unwrap();
+        assert_eq!(reader.read_record::<String>().unwrap().unwrap(), "first");
+        assert!(reader.read_record::<String>().is_err());
+        assert_eq!(reader.estimate_total(1, good_len), 2);
+
+        /* Files without the magic are plain bincode streams */
+        let mut legacy = bincode::serialize(&"old".to_string()).unwrap();
+        legacy.extend(bincode::serialize(&"format".to_string()).unwrap());
+        let mut reader = RecordReader::new(Cursor::new(legacy)).unwrap();
+        assert!(!reader.is_framed());
+        assert_eq!(reader.read_record::<String>().unwrap().unwrap(), "old");
+        assert_eq!(reader.read_record::<String>().unwrap().unwrap(), "format");
+        assert!(reader.read_record::<String>().unwrap().is_none());
+        assert_eq!(reader.estimate_total(2, reader.offset()), 2);
+        let mut reader = RecordReader::new(Cursor::new(Vec::new())).unwrap();
+        assert!(reader.read_record::<String>().unwrap().is_none());
+    }
+}
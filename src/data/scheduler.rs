@@ -0,0 +1,542 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::{add_metrics, DataMetrics, Metric};
+use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub static SCHEDULER_FILE_NAME: &str = "scheduler";
+
+/// The lines of /proc/stat about the scheduler.
+const STAT_KEYS: &[&str] = &["ctxt", "procs_running", "procs_blocked"];
+
+/// Gather /proc/loadavg, /proc/schedstat and the scheduler lines of /proc/stat. Kernels built
+/// without CONFIG_SCHEDSTATS have no /proc/schedstat, only the load is collected then.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SchedulerRaw {
+    pub time: TimeEnum,
+    pub loadavg: String,
+    pub schedstat: String,
+    pub stat: String,
+}
+
+impl SchedulerRaw {
+    fn new() -> Self {
+        SchedulerRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            loadavg: String::new(),
+            schedstat: String::new(),
+            stat: String::new(),
+        }
+    }
+}
+
+impl CollectData for SchedulerRaw {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.loadavg = std::fs::read_to_string("/proc/loadavg")?;
+        self.schedstat = std::fs::read_to_string("/proc/schedstat").unwrap_or_default();
+        /* The rest of /proc/stat is collected by cpu_utilization */
+        self.stat = std::fs::read_to_string("/proc/stat")?
+            .lines()
+            .filter(|l| {
+                STAT_KEYS
+                    .iter()
+                    .any(|k| l.split_whitespace().next() == Some(*k))
+            })
+            .map(|l| format!("{}\n", l))
+            .collect();
+        trace!("{:#?} {:#?} {:#?}", self.loadavg, self.schedstat, self.stat);
+        Ok(())
+    }
+}
+
+/// The scheduler counters of a CPU in /proc/schedstat.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SchedCpuValues {
+    pub cpu: u64,
+    /// Nanoseconds spent running tasks.
+    pub run_time: u64,
+    /// Nanoseconds tasks spent waiting on the run queue.
+    pub wait_time: u64,
+    /// Number of timeslices run.
+    pub timeslices: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LoadValues {
+    pub load1: f64,
+    pub load5: f64,
+    pub load15: f64,
+    pub runnable: u64,
+    pub tasks: u64,
+}
+
+/// The scheduler lines of /proc/stat. Older kernels may not have the procs_* lines.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct KernelValues {
+    pub ctxt: u64,
+    pub procs_running: u64,
+    pub procs_blocked: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Scheduler {
+    pub time: TimeEnum,
+    pub load: LoadValues,
+    pub per_cpu: Vec<SchedCpuValues>,
+    pub kernel: Option<KernelValues>,
+}
+
+impl Scheduler {
+    fn new() -> Self {
+        Scheduler {
+            time: TimeEnum::DateTime(Utc::now()),
+            load: LoadValues::default(),
+            per_cpu: Vec::new(),
+            kernel: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct KernelEntry {
+    pub time: TimeEnum,
+    /// Context switches per second since the previous sample.
+    pub ctxt: u64,
+    pub procs_running: u64,
+    pub procs_blocked: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LoadEntry {
+    pub time: TimeEnum,
+    pub values: LoadValues,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct WaitEntry {
+    pub time: TimeEnum,
+    /// Average microseconds a task waited on the run queue per timeslice.
+    pub latency: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CpuWaitData {
+    pub cpu: u64,
+    pub data: Vec<WaitEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndWaitData {
+    pub per_cpu: Vec<CpuWaitData>,
+    pub metadata: GraphMetadata,
+}
+
+/// Parse a line such as "0.32 0.30 0.23 2/71 5192".
+fn parse_loadavg(data: &str) -> Result<LoadValues> {
+    let fields: Vec<&str> = data.split_whitespace().collect();
+    if fields.len() < 4 {
+        return Err(PDError::ProcessorOptionExtractError.into());
+    }
+    let (runnable, tasks) = fields[3]
+        .split_once('/')
+        .ok_or(PDError::ProcessorOptionExtractError)?;
+    Ok(LoadValues {
+        load1: fields[0].parse()?,
+        load5: fields[1].parse()?,
+        load15: fields[2].parse()?,
+        runnable: runnable.parse()?,
+        tasks: tasks.parse()?,
+    })
+}
+
+/// Parse the scheduler lines kept from /proc/stat, None if there are none.
+fn parse_stat(data: &str) -> Result<Option<KernelValues>> {
+    let mut kernel = None;
+    for line in data.lines() {
+        let (key, value) = match line.split_once(' ') {
+            Some((k, v)) => (k, v.trim().parse::<u64>()?),
+            None => continue,
+        };
+        let values: &mut KernelValues = kernel.get_or_insert_with(KernelValues::default);
+        match key {
+            "ctxt" => values.ctxt = value,
+            "procs_running" => values.procs_running = value,
+            "procs_blocked" => values.procs_blocked = value,
+            _ => {}
+        }
+    }
+    Ok(kernel)
+}
+
+/// Parse the "cpuN" lines of /proc/schedstat. Their last three fields are the run time, wait time
+/// and timeslices of the CPU in every version of the file.
+fn parse_schedstat(data: &str) -> Result<Vec<SchedCpuValues>> {
+    let mut per_cpu = Vec::new();
+    for line in data.lines() {
+        let mut fields = line.split_whitespace();
+        let cpu = match fields.next().and_then(|f| f.strip_prefix("cpu")) {
+            Some(cpu) => cpu.parse()?,
+            None => continue,
+        };
+        let fields: Vec<&str> = fields.collect();
+        if fields.len() < 3 {
+            return Err(PDError::ProcessorOptionExtractError.into());
+        }
+        let last = &fields[fields.len() - 3..];
+        per_cpu.push(SchedCpuValues {
+            cpu,
+            run_time: last[0].parse()?,
+            wait_time: last[1].parse()?,
+            timeslices: last[2].parse()?,
+        });
+    }
+    Ok(per_cpu)
+}
+
+fn get_load(values: Vec<Scheduler>, metrics: &mut DataMetrics) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut metric = Metric::new("load1".to_string());
+    let mut end_values = Vec::new();
+    for value in values {
+        metric.insert_value(value.load.load1);
+        end_values.push(LoadEntry {
+            time: value.time - time_zero,
+            values: value.load,
+        });
+    }
+    add_metrics(
+        "load1".to_string(),
+        &mut metric,
+        metrics,
+        SCHEDULER_FILE_NAME.to_string(),
+    )?;
+    Ok(serde_json::to_string(&end_values)?)
+}
+
+/// The context switch rate and the number of running and blocked tasks.
+fn get_kernel(values: Vec<Scheduler>) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut end_values = Vec::new();
+    let mut prev: Option<(TimeEnum, u64)> = None;
+    for value in values {
+        let kernel = match value.kernel {
+            Some(k) => k,
+            None => continue,
+        };
+        let time = value.time - time_zero;
+        let ctxt = match (prev, time) {
+            (Some((TimeEnum::TimeDiff(prev_ms), prev_ctxt)), TimeEnum::TimeDiff(ms))
+                if ms > prev_ms =>
+            {
+                kernel.ctxt.saturating_sub(prev_ctxt) * 1000 / (ms - prev_ms)
+            }
+            _ => 0,
+        };
+        end_values.push(KernelEntry {
+            time,
+            ctxt,
+            procs_running: kernel.procs_running,
+            procs_blocked: kernel.procs_blocked,
+        });
+        prev = Some((time, kernel.ctxt));
+    }
+    Ok(serde_json::to_string(&end_values)?)
+}
+
+fn get_wait_latency(values: Vec<Scheduler>, metrics: &mut DataMetrics) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut metric = Metric::new("wait_latency".to_string());
+    let mut metadata = GraphMetadata::new();
+    /* CPUs are matched by id, as they can go offline and come back during the run */
+    let mut per_cpu: BTreeMap<u64, Vec<WaitEntry>> = BTreeMap::new();
+    for pair in values.windows(2) {
+        let (prev, value) = (&pair[0], &pair[1]);
+        for cpu_values in &value.per_cpu {
+            let prev_values = match prev.per_cpu.iter().find(|p| p.cpu == cpu_values.cpu) {
+                Some(p) => p,
+                None => continue,
+            };
+            let timeslices = cpu_values.timeslices.saturating_sub(prev_values.timeslices);
+            let wait = cpu_values.wait_time.saturating_sub(prev_values.wait_time);
+            let latency = match timeslices {
+                0 => 0.0,
+                _ => wait as f64 / timeslices as f64 / 1000.0,
+            };
+            metric.insert_value(latency);
+            metadata.update_limits(GraphLimitType::F64(latency));
+            per_cpu.entry(cpu_values.cpu).or_default().push(WaitEntry {
+                time: value.time - time_zero,
+                latency,
+            });
+        }
+    }
+    add_metrics(
+        "wait_latency".to_string(),
+        &mut metric,
+        metrics,
+        SCHEDULER_FILE_NAME.to_string(),
+    )?;
+    let per_cpu = per_cpu
+        .into_iter()
+        .map(|(cpu, data)| CpuWaitData { cpu, data })
+        .collect();
+    let wait_data = EndWaitData { per_cpu, metadata };
+    Ok(serde_json::to_string(&wait_data)?)
+}
+
+impl GetData for Scheduler {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::SchedulerRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut scheduler = Scheduler::new();
+        scheduler.time = raw_value.time;
+        scheduler.load = parse_loadavg(&raw_value.loadavg)?;
+        scheduler.per_cpu = parse_schedstat(&raw_value.schedstat)?;
+        scheduler.kernel = parse_stat(&raw_value.stat)?;
+        Ok(ProcessedData::Scheduler(scheduler))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Scheduler(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => {
+                let mut keys = vec!["load"];
+                if values[0].kernel.is_some() {
+                    keys.push("kernel");
+                }
+                if !values[0].per_cpu.is_empty() {
+                    keys.push("wait_latency");
+                }
+                Ok(serde_json::to_string(&keys)?)
+            }
+            "values" => {
+                let (_, key) = &param[2];
+                match key.as_str() {
+                    "load" => get_load(values, metrics),
+                    "kernel" => get_kernel(values),
+                    "wait_latency" => get_wait_latency(values, metrics),
+                    _ => Err(PDError::VisualizerUnsupportedAPI.into()),
+                }
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_scheduler() {
+    let scheduler_raw = SchedulerRaw::new();
+    let file_name = SCHEDULER_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::SchedulerRaw(scheduler_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let scheduler = Scheduler::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Scheduler(scheduler.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/scheduler.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_loadavg, parse_schedstat, parse_stat, EndWaitData, KernelValues, LoadValues,
+        SchedCpuValues, Scheduler, SchedulerRaw,
+    };
+    use crate::data::test_utils::{get_data, get_keys, process_samples, sample_time};
+    use crate::data::{CollectData, CollectorParams, Data};
+    use crate::utils::DataMetrics;
+
+    /// A sample with the given (wait_time, timeslices) of the online CPUs.
+    fn raw_data(seconds: i64, cpus: &[(u64, (u64, u64))]) -> Data {
+        let mut schedstat = "version 15\ntimestamp 4295\n".to_string();
+        for (cpu, (wait_time, timeslices)) in cpus {
+            schedstat.push_str(&format!(
+                "cpu{} 0 0 0 0 0 0 1000 {} {}\n\
+                 domain0 3 1 1 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n",
+                cpu, wait_time, timeslices
+            ));
+        }
+        Data::SchedulerRaw(SchedulerRaw {
+            time: sample_time(seconds),
+            loadavg: "1.50 0.75 0.25 3/120 4242\n".to_string(),
+            schedstat,
+            stat: format!(
+                "ctxt {}\nprocs_running 3\nprocs_blocked 1\n",
+                5000 * seconds
+            ),
+        })
+    }
+
+    fn wait_latency(samples: Vec<Data>, metrics: &mut DataMetrics) -> EndWaitData {
+        let buffer = process_samples(Scheduler::new, samples);
+        let json = get_data(
+            Scheduler::new,
+            &buffer,
+            "get=values&key=wait_latency",
+            metrics,
+        )
+        .unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn latency(data: &EndWaitData, cpu: u64) -> Vec<f64> {
+        data.per_cpu
+            .iter()
+            .find(|c| c.cpu == cpu)
+            .unwrap()
+            .data
+            .iter()
+            .map(|e| e.latency)
+            .collect()
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let mut scheduler = SchedulerRaw::new();
+        let params = CollectorParams::new();
+
+        scheduler.collect_data(&params).unwrap();
+        assert!(!scheduler.loadavg.is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse_loadavg("0.32 0.30 0.23 2/71 5192\n").unwrap(),
+            LoadValues {
+                load1: 0.32,
+                load5: 0.30,
+                load15: 0.23,
+                runnable: 2,
+                tasks: 71,
+            }
+        );
+        assert!(parse_loadavg("0.32 0.30").is_err());
+        let per_cpu = parse_schedstat("version 15\ncpu3 0 0 5 6 7 8 900 800 70\n").unwrap();
+        assert_eq!(
+            per_cpu,
+            vec![SchedCpuValues {
+                cpu: 3,
+                run_time: 900,
+                wait_time: 800,
+                timeslices: 70,
+            }]
+        );
+        assert!(parse_schedstat("").unwrap().is_empty());
+        assert_eq!(
+            parse_stat("ctxt 42\nprocs_running 2\n").unwrap(),
+            Some(KernelValues {
+                ctxt: 42,
+                procs_running: 2,
+                procs_blocked: 0,
+            })
+        );
+        assert_eq!(parse_stat("").unwrap(), None);
+    }
+
+    #[test]
+    fn test_get_wait_latency() {
+        let samples = vec![
+            raw_data(0, &[(0, (0, 0)), (1, (0, 0))]),
+            raw_data(1, &[(0, (2_000_000, 100)), (1, (0, 0))]),
+            raw_data(2, &[(0, (2_500_000, 200)), (1, (300_000, 3))]),
+        ];
+        assert_eq!(
+            get_keys(
+                Scheduler::new,
+                &process_samples(Scheduler::new, samples.clone())
+            ),
+            vec!["load", "kernel", "wait_latency"]
+        );
+
+        let mut metrics = DataMetrics::new(String::new());
+        let data = wait_latency(samples, &mut metrics);
+        assert_eq!(latency(&data, 0), vec![20.0, 5.0]);
+        assert_eq!(latency(&data, 1), vec![0.0, 100.0]);
+        assert_eq!(data.metadata.limits.high, 100);
+        assert!(metrics.values["scheduler"].contains_key("wait_latency"));
+    }
+
+    #[test]
+    fn test_wait_latency_hotplug() {
+        /* cpu0 comes online after the first sample and cpu1 goes offline for one sample */
+        let data = wait_latency(
+            vec![
+                raw_data(0, &[(1, (0, 0))]),
+                raw_data(1, &[(0, (0, 0)), (1, (1_000_000, 10))]),
+                raw_data(2, &[(0, (4_000_000, 100))]),
+                raw_data(3, &[(0, (4_000_000, 100)), (1, (1_000_000, 10))]),
+                raw_data(4, &[(0, (4_000_000, 100)), (1, (3_000_000, 20))]),
+            ],
+            &mut DataMetrics::new(String::new()),
+        );
+        assert_eq!(data.per_cpu.len(), 2);
+        assert_eq!(latency(&data, 0), vec![40.0, 0.0, 0.0]);
+        assert_eq!(latency(&data, 1), vec![100.0, 200.0]);
+    }
+
+    #[test]
+    fn test_get_kernel() {
+        let buffer = process_samples(
+            Scheduler::new,
+            (0..3).map(|seconds| raw_data(seconds, &[])).collect(),
+        );
+        let json = get_data(
+            Scheduler::new,
+            &buffer,
+            "get=values&key=kernel",
+            &mut DataMetrics::new(String::new()),
+        )
+        .unwrap();
+        let values: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        let ctxt: Vec<u64> = values.iter().map(|v| v["ctxt"].as_u64().unwrap()).collect();
+        assert_eq!(ctxt, vec![0, 5000, 5000]);
+        assert_eq!(values[2]["procs_running"], 3);
+        assert_eq!(values[2]["procs_blocked"], 1);
+    }
+}
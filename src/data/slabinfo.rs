@@ -0,0 +1,356 @@
+extern crate ctor;
+
+use crate::data::processes::{keep_top_entries, EndEntries, EndEntry, Sample};
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::DataMetrics;
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub static SLABINFO_FILE_NAME: &str = "slabinfo";
+
+static PROC_SLABINFO: &str = "/proc/slabinfo";
+
+static SYS_KERNEL_SLAB: &str = "/sys/kernel/slab";
+
+/// The slab caches, as /proc/slabinfo. That file is only readable by root, otherwise the lines
+/// are built from /sys/kernel/slab with the name, active objects, objects and object size columns.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SlabinfoRaw {
+    pub time: TimeEnum,
+    pub data: String,
+}
+
+impl SlabinfoRaw {
+    fn new() -> Self {
+        SlabinfoRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            data: String::new(),
+        }
+    }
+}
+
+/// The first number of a /sys/kernel/slab file, which can be followed by per node counts.
+fn read_first_number(path: &Path) -> Option<u64> {
+    fs::read_to_string(path)
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Build slabinfo lines from a /sys/kernel/slab directory. Merged caches appear once per alias,
+/// as links to the same directory, so each directory is read once under the first alias name.
+fn read_sys_slab(root: &Path) -> Result<String> {
+    let mut caches: BTreeMap<PathBuf, String> = BTreeMap::new();
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let dir = fs::canonicalize(entry.path()).unwrap_or(entry.path());
+        /* Unnamed merged caches start with ':', prefer any alias over them */
+        let rank = |n: &String| (n.starts_with(':'), n.clone());
+        if caches.get(&dir).is_none_or(|n| rank(&name) < rank(n)) {
+            caches.insert(dir, name);
+        }
+    }
+    let mut data = String::new();
+    for (dir, name) in caches {
+        let (active, total, size) = match (
+            read_first_number(&dir.join("objects")),
+            read_first_number(&dir.join("total_objects")),
+            read_first_number(&dir.join("slab_size")),
+        ) {
+            (Some(a), Some(t), Some(s)) => (a, t, s),
+            _ => continue,
+        };
+        data.push_str(&format!("{} {} {} {}\n", name, active, total, size));
+    }
+    Ok(data)
+}
+
+fn read_slabinfo() -> Result<String> {
+    match fs::read_to_string(PROC_SLABINFO) {
+        Ok(data) => Ok(data),
+        Err(_) => read_sys_slab(Path::new(SYS_KERNEL_SLAB)),
+    }
+}
+
+impl CollectData for SlabinfoRaw {
+    fn prepare_data_collector(&mut self, _params: &CollectorParams) -> Result<()> {
+        match read_slabinfo() {
+            Ok(data) if !data.is_empty() => Ok(()),
+            _ => Err(PDError::CollectorSlabinfoUnavailable.into()),
+        }
+    }
+
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.data = read_slabinfo()?;
+        trace!("{:#?}", self.data);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SlabCache {
+    pub name: String,
+    pub active_objects: u64,
+    pub objects: u64,
+    /// Bytes per object, including the slab metadata.
+    pub object_size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Slabinfo {
+    pub time: TimeEnum,
+    pub caches: Vec<SlabCache>,
+}
+
+impl Slabinfo {
+    fn new() -> Self {
+        Slabinfo {
+            time: TimeEnum::DateTime(Utc::now()),
+            caches: Vec::new(),
+        }
+    }
+}
+
+static SLAB_KEYS: [&str; 2] = ["memory", "active_objects"];
+
+fn get_value(cache: &SlabCache, key: &str) -> f64 {
+    match key {
+        /* KiB */
+        "memory" => (cache.objects * cache.object_size) as f64 / 1024.0,
+        _ => cache.active_objects as f64,
+    }
+}
+
+/// The caches that grew the most over the run, with their value in each sample. Caches created
+/// during the run grew from nothing.
+fn get_growth(values: Vec<Slabinfo>, key: &str) -> Result<String> {
+    let time_zero = values[0].time;
+    let first: HashMap<&String, f64> = values[0]
+        .caches
+        .iter()
+        .map(|c| (&c.name, get_value(c, key)))
+        .collect();
+    let mut caches: BTreeMap<String, EndEntry> = BTreeMap::new();
+    for value in &values {
+        for cache in &value.caches {
+            let entry = caches.entry(cache.name.clone()).or_insert(EndEntry {
+                name: cache.name.clone(),
+                total: 0.0,
+                entries: Vec::new(),
+            });
+            let sample = get_value(cache, key);
+            entry.total = sample - first.get(&cache.name).unwrap_or(&0.0);
+            entry.entries.push(Sample {
+                value: sample,
+                time: value.time - time_zero,
+            });
+        }
+    }
+    let mut end_values = EndEntries {
+        collection_time: values.last().unwrap().time - time_zero,
+        end_entries: caches.into_values().collect(),
+    };
+    keep_top_entries(&mut end_values.end_entries, |e| e.total);
+    Ok(serde_json::to_string(&end_values)?)
+}
+
+impl GetData for Slabinfo {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::SlabinfoRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut slabinfo = Slabinfo::new();
+        slabinfo.time = raw_value.time;
+        for line in raw_value.data.lines() {
+            if line.starts_with("slabinfo") || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 4 {
+                continue;
+            }
+            slabinfo.caches.push(SlabCache {
+                name: fields[0].to_string(),
+                active_objects: fields[1].parse()?,
+                objects: fields[2].parse()?,
+                object_size: fields[3].parse()?,
+            });
+        }
+        Ok(ProcessedData::Slabinfo(slabinfo))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        _metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Slabinfo(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => Ok(serde_json::to_string(&SLAB_KEYS)?),
+            "values" => {
+                let (_, key) = &param[2];
+                if !SLAB_KEYS.contains(&key.as_str()) {
+                    return Err(PDError::VisualizerUnsupportedAPI.into());
+                }
+                get_growth(values, key)
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_slabinfo() {
+    let slabinfo_raw = SlabinfoRaw::new();
+    let file_name = SLABINFO_FILE_NAME.to_string();
+    let mut dt = DataType::new(
+        Data::SlabinfoRaw(slabinfo_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    dt.is_profile_option();
+    let js_file_name = file_name.clone() + ".js";
+    let slabinfo = Slabinfo::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Slabinfo(slabinfo),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/slabinfo.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_sys_slab, SlabCache, Slabinfo, SlabinfoRaw};
+    use crate::data::processes::EndEntries;
+    use crate::data::test_utils::{get_data, process_samples, sample_time};
+    use crate::data::{Data, ProcessedData};
+    use crate::utils::DataMetrics;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_sys_slab() {
+        let root = TempDir::with_prefix("aperf_slab").unwrap();
+        for (dir, objects, total, size) in [
+            (":a-0000192", "97752 N0=97752", "100905 N0=100905", "192"),
+            ("inode_cache", "10", "20", "600"),
+        ] {
+            let cache = root.path().join(dir);
+            fs::create_dir(&cache).unwrap();
+            fs::write(cache.join("objects"), objects).unwrap();
+            fs::write(cache.join("total_objects"), total).unwrap();
+            fs::write(cache.join("slab_size"), size).unwrap();
+        }
+        symlink(root.path().join(":a-0000192"), root.path().join("dentry")).unwrap();
+        symlink(root.path().join(":a-0000192"), root.path().join("vm_area")).unwrap();
+
+        let data = read_sys_slab(root.path()).unwrap();
+        let mut lines: Vec<&str> = data.lines().collect();
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec!["dentry 97752 100905 192", "inode_cache 10 20 600"]
+        );
+    }
+
+    #[test]
+    fn test_get_growth() {
+        let samples = [
+            "slabinfo - version: 2.1\n# name <active_objs> <num_objs> <objsize>\n\
+             dentry 100 100 192 : tunables 0 0 0 : slabdata 5 5 0\n\
+             kmalloc-64 50 64 64 : tunables 0 0 0 : slabdata 1 1 0\n",
+            "dentry 1000 1050 192\nkmalloc-64 40 64 64\nnew_cache 7 8 1024\n",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(seconds, data)| {
+            Data::SlabinfoRaw(SlabinfoRaw {
+                time: sample_time(seconds as i64),
+                data: data.to_string(),
+            })
+        })
+        .collect();
+        let buffer = process_samples(Slabinfo::new, samples);
+        match &buffer[0] {
+            ProcessedData::Slabinfo(s) => assert_eq!(
+                s.caches[1],
+                SlabCache {
+                    name: "kmalloc-64".to_string(),
+                    active_objects: 50,
+                    objects: 64,
+                    object_size: 64,
+                }
+            ),
+            _ => unreachable!(),
+        }
+
+        let mut metrics = DataMetrics::new(String::new());
+        let json = get_data(
+            Slabinfo::new,
+            &buffer,
+            "get=values&key=active_objects",
+            &mut metrics,
+        )
+        .unwrap();
+        let growth: EndEntries = serde_json::from_str(&json).unwrap();
+        let names: Vec<&str> = growth.end_entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["dentry", "new_cache", "kmalloc-64"]);
+        assert_eq!(growth.end_entries[0].total, 900.0);
+        assert_eq!(growth.end_entries[0].entries.len(), 2);
+        assert_eq!(growth.end_entries[2].total, -10.0);
+
+        let json = get_data(
+            Slabinfo::new,
+            &buffer,
+            "get=values&key=memory",
+            &mut metrics,
+        )
+        .unwrap();
+        let growth: EndEntries = serde_json::from_str(&json).unwrap();
+        assert_eq!(growth.end_entries[0].name, "dentry");
+        assert_eq!(
+            growth.end_entries[0].total,
+            (1050.0 - 100.0) * 192.0 / 1024.0
+        );
+    }
+}
@@ -0,0 +1,304 @@
+extern crate ctor;
+
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::overhead::sample_every;
+use crate::utils::{add_metrics, DataMetrics, Metric};
+use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub static SNMP_FILE_NAME: &str = "snmp";
+
+/// Values that are not counters. They are plotted as read instead of as deltas.
+static SNMP_GAUGES: [&str; 7] = [
+    "Ip.Forwarding",
+    "Ip.DefaultTTL",
+    "Tcp.RtoAlgorithm",
+    "Tcp.RtoMin",
+    "Tcp.RtoMax",
+    "Tcp.MaxConn",
+    "Tcp.CurrEstab",
+];
+
+/// Gather /proc/net/snmp and, if IPv6 is enabled, /proc/net/snmp6.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnmpRaw {
+    pub time: TimeEnum,
+    pub snmp: String,
+    pub snmp6: String,
+}
+
+impl SnmpRaw {
+    fn new() -> Self {
+        SnmpRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            snmp: String::new(),
+            snmp6: String::new(),
+        }
+    }
+}
+
+impl CollectData for SnmpRaw {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.snmp = std::fs::read_to_string("/proc/net/snmp")?;
+        self.snmp6 = std::fs::read_to_string("/proc/net/snmp6").unwrap_or_default();
+        trace!("{:#?} {:#?}", self.snmp, self.snmp6);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snmp {
+    pub time: TimeEnum,
+    pub snmp_data: HashMap<String, i64>,
+}
+
+impl Snmp {
+    fn new() -> Self {
+        Snmp {
+            time: TimeEnum::DateTime(Utc::now()),
+            snmp_data: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SnmpEntry {
+    pub time: TimeEnum,
+    pub value: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndSnmpData {
+    pub data: Vec<SnmpEntry>,
+    pub metadata: GraphMetadata,
+}
+
+/// Parse the header and value line pairs of /proc/net/snmp, e.g. "Tcp: ... RetransSegs ..."
+/// followed by "Tcp: ... 12 ...", into keys such as "Tcp.RetransSegs".
+fn parse_snmp(data: &str, map: &mut HashMap<String, i64>) -> Result<()> {
+    let mut lines = data.lines();
+    while let (Some(header), Some(values)) = (lines.next(), lines.next()) {
+        let mut names = header.split_whitespace();
+        let mut values = values.split_whitespace();
+        let tag = names.next().ok_or(PDError::ProcessorOptionExtractError)?;
+        if values.next() != Some(tag) {
+            return Err(PDError::ProcessorOptionExtractError.into());
+        }
+        let tag = tag.trim_end_matches(':');
+        for name in names {
+            let value = values.next().ok_or(PDError::ProcessorOptionExtractError)?;
+            map.insert(format!("{}.{}", tag, name), value.parse()?);
+        }
+    }
+    Ok(())
+}
+
+/// Parse the name and value lines of /proc/net/snmp6, e.g. "Ip6InReceives 3", into keys such
+/// as "Ip6.InReceives".
+fn parse_snmp6(data: &str, map: &mut HashMap<String, i64>) -> Result<()> {
+    for line in data.lines() {
+        let (name, value) = line
+            .split_once(char::is_whitespace)
+            .ok_or(PDError::ProcessorOptionExtractError)?;
+        let key = match name.find('6') {
+            Some(i) => format!("{}.{}", &name[..=i], &name[i + 1..]),
+            None => name.to_string(),
+        };
+        map.insert(key, value.trim().parse()?);
+    }
+    Ok(())
+}
+
+fn get_entry(
+    values: Vec<Snmp>,
+    run: &str,
+    key: String,
+    metrics: &mut DataMetrics,
+) -> Result<String> {
+    let mut end_values = Vec::new();
+    let mut metric = Metric::new(key.clone());
+    let mut metadata = GraphMetadata::new();
+    let time_zero = values[0].time;
+    let gauge = SNMP_GAUGES.contains(&key.as_str());
+    let mut prev_value = None;
+    for value in values {
+        let curr_value = *value
+            .snmp_data
+            .get(&key)
+            .ok_or(PDError::VisualizerSnmpValueGetError(key.to_string()))?;
+        let snmp_value = match gauge {
+            true => curr_value,
+            false => {
+                (curr_value - prev_value.unwrap_or(curr_value)).max(0)
+                    / sample_every(run, SNMP_FILE_NAME, value.time) as i64
+            }
+        };
+        metric.insert_value(snmp_value as f64);
+        metadata.update_limits(GraphLimitType::UInt64(snmp_value.max(0) as u64));
+        end_values.push(SnmpEntry {
+            time: (value.time - time_zero),
+            value: snmp_value,
+        });
+        prev_value = Some(curr_value);
+    }
+    let snmp_data = EndSnmpData {
+        data: end_values,
+        metadata,
+    };
+    add_metrics(key, &mut metric, metrics, SNMP_FILE_NAME.to_string())?;
+    Ok(serde_json::to_string(&snmp_data)?)
+}
+
+fn get_entries(value: Snmp) -> Result<String> {
+    let mut keys: Vec<String> = value.snmp_data.into_keys().collect();
+    keys.sort();
+    Ok(serde_json::to_string(&keys)?)
+}
+
+impl GetData for Snmp {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::SnmpRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut snmp = Snmp::new();
+        parse_snmp(&raw_value.snmp, &mut snmp.snmp_data)?;
+        parse_snmp6(&raw_value.snmp6, &mut snmp.snmp_data)?;
+        snmp.time = raw_value.time;
+        Ok(ProcessedData::Snmp(snmp))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Snmp(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, run) = &param[0];
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => get_entries(values[0].clone()),
+            "values" => {
+                let (_, key) = &param[2];
+                get_entry(values, run, key.to_string(), metrics)
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_snmp() {
+    let snmp_raw = SnmpRaw::new();
+    let file_name = SNMP_FILE_NAME.to_string();
+    let dt = DataType::new(Data::SnmpRaw(snmp_raw.clone()), file_name.clone(), false);
+    let js_file_name = file_name.clone() + ".js";
+    let snmp = Snmp::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Snmp(snmp.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/snmp.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_snmp, parse_snmp6, EndSnmpData, Snmp, SnmpRaw};
+    use crate::data::test_utils::{get_data, process_samples, sample_time};
+    use crate::data::{CollectData, CollectorParams, Data};
+    use crate::utils::DataMetrics;
+    use std::collections::HashMap;
+
+    fn raw_data(seconds: i64, retrans: u64, curr_estab: u64) -> Data {
+        Data::SnmpRaw(SnmpRaw {
+            time: sample_time(seconds),
+            snmp: format!(
+                "Tcp: RtoAlgorithm MaxConn CurrEstab RetransSegs\n\
+                 Tcp: 1 -1 {} {}\n\
+                 Udp: InDatagrams RcvbufErrors\n\
+                 Udp: 100 0\n",
+                curr_estab, retrans
+            ),
+            snmp6: "Ip6InReceives                   \t3\nUdpLite6InErrors\t0\n".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let mut snmp = SnmpRaw::new();
+        let params = CollectorParams::new();
+
+        snmp.collect_data(&params).unwrap();
+        assert!(!snmp.snmp.is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut map = HashMap::new();
+        parse_snmp(
+            "Ip: Forwarding DefaultTTL\nIp: 2 64\nTcp: MaxConn RetransSegs\nTcp: -1 12\n",
+            &mut map,
+        )
+        .unwrap();
+        parse_snmp6("Ip6InReceives \t3\nIcmp6InType1\t7\n", &mut map).unwrap();
+        assert_eq!(map.get("Ip.DefaultTTL"), Some(&64));
+        assert_eq!(map.get("Tcp.MaxConn"), Some(&-1));
+        assert_eq!(map.get("Tcp.RetransSegs"), Some(&12));
+        assert_eq!(map.get("Ip6.InReceives"), Some(&3));
+        assert_eq!(map.get("Icmp6.InType1"), Some(&7));
+        assert!(parse_snmp("Ip: Forwarding\nTcp: 1\n", &mut map).is_err());
+        assert!(parse_snmp("Ip: Forwarding DefaultTTL\nIp: 2\n", &mut map).is_err());
+    }
+
+    #[test]
+    fn test_get_values() {
+        let buffer = process_samples(
+            Snmp::new,
+            vec![raw_data(0, 10, 5), raw_data(1, 25, 7), raw_data(2, 25, 3)],
+        );
+        let mut metrics = DataMetrics::new(String::new());
+        let get = |key: &str, metrics: &mut DataMetrics| {
+            let query = format!("get=values&key={}", key);
+            let json = get_data(Snmp::new, &buffer, &query, metrics).unwrap();
+            let data: EndSnmpData = serde_json::from_str(&json).unwrap();
+            data.data.iter().map(|e| e.value).collect::<Vec<i64>>()
+        };
+        assert_eq!(get("Tcp.RetransSegs", &mut metrics), vec![0, 15, 0]);
+        /* Gauges are plotted as read */
+        assert_eq!(get("Tcp.CurrEstab", &mut metrics), vec![5, 7, 3]);
+        assert_eq!(get("UdpLite6.InErrors", &mut metrics), vec![0, 0, 0]);
+        assert!(metrics.values["snmp"].contains_key("Tcp.RetransSegs"));
+    }
+}
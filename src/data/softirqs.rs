@@ -0,0 +1,309 @@
+extern crate ctor;
+
+use crate::data::interrupts::{
+    get_per_cpu_deltas, InterruptCPUData, InterruptLine, InterruptLineData,
+};
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::{add_metrics, DataMetrics, Metric};
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::trace;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub static SOFTIRQS_FILE_NAME: &str = "softirqs";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SoftirqsRaw {
+    pub time: TimeEnum,
+    pub data: String,
+}
+
+impl SoftirqsRaw {
+    fn new() -> Self {
+        SoftirqsRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            data: String::new(),
+        }
+    }
+}
+
+impl CollectData for SoftirqsRaw {
+    fn collect_data(&mut self, _params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.data = String::new();
+        self.data = std::fs::read_to_string("/proc/softirqs")?;
+        trace!("{:#?}", self.data);
+        Ok(())
+    }
+}
+
+/// Each softirq type, e.g. NET_RX, is kept as an interrupt line with its count per CPU.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Softirqs {
+    pub softirq_data: Vec<InterruptLineData>,
+}
+
+impl Softirqs {
+    fn new() -> Self {
+        Softirqs {
+            softirq_data: Vec::new(),
+        }
+    }
+}
+
+fn line_name(line_data: &InterruptLineData) -> String {
+    match &line_data.interrupt_line {
+        InterruptLine::InterruptStr(v) => v.clone(),
+        InterruptLine::InterruptNr(v) => v.to_string(),
+        InterruptLine::None => String::new(),
+    }
+}
+
+fn get_softirqs(value: Softirqs) -> Result<String> {
+    let keys: Vec<String> = value.softirq_data.iter().map(line_name).collect();
+    Ok(serde_json::to_string(&keys)?)
+}
+
+/// How many times its fair share of a softirq the busiest CPU handled over the run. 1 when the
+/// CPUs handled it evenly.
+fn get_imbalance(end_values: &[InterruptLineData]) -> Option<f64> {
+    let mut per_cpu: HashMap<u64, u64> = HashMap::new();
+    for value in end_values {
+        for cpu_data in &value.per_cpu {
+            *per_cpu.entry(cpu_data.cpu).or_insert(0) += cpu_data.count;
+        }
+    }
+    let total: u64 = per_cpu.values().sum();
+    let busiest = per_cpu.values().max()?;
+    if total == 0 {
+        return None;
+    }
+    Some(*busiest as f64 * per_cpu.len() as f64 / total as f64)
+}
+
+fn get_softirq_data(
+    values: Vec<Softirqs>,
+    run: &str,
+    key: String,
+    metrics: &mut DataMetrics,
+) -> Result<String> {
+    let key_values: Vec<InterruptLineData> = values
+        .into_iter()
+        .flat_map(|v| v.softirq_data)
+        .filter(|l| line_name(l) == key)
+        .collect();
+    if key_values.is_empty() {
+        return Err(PDError::VisualizerSoftirqValueGetError(key).into());
+    }
+    let end_values = get_per_cpu_deltas(key_values, run, SOFTIRQS_FILE_NAME)?;
+    if let Some(imbalance) = get_imbalance(&end_values) {
+        let mut metric = Metric::new(format!("{} imbalance", key));
+        metric.insert_value(imbalance);
+        add_metrics(
+            metric.name.clone(),
+            &mut metric,
+            metrics,
+            SOFTIRQS_FILE_NAME.to_string(),
+        )?;
+    }
+    Ok(serde_json::to_string(&end_values)?)
+}
+
+impl GetData for Softirqs {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::SoftirqsRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut lines = raw_value.data.lines();
+
+        /* Collect the CPUs from the 1st line */
+        let cpus_nr = lines
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|cpu| cpu.trim_start_matches("CPU").parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>()?;
+
+        let mut softirqs = Softirqs::new();
+        for line in lines {
+            let (name, counts) = line
+                .split_once(':')
+                .ok_or(PDError::ProcessorOptionExtractError)?;
+            let mut per_cpu = Vec::new();
+            for (cpu, count) in cpus_nr.iter().zip(counts.split_whitespace()) {
+                per_cpu.push(InterruptCPUData {
+                    cpu: *cpu,
+                    count: count.parse()?,
+                });
+            }
+            softirqs.softirq_data.push(InterruptLineData {
+                time: raw_value.time,
+                interrupt_line: InterruptLine::InterruptStr(name.trim().to_string()),
+                interrupt_type: String::new(),
+                interrupt_device: String::new(),
+                per_cpu,
+            });
+        }
+        Ok(ProcessedData::Softirqs(softirqs))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["keys".to_string(), "values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Softirqs(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, run) = &param[0];
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "keys" => get_softirqs(values[0].clone()),
+            "values" => {
+                let (_, key) = &param[2];
+                get_softirq_data(values, run, key.to_string(), metrics)
+            }
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_softirqs() {
+    let softirqs_raw = SoftirqsRaw::new();
+    let file_name = SOFTIRQS_FILE_NAME.to_string();
+    let dt = DataType::new(
+        Data::SoftirqsRaw(softirqs_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    let js_file_name = file_name.clone() + ".js";
+    let softirqs = Softirqs::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Softirqs(softirqs.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/softirqs.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Softirqs, SoftirqsRaw};
+    use crate::data::interrupts::InterruptLineData;
+    use crate::data::test_utils::{get_data, get_keys, process_samples, sample_time};
+    use crate::data::{CollectData, CollectorParams, Data};
+    use crate::utils::{DataMetrics, ValueType};
+
+    fn raw_data(seconds: i64, net_rx: [u64; 4]) -> Data {
+        Data::SoftirqsRaw(SoftirqsRaw {
+            time: sample_time(seconds),
+            data: format!(
+                "                    CPU0       CPU1       CPU2       CPU3\n          \
+                 HI:          0          0          0          0\n       \
+                 TIMER:     104967     100000     100000     100000\n      \
+                 NET_RX:    {}    {}    {}    {}\n",
+                net_rx[0], net_rx[1], net_rx[2], net_rx[3]
+            ),
+        })
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let mut softirqs = SoftirqsRaw::new();
+        let params = CollectorParams::new();
+
+        softirqs.collect_data(&params).unwrap();
+        assert!(softirqs.data.contains("NET_RX"));
+    }
+
+    #[test]
+    fn test_get_values() {
+        let buffer = process_samples(
+            Softirqs::new,
+            vec![raw_data(0, [0, 0, 0, 0]), raw_data(1, [700, 100, 100, 100])],
+        );
+        assert_eq!(
+            get_keys(Softirqs::new, &buffer),
+            vec!["HI", "TIMER", "NET_RX"]
+        );
+
+        let mut metrics = DataMetrics::new(String::new());
+        let json = get_data(
+            Softirqs::new,
+            &buffer,
+            "get=values&key=NET_RX",
+            &mut metrics,
+        )
+        .unwrap();
+        let data: Vec<InterruptLineData> = serde_json::from_str(&json).unwrap();
+        let counts: Vec<u64> = data[1].per_cpu.iter().map(|c| c.count).collect();
+        assert_eq!(counts, vec![700, 100, 100, 100]);
+        assert_eq!(data[1].per_cpu[3].cpu, 3);
+
+        /* CPU0 handled 70% of NET_RX, 2.8 times its share of 25% */
+        match metrics.values["softirqs"]["NET_RX imbalance"] {
+            ValueType::Stats(ref stats) => assert!((stats.mean - 2.8).abs() < 1e-9),
+            _ => unreachable!(),
+        }
+        /* HI never fired */
+        get_data(Softirqs::new, &buffer, "get=values&key=HI", &mut metrics).unwrap();
+        assert!(!metrics.values["softirqs"].contains_key("HI imbalance"));
+        assert!(get_data(
+            Softirqs::new,
+            &buffer,
+            "get=values&key=NET_TX",
+            &mut metrics
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_counter_wrap() {
+        let buffer = process_samples(
+            Softirqs::new,
+            vec![
+                raw_data(0, [4_294_967_000, 0, 0, 0]),
+                raw_data(1, [200, 100, 100, 100]),
+            ],
+        );
+        let json = get_data(
+            Softirqs::new,
+            &buffer,
+            "get=values&key=NET_RX",
+            &mut DataMetrics::new(String::new()),
+        )
+        .unwrap();
+        let data: Vec<InterruptLineData> = serde_json::from_str(&json).unwrap();
+        /* CPU0 wrapped around 2^32 = 4294967296 */
+        assert_eq!(data[1].per_cpu[0].count, 496);
+        assert_eq!(data[1].per_cpu[1].count, 100);
+    }
+}
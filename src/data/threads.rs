@@ -0,0 +1,387 @@
+extern crate ctor;
+
+use crate::data::processes::{
+    keep_top_entries, PROC_PID_STAT_KERNELSPACE_TIME_POS, PROC_PID_STAT_USERSPACE_TIME_POS,
+};
+use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::utils::DataMetrics;
+use crate::visualizer::{DataVisualizer, GetData};
+use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+pub static THREADS_FILE_NAME: &str = "threads";
+
+/// The /proc/<pid>/task/<tid>/stat of every thread of the processes given with --threads.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThreadsRaw {
+    pub time: TimeEnum,
+    pub ticks_per_second: u64,
+    pub data: String,
+}
+
+impl ThreadsRaw {
+    fn new() -> Self {
+        ThreadsRaw {
+            time: TimeEnum::DateTime(Utc::now()),
+            ticks_per_second: 0,
+            data: String::new(),
+        }
+    }
+}
+
+/// The PIDs in `proc_dir` matching a target, by PID or by process name.
+fn matching_pids(proc_dir: &Path, targets: &[&str]) -> Result<Vec<String>> {
+    let by_name = targets.iter().any(|t| !t.chars().all(char::is_numeric));
+    let mut pids = Vec::new();
+    for entry in fs::read_dir(proc_dir)? {
+        let entry = entry?;
+        let pid = entry.file_name().to_string_lossy().to_string();
+        if !pid.chars().all(char::is_numeric) {
+            continue;
+        }
+        if targets.contains(&pid.as_str()) {
+            pids.push(pid);
+            continue;
+        }
+        if by_name {
+            let comm = fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+            if targets.contains(&comm.trim()) {
+                pids.push(pid);
+            }
+        }
+    }
+    Ok(pids)
+}
+
+fn get_targets(params: &CollectorParams) -> Vec<&str> {
+    match params.profile.get(THREADS_FILE_NAME) {
+        Some(targets) => targets
+            .split(',')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+impl CollectData for ThreadsRaw {
+    fn prepare_data_collector(&mut self, params: &CollectorParams) -> Result<()> {
+        let targets = get_targets(params);
+        if targets.is_empty() {
+            return Err(PDError::CollectorThreadsNoTarget.into());
+        }
+        /* The processes can start after the recording does */
+        if matching_pids(Path::new("/proc"), &targets)?.is_empty() {
+            warn!("No process matches --threads {}", targets.join(","));
+        }
+        self.ticks_per_second = procfs::ticks_per_second()? as u64;
+        Ok(())
+    }
+
+    fn collect_data(&mut self, params: &CollectorParams) -> Result<()> {
+        self.time = TimeEnum::DateTime(Utc::now());
+        self.data = String::new();
+        let proc_dir = Path::new("/proc");
+        for pid in matching_pids(proc_dir, &get_targets(params))? {
+            let task_dir = match fs::read_dir(proc_dir.join(&pid).join("task")) {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            for task in task_dir {
+                if let Ok(v) = fs::read_to_string(task?.path().join("stat")) {
+                    self.data.push_str(&v);
+                }
+            }
+        }
+        trace!("{:#?}", self.data);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThreadSample {
+    /// The thread name with the number of a pooled thread replaced, e.g. "worker-*".
+    pub name: String,
+    pub tid: u64,
+    pub cpu_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Threads {
+    pub time: TimeEnum,
+    pub ticks_per_second: u64,
+    pub entries: Vec<ThreadSample>,
+}
+
+impl Threads {
+    fn new() -> Self {
+        Threads {
+            time: TimeEnum::DateTime(Utc::now()),
+            ticks_per_second: 0,
+            entries: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ThreadGroupSample {
+    pub time: TimeEnum,
+    /// Percent of one CPU.
+    pub value: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ThreadGroup {
+    pub name: String,
+    /// Most threads of the group seen in a sample.
+    pub threads: usize,
+    /// Seconds of CPU time.
+    pub total: f64,
+    pub samples: Vec<ThreadGroupSample>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct EndThreadsData {
+    pub collection_time: TimeEnum,
+    pub groups: Vec<ThreadGroup>,
+}
+
+/// Group the threads of a pool under one name by replacing the number ending their name, e.g.
+/// "worker-12" and "GC Thread#3" become "worker-*" and "GC Thread#*".
+pub fn normalize_thread_name(name: &str) -> String {
+    let trimmed = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    match trimmed.len() == name.len() {
+        true => name.to_string(),
+        false => format!("{}*", trimmed),
+    }
+}
+
+/// CPU used by each thread group per interval. A thread that was not in the previous sample
+/// started during the interval, all its CPU time is counted.
+fn get_groups(values: Vec<Threads>) -> Result<String> {
+    let time_zero = values[0].time;
+    let mut total_time = 1;
+    if let TimeEnum::TimeDiff(v) = values.last().unwrap().time - time_zero {
+        total_time = v.max(1);
+    }
+    let mut groups: BTreeMap<String, ThreadGroup> = BTreeMap::new();
+    for pair in values.windows(2) {
+        let (prev, value) = (&pair[0], &pair[1]);
+        let ms = match value.time - prev.time {
+            TimeEnum::TimeDiff(ms) if ms > 0 => ms,
+            _ => continue,
+        };
+        let prev_times: HashMap<u64, u64> =
+            prev.entries.iter().map(|e| (e.tid, e.cpu_time)).collect();
+        let mut ticks: BTreeMap<&String, (u64, usize)> = BTreeMap::new();
+        for entry in &value.entries {
+            let delta = entry
+                .cpu_time
+                .saturating_sub(*prev_times.get(&entry.tid).unwrap_or(&0));
+            let group = ticks.entry(&entry.name).or_insert((0, 0));
+            group.0 += delta;
+            group.1 += 1;
+        }
+        let ticks_per_second = value.ticks_per_second.max(1) as f64;
+        for (name, (delta, threads)) in ticks {
+            let group = groups.entry(name.clone()).or_insert(ThreadGroup {
+                name: name.clone(),
+                threads: 0,
+                total: 0.0,
+                samples: Vec::new(),
+            });
+            let seconds = delta as f64 / ticks_per_second;
+            group.threads = group.threads.max(threads);
+            group.total += seconds;
+            group.samples.push(ThreadGroupSample {
+                time: value.time - time_zero,
+                value: seconds * 100.0 * 1000.0 / ms as f64,
+            });
+        }
+    }
+    let mut end_groups: Vec<ThreadGroup> = groups.into_values().collect();
+    keep_top_entries(&mut end_groups, |g| g.total);
+    Ok(serde_json::to_string(&EndThreadsData {
+        collection_time: TimeEnum::TimeDiff(total_time),
+        groups: end_groups,
+    })?)
+}
+
+impl GetData for Threads {
+    fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
+        let raw_value = match buffer {
+            Data::ThreadsRaw(ref value) => value,
+            _ => return Err(PDError::InvalidRawDataType.into()),
+        };
+        let mut threads = Threads::new();
+        threads.time = raw_value.time;
+        threads.ticks_per_second = raw_value.ticks_per_second;
+        for line in raw_value.data.lines() {
+            /* The name can hold spaces and parentheses, it ends at the last ')' */
+            let (open_pos, close_pos) = match (line.find('('), line.rfind(')')) {
+                (Some(o), Some(c)) if o < c => (o, c),
+                _ => continue,
+            };
+            let values: Vec<&str> = line[close_pos + 1..].split_whitespace().collect();
+            if values.len() < PROC_PID_STAT_KERNELSPACE_TIME_POS + 1 {
+                continue;
+            }
+            threads.entries.push(ThreadSample {
+                name: normalize_thread_name(&line[open_pos + 1..close_pos]),
+                tid: line[..open_pos].trim().parse()?,
+                cpu_time: values[PROC_PID_STAT_USERSPACE_TIME_POS].parse::<u64>()?
+                    + values[PROC_PID_STAT_KERNELSPACE_TIME_POS].parse::<u64>()?,
+            });
+        }
+        Ok(ProcessedData::Threads(threads))
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        query: String,
+        _metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Threads(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, req_str) = &param[1];
+
+        match req_str.as_str() {
+            "values" => get_groups(values),
+            _ => panic!("Unsupported API"),
+        }
+    }
+}
+
+#[ctor]
+fn init_threads() {
+    let threads_raw = ThreadsRaw::new();
+    let file_name = THREADS_FILE_NAME.to_string();
+    let mut dt = DataType::new(
+        Data::ThreadsRaw(threads_raw.clone()),
+        file_name.clone(),
+        false,
+    );
+    dt.is_profile_option();
+    let js_file_name = file_name.clone() + ".js";
+    let threads = Threads::new();
+    let dv = DataVisualizer::new(
+        ProcessedData::Threads(threads.clone()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/threads.js")).to_string(),
+        file_name.clone(),
+    );
+
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .add_datatype(file_name.clone(), dt);
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name.clone(), dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        matching_pids, normalize_thread_name, EndThreadsData, Threads, ThreadsRaw,
+        THREADS_FILE_NAME,
+    };
+    use crate::data::test_utils::{get_data, process_samples, sample_time};
+    use crate::data::{CollectData, CollectorParams, Data};
+    use crate::utils::DataMetrics;
+    use std::path::Path;
+
+    fn stat_line(tid: u64, name: &str, utime: u64) -> String {
+        format!(
+            "{} ({}) S 1 100 100 0 -1 1077936192 0 0 0 0 {} 0 0 0 20 0 30 0 100 0 0\n",
+            tid, name, utime
+        )
+    }
+
+    #[test]
+    fn test_normalize_thread_name() {
+        assert_eq!(normalize_thread_name("worker-12"), "worker-*");
+        assert_eq!(normalize_thread_name("GC Thread#3"), "GC Thread#*");
+        assert_eq!(normalize_thread_name("pool-1-thread-1"), "pool-1-thread-*");
+        assert_eq!(normalize_thread_name("java"), "java");
+        assert_eq!(normalize_thread_name("C2 CompilerThre"), "C2 CompilerThre");
+    }
+
+    #[test]
+    fn test_collect_data() {
+        let pid = std::process::id().to_string();
+        assert_eq!(
+            matching_pids(Path::new("/proc"), &[pid.as_str()]).unwrap(),
+            vec![pid.clone()]
+        );
+        let mut threads = ThreadsRaw::new();
+        let mut params = CollectorParams::new();
+        params
+            .profile
+            .insert(THREADS_FILE_NAME.to_string(), format!("{},", pid));
+        threads.prepare_data_collector(&params).unwrap();
+        threads.collect_data(&params).unwrap();
+        assert!(!threads.data.is_empty());
+
+        params.profile.clear();
+        assert!(threads.prepare_data_collector(&params).is_err());
+    }
+
+    #[test]
+    fn test_get_values() {
+        let samples = [
+            stat_line(100, "java", 10) + &stat_line(101, "worker-1", 0),
+            /* worker-2 started during the interval, worker-1 used 50 ticks */
+            stat_line(100, "java", 10)
+                + &stat_line(101, "worker-1", 50)
+                + &stat_line(102, "worker-2", 30)
+                + &stat_line(103, "(sd-pam) x", 5),
+        ];
+        let samples = samples
+            .into_iter()
+            .enumerate()
+            .map(|(seconds, data)| {
+                Data::ThreadsRaw(ThreadsRaw {
+                    time: sample_time(seconds as i64),
+                    ticks_per_second: 100,
+                    data,
+                })
+            })
+            .collect();
+        let buffer = process_samples(Threads::new, samples);
+        let json = get_data(
+            Threads::new,
+            &buffer,
+            "get=values",
+            &mut DataMetrics::new(String::new()),
+        )
+        .unwrap();
+        let data: EndThreadsData = serde_json::from_str(&json).unwrap();
+        let names: Vec<&str> = data.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["worker-*", "(sd-pam) x", "java"]);
+        assert_eq!(data.groups[0].threads, 2);
+        assert!((data.groups[0].samples[0].value - 80.0).abs() < 1e-9);
+        assert!((data.groups[0].total - 0.8).abs() < 1e-9);
+    }
+}
@@ -7,6 +7,9 @@ use std::path::PathBuf;
 pub struct CpuInfo {
     pub vendor: String,
     pub model_name: String,
+    pub microcode: String,
+    /// The "flags" on x86, the "Features" on arm64.
+    pub flags: Vec<String>,
 }
 
 impl CpuInfo {
@@ -14,6 +17,8 @@ impl CpuInfo {
         CpuInfo {
             vendor: String::new(),
             model_name: String::new(),
+            microcode: String::new(),
+            flags: Vec::new(),
         }
     }
 }
@@ -36,6 +41,10 @@ pub fn get_cpu_info() -> Result<CpuInfo> {
         match key.as_str() {
             "vendor_id" => cpu_info.vendor = value,
             "model name" => cpu_info.model_name = value,
+            "microcode" => cpu_info.microcode = value,
+            "flags" | "Features" => {
+                cpu_info.flags = value.split_whitespace().map(|f| f.to_string()).collect()
+            }
             _ => {}
         }
     }
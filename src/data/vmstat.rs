@@ -1,6 +1,7 @@
 extern crate ctor;
 
 use crate::data::{CollectData, CollectorParams, Data, DataType, ProcessedData, TimeEnum};
+use crate::overhead::sample_every;
 use crate::utils::{add_metrics, DataMetrics, Metric};
 use crate::visualizer::{DataVisualizer, GetData, GraphLimitType, GraphMetadata};
 use crate::{PDError, PERFORMANCE_DATA, VISUALIZATION_DATA};
@@ -74,7 +75,12 @@ struct VmstatEntry {
     pub value: i64,
 }
 
-fn get_entry(values: Vec<Vmstat>, key: String, metrics: &mut DataMetrics) -> Result<String> {
+fn get_entry(
+    values: Vec<Vmstat>,
+    run: &str,
+    key: String,
+    metrics: &mut DataMetrics,
+) -> Result<String> {
     let mut end_values = Vec::new();
     let mut metric = Metric::new(key.clone());
     let mut metadata = GraphMetadata::new();
@@ -95,7 +101,8 @@ fn get_entry(values: Vec<Vmstat>, key: String, metrics: &mut DataMetrics) -> Res
 
         let mut v = *curr_value;
         if !key.contains("nr_") {
-            v = *curr_value - *prev_value;
+            v = (*curr_value - *prev_value)
+                / sample_every(run, VMSTAT_FILE_NAME, current_time) as i64;
         }
         metadata.update_limits(GraphLimitType::UInt64(v as u64));
         let vmstat_entry = VmstatEntry {
@@ -124,7 +131,7 @@ impl GetData for Vmstat {
     fn process_raw_data(&mut self, buffer: Data) -> Result<ProcessedData> {
         let raw_value = match buffer {
             Data::VmstatRaw(ref value) => value,
-            _ => panic!("Invalid Data type in raw file"),
+            _ => return Err(PDError::InvalidRawDataType.into()),
         };
         let mut vmstat = Vmstat::new();
         let reader = BufReader::new(raw_value.data.as_bytes());
@@ -160,13 +167,14 @@ impl GetData for Vmstat {
             }
         }
         let param: Vec<(String, String)> = serde_urlencoded::from_str(&query).unwrap();
+        let (_, run) = &param[0];
         let (_, req_str) = &param[1];
 
         match req_str.as_str() {
             "keys" => get_entries(values[0].clone()),
             "values" => {
                 let (_, key) = &param[2];
-                get_entry(values, key.to_string(), metrics)
+                get_entry(values, run, key.to_string(), metrics)
             }
             _ => panic!("Unsupported API"),
         }
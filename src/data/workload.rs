@@ -0,0 +1,370 @@
+extern crate ctor;
+
+use crate::data::ProcessedData;
+use crate::utils::DataMetrics;
+use crate::visualizer::{DataVisualizer, GetData, ReportParams};
+use crate::VISUALIZATION_DATA;
+use anyhow::Result;
+use chrono::prelude::*;
+use ctor::ctor;
+use log::{info, trace, warn};
+use nix::sys::signal::{self, SigSet, Signal};
+use nix::unistd::Pid;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub static WORKLOAD_FILE_NAME: &str = "aperf_workload";
+
+/// Number of trailing stdout/stderr lines of the workload kept in the run directory.
+const OUTPUT_TAIL_LINES: usize = 100;
+
+/// How long a workload gets to exit after SIGTERM before it is killed.
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the output to close once the workload is gone. A process which left
+/// the workload's process group, such as a daemon, can keep the pipes open indefinitely.
+const OUTPUT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Details of the command launched by `aperf record -- <command>`.
+///
+/// Offsets are in milliseconds from the first collection tick, matching TimeEnum::TimeDiff.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Workload {
+    pub command: Vec<String>,
+    pub pid: u32,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub start_offset: u64,
+    pub end_offset: Option<u64>,
+    pub exit_code: Option<i32>,
+    pub exit_signal: Option<i32>,
+    pub terminated_by_aperf: bool,
+    pub stdout_tail: Vec<String>,
+    pub stderr_tail: Vec<String>,
+}
+
+impl Workload {
+    pub fn new() -> Self {
+        Workload {
+            command: Vec::new(),
+            pid: 0,
+            start_time: Utc::now(),
+            end_time: None,
+            start_offset: 0,
+            end_offset: None,
+            exit_code: None,
+            exit_signal: None,
+            terminated_by_aperf: false,
+            stdout_tail: Vec::new(),
+            stderr_tail: Vec::new(),
+        }
+    }
+}
+
+impl Default for Workload {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type OutputTail = Arc<Mutex<VecDeque<String>>>;
+
+/// A running workload. Its output is passed through to aperf's stdout/stderr while
+/// the last OUTPUT_TAIL_LINES lines of each are kept for the run directory.
+///
+/// The workload runs in its own process group so that the processes it starts are
+/// stopped with it.
+pub struct WorkloadRunner {
+    child: Child,
+    collection_start: DateTime<Utc>,
+    output_readers: Vec<(JoinHandle<()>, OutputTail)>,
+    pub workload: Workload,
+}
+
+fn tail_output<R, W>(input: R, mut output: W) -> (JoinHandle<()>, OutputTail)
+where
+    R: Read + Send + 'static,
+    W: Write + Send + 'static,
+{
+    let tail: OutputTail = Arc::new(Mutex::new(VecDeque::with_capacity(OUTPUT_TAIL_LINES)));
+    let thread_tail = tail.clone();
+    let handle = thread::spawn(move || {
+        let mut reader = BufReader::new(input);
+        let mut line = Vec::new();
+        while let Ok(n) = reader.read_until(b'\n', &mut line) {
+            if n == 0 {
+                break;
+            }
+            let _ = output.write_all(&line);
+            let mut tail = thread_tail.lock().unwrap();
+            if tail.len() == OUTPUT_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(String::from_utf8_lossy(&line).trim_end().to_string());
+            line.clear();
+        }
+    });
+    (handle, tail)
+}
+
+impl WorkloadRunner {
+    pub fn spawn(command: &[String], collection_start: DateTime<Utc>) -> Result<Self> {
+        let mut cmd = Command::new(&command[0]);
+        cmd.args(&command[1..])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0);
+        /*
+         * The collection loop blocks SIGINT, SIGTERM and SIGCHLD to read them from a
+         * signalfd. The signal mask survives exec, so clear it for the workload.
+         */
+        unsafe {
+            cmd.pre_exec(|| SigSet::all().thread_unblock().map_err(std::io::Error::from));
+        }
+        let mut child = cmd.spawn()?;
+        let start_time = Utc::now();
+        let output_readers = vec![
+            tail_output(child.stdout.take().unwrap(), std::io::stdout()),
+            tail_output(child.stderr.take().unwrap(), std::io::stderr()),
+        ];
+        let mut workload = Workload::new();
+        workload.command = command.to_vec();
+        workload.pid = child.id();
+        workload.start_time = start_time;
+        workload.start_offset = (start_time - collection_start).num_milliseconds() as u64;
+        info!(
+            "Started workload '{}' (PID {})",
+            command.join(" "),
+            child.id()
+        );
+
+        Ok(WorkloadRunner {
+            child,
+            collection_start,
+            output_readers,
+            workload,
+        })
+    }
+
+    fn set_exit_status(&mut self, status: ExitStatus) {
+        let end_time = Utc::now();
+        self.workload.end_time = Some(end_time);
+        self.workload.end_offset =
+            Some((end_time - self.collection_start).num_milliseconds() as u64);
+        self.workload.exit_code = status.code();
+        self.workload.exit_signal = status.signal();
+        info!("Workload (PID {}) exited: {}", self.workload.pid, status);
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.workload.end_time.is_some()
+    }
+
+    /// Reap the workload if it has exited. Called when SIGCHLD is received, which
+    /// may also be for one of the collectors' child processes.
+    pub fn try_wait(&mut self) -> Result<bool> {
+        if self.has_exited() {
+            return Ok(true);
+        }
+        match self.child.try_wait()? {
+            Some(status) => {
+                self.set_exit_status(status);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Whether any process is left in the workload's process group.
+    fn group_is_alive(&self) -> bool {
+        signal::killpg(Pid::from_raw(self.workload.pid as i32), None).is_ok()
+    }
+
+    /// Stop the workload and anything left in its process group, sending SIGTERM first
+    /// and SIGKILL to what is still running after TERMINATE_TIMEOUT.
+    pub fn terminate(&mut self) -> Result<()> {
+        let group = Pid::from_raw(self.workload.pid as i32);
+        if !self.try_wait()? {
+            info!("Terminating workload (PID {})", self.workload.pid);
+            self.workload.terminated_by_aperf = true;
+        } else if !self.group_is_alive() {
+            return Ok(());
+        }
+        /* The group is gone once every process in it has exited */
+        let _ = signal::killpg(group, Signal::SIGTERM);
+        let deadline = Instant::now() + TERMINATE_TIMEOUT;
+        while Instant::now() < deadline {
+            if self.try_wait()? && !self.group_is_alive() {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        warn!(
+            "Workload (PID {}) did not exit on SIGTERM, killing its process group",
+            self.workload.pid
+        );
+        let _ = signal::killpg(group, Signal::SIGKILL);
+        if !self.has_exited() {
+            let status = self.child.wait()?;
+            self.set_exit_status(status);
+        }
+        Ok(())
+    }
+
+    /// Collect the output tails and write the workload details into the run directory.
+    pub fn write_to_file(mut self, dir: &Path) -> Result<()> {
+        self.terminate()?;
+        let deadline = Instant::now() + OUTPUT_TIMEOUT;
+        while self.output_readers.iter().any(|(h, _)| !h.is_finished()) {
+            if Instant::now() >= deadline {
+                /* Leave the readers behind, they end with aperf */
+                warn!("Workload output is still open, keeping what was read so far");
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let mut tails: Vec<Vec<String>> = self
+            .output_readers
+            .drain(..)
+            .map(|(_, tail)| tail.lock().unwrap().iter().cloned().collect())
+            .collect();
+        self.workload.stderr_tail = tails.pop().unwrap_or_default();
+        self.workload.stdout_tail = tails.pop().unwrap_or_default();
+        let path = dir.join(format!("{}.json", WORKLOAD_FILE_NAME));
+        trace!("Writing workload details to {}", path.display());
+        fs::write(path, serde_json::to_string_pretty(&self.workload)?)?;
+        Ok(())
+    }
+}
+
+impl GetData for Workload {
+    fn custom_raw_data_parser(&mut self, params: ReportParams) -> Result<Vec<ProcessedData>> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .open(params.data_file_path)?;
+        let workload: Workload = serde_json::from_reader(file)?;
+        Ok(vec![ProcessedData::Workload(workload)])
+    }
+
+    fn get_calls(&mut self) -> Result<Vec<String>> {
+        Ok(vec!["values".to_string()])
+    }
+
+    fn get_data(
+        &mut self,
+        buffer: Vec<ProcessedData>,
+        _query: String,
+        _metrics: &mut DataMetrics,
+    ) -> Result<String> {
+        let mut values = Vec::new();
+        for data in buffer {
+            match data {
+                ProcessedData::Workload(ref value) => values.push(value.clone()),
+                _ => unreachable!(),
+            }
+        }
+        Ok(serde_json::to_string(&values)?)
+    }
+}
+
+#[ctor]
+fn init_workload() {
+    let file_name = WORKLOAD_FILE_NAME.to_string();
+    let js_file_name = file_name.clone() + ".js";
+    let mut dv = DataVisualizer::new(
+        ProcessedData::Workload(Workload::new()),
+        file_name.clone(),
+        js_file_name,
+        include_str!(concat!(env!("JS_DIR"), "/aperf_workload.js")).to_string(),
+        file_name.clone(),
+    );
+    dv.has_custom_raw_data_parser();
+
+    VISUALIZATION_DATA
+        .lock()
+        .unwrap()
+        .add_visualizer(file_name, dv);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Workload, WorkloadRunner};
+    use crate::utils::DataMetrics;
+    use crate::visualizer::{GetData, ReportParams};
+    use chrono::prelude::*;
+    use nix::sys::signal;
+    use nix::unistd::Pid;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_background_child_is_stopped() {
+        let dir = TempDir::with_prefix("aperf_workload").unwrap();
+        let command: Vec<String> = ["sh", "-c", "sleep 60 & echo $!; echo done"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut runner = WorkloadRunner::spawn(&command, Utc::now()).unwrap();
+        while !runner.try_wait().unwrap() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let start = Instant::now();
+        runner.write_to_file(dir.path()).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        let workload: Workload = serde_json::from_str(
+            &fs::read_to_string(dir.path().join("aperf_workload.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(workload.exit_code, Some(0));
+        assert!(!workload.terminated_by_aperf);
+        assert_eq!(workload.stdout_tail[1], "done");
+        let sleep_pid: i32 = workload.stdout_tail[0].parse().unwrap();
+        assert!(signal::kill(Pid::from_raw(sleep_pid), None).is_err());
+    }
+
+    #[test]
+    fn test_workload_parse() {
+        let dir = TempDir::with_prefix("aperf_workload").unwrap();
+        let mut workload = Workload::new();
+        workload.command = vec!["sleep".to_string(), "1".to_string()];
+        workload.pid = 42;
+        workload.end_offset = Some(1000);
+        workload.exit_code = Some(0);
+        let path = dir.path().join("aperf_workload.json");
+        fs::write(&path, serde_json::to_string(&workload).unwrap()).unwrap();
+
+        let params = ReportParams {
+            data_dir: dir.path().to_path_buf(),
+            tmp_dir: PathBuf::new(),
+            report_dir: PathBuf::new(),
+            run_name: String::new(),
+            data_file_path: path,
+        };
+        let mut w = Workload::new();
+        let processed = w.custom_raw_data_parser(params).unwrap();
+        let ret = w
+            .get_data(
+                processed,
+                String::new(),
+                &mut DataMetrics::new(String::new()),
+            )
+            .unwrap();
+        let values: Vec<Workload> = serde_json::from_str(&ret).unwrap();
+        assert_eq!(values[0].command, vec!["sleep", "1"]);
+        assert_eq!(values[0].pid, 42);
+        assert_eq!(values[0].end_offset, Some(1000));
+        assert_eq!(values[0].exit_code, Some(0));
+    }
+}
@@ -75,5 +75,14 @@ let all_rules: Rules[] = [
     diskstats_rules,
     meminfo_rules,
     netstat_rules,
+    net_dev_rules,
+    snmp_rules,
+    softirqs_rules,
     vmstat_rules,
+    pressure_rules,
+    numa_rules,
+    cpufreq_rules,
+    filesystem_usage_rules,
+    mounts_rules,
+    hardware_topology_rules,
 ];
@@ -0,0 +1,54 @@
+/* Markers of a run, added with `aperf mark` or when a workload starts and exits. */
+function get_markers(run) {
+    if (typeof aperf_markers_raw_data === 'undefined') {
+        return [];
+    }
+    for (let i = 0; i < aperf_markers_raw_data['runs'].length; i++) {
+        let this_run_data = aperf_markers_raw_data['runs'][i];
+        if (this_run_data['name'] != run || this_run_data['collected'] === false) {
+            continue;
+        }
+        let values = this_run_data['key_values']['values'];
+        if (values === undefined || values == "No data collected") {
+            return [];
+        }
+        return JSON.parse(values);
+    }
+    return [];
+}
+
+/* Draws the markers of a run on a time-series graph as labelled vertical lines. */
+function add_markers(run, layout) {
+    let markers = get_markers(run);
+    if (markers.length == 0) {
+        return layout;
+    }
+    layout.shapes = markers.map(function (marker) {
+        let x = time_diff_seconds(marker.time);
+        return {
+            type: 'line',
+            xref: 'x',
+            yref: 'paper',
+            x0: x,
+            x1: x,
+            y0: 0,
+            y1: 1,
+            line: { color: 'grey', width: 1, dash: 'dot' },
+        };
+    });
+    layout.annotations = markers.map(function (marker) {
+        return {
+            x: time_diff_seconds(marker.time),
+            y: 1,
+            xref: 'x',
+            yref: 'paper',
+            text: marker.label,
+            showarrow: false,
+            textangle: -90,
+            xanchor: 'right',
+            yanchor: 'top',
+            font: { size: 10 },
+        };
+    });
+    return layout;
+}
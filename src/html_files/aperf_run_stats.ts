@@ -6,7 +6,7 @@ let aperfstats_rules = {
     rules: []
 }
 
-function getAperfEntry(elem, key, run_data) {
+function getAperfEntry(elem, key, run_data, run) {
     var value = JSON.parse(run_data);
     let collect = value.collect;
     let print = value.print;
@@ -15,11 +15,11 @@ function getAperfEntry(elem, key, run_data) {
     let x_print = [];
     let y_print = [];
     for (var i = 0; i < collect.length; i++) {
-        x_collect.push(collect[i].time.TimeDiff);
+        x_collect.push(time_diff_seconds(collect[i].time));
         y_collect.push(collect[i].time_taken);
     }
     for (var i = 0; i < print.length; i++) {
-        x_print.push(print[i].time.TimeDiff);
+        x_print.push(time_diff_seconds(print[i].time));
         y_print.push(print[i].time_taken);
     }
     var TESTER = elem;
@@ -46,7 +46,7 @@ function getAperfEntry(elem, key, run_data) {
             range: [limits.low, limits.high],
         },
     }
-    Plotly.newPlot(TESTER, [aperfstat_collect_data, aperfstat_print_data], layout, { frameMargins: 0 });
+    Plotly.newPlot(TESTER, [aperfstat_collect_data, aperfstat_print_data], add_markers(run, layout), { frameMargins: 0 });
 }
 
 function getAperfEntries(run, container_id, keys, run_data) {
@@ -61,7 +61,7 @@ function getAperfEntries(run, container_id, keys, run_data) {
             return;
         }
         addElemToNode(container_id, elem);
-        emptyOrCallback(keys, false, getAperfEntry, elem, value, run_data);
+        emptyOrCallback(keys, false, getAperfEntry, elem, value, run_data, run);
     }
 }
 
@@ -75,6 +75,7 @@ function aperfStat() {
         let run_name = aperf_run_stats_raw_data['runs'][i]['name'];
         let elem_id = `${run_name}-aperfstat-per-data`;
         let this_run_data = aperf_run_stats_raw_data['runs'][i];
+        show_health(elem_id, this_run_data);
         getAperfEntries(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
     }
     got_aperfstat_data = true;
@@ -0,0 +1,74 @@
+let got_aperf_workload_data = false;
+
+function addWorkloadField(container_id, name, value) {
+    var div = document.createElement('div');
+    addElemToNode(container_id, div);
+    var b = document.createElement('b');
+    b.style.display = "inline-block";
+    b.innerHTML = `${name}: `;
+    div.appendChild(b);
+    var text_value = document.createElement('div');
+    text_value.style.display = "inline-block";
+    text_value.textContent = ` ${value}`;
+    div.appendChild(text_value);
+}
+
+function addWorkloadOutput(container_id, name, lines) {
+    var h4 = document.createElement('h4');
+    h4.innerHTML = name;
+    addElemToNode(container_id, h4);
+    var text_value = document.createElement('pre');
+    text_value.style.whiteSpace = "pre-wrap";
+    text_value.textContent = lines.length > 0 ? lines.join("\n") : "(empty)";
+    addElemToNode(container_id, text_value);
+}
+
+function getWorkload(run, container_id, run_data) {
+    var div = document.createElement('div');
+    div.id = `aperfworkload-${run}-container`;
+    addElemToNode(container_id, div);
+    if (run_data == "No data collected") {
+        var text_value = document.createElement('pre');
+        text_value.innerHTML = "No workload was run";
+        addElemToNode(div.id, text_value);
+        return;
+    }
+    let data = JSON.parse(run_data)[0];
+    let exit_status;
+    if (data.exit_code != null) {
+        exit_status = `exited with code ${data.exit_code}`;
+    } else if (data.exit_signal != null) {
+        exit_status = `killed by signal ${data.exit_signal}`;
+    } else {
+        exit_status = "unknown";
+    }
+    if (data.terminated_by_aperf) {
+        exit_status += " (terminated by APerf at the end of the collection period)";
+    }
+    addWorkloadField(div.id, "Command", data.command.join(" "));
+    addWorkloadField(div.id, "PID", data.pid);
+    addWorkloadField(div.id, "Start (s)", data.start_offset / 1000);
+    if (data.end_offset != null) {
+        addWorkloadField(div.id, "End (s)", data.end_offset / 1000);
+        addWorkloadField(div.id, "Duration (s)", (data.end_offset - data.start_offset) / 1000);
+    }
+    addWorkloadField(div.id, "Exit status", exit_status);
+    addWorkloadOutput(div.id, "stdout (tail)", data.stdout_tail);
+    addWorkloadOutput(div.id, "stderr (tail)", data.stderr_tail);
+}
+
+function aperfWorkload() {
+    if (got_aperf_workload_data) {
+        return;
+    }
+    clear_and_create('aperfworkload');
+    for (let i = 0; i < aperf_workload_raw_data['runs'].length; i++) {
+        let run_name = aperf_workload_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-aperfworkload-per-data`;
+        let this_run_data = aperf_workload_raw_data['runs'][i];
+        setTimeout(() => {
+            getWorkload(run_name, elem_id, this_run_data['key_values']['values']);
+        })
+    }
+    got_aperf_workload_data = true;
+}
@@ -0,0 +1,82 @@
+let got_cgroup_data: boolean|string = "none";
+
+let cgroup_rankings = {
+    "cpu": { title: 'Top cgroups by CPU time', yaxis: 'CPU (% of one CPU)' },
+    "throttling": { title: 'Top cgroups by CPU throttling', yaxis: 'Throttled (ms/s)' },
+    "memory": { title: 'Top cgroups by peak memory', yaxis: 'Memory (MiB)' },
+    "io": { title: 'Top cgroups by IO', yaxis: 'Read and written (MB/s)' },
+    "cpu_pressure": { title: 'Top cgroups by CPU pressure', yaxis: 'Stalled (% of time)' },
+};
+
+function getCgroupRanking(run, container_id, ranking, run_data) {
+    if (!(ranking in run_data)) {
+        var no_data_div = document.createElement('div');
+        no_data_div.id = `cgroup-${run}-no-data`;
+        no_data_div.innerHTML = "No data collected";
+        addElemToNode(container_id, no_data_div);
+        return;
+    }
+    var data = JSON.parse(run_data[ranking]);
+    if (data.entries.length == 0) {
+        var h3 = document.createElement('h3');
+        h3.innerText = "No cgroup to rank.";
+        addElemToNode(container_id, h3);
+        return;
+    }
+    let cgroup_datas = [];
+    data.entries.forEach(function (value, index, arr) {
+        var x_time = [];
+        var y_data = [];
+        value.samples.forEach(function (v, i, a) {
+            x_time.push(time_diff_seconds(v.time));
+            y_data.push(v.value);
+        });
+        var cgroup_data: Partial<Plotly.PlotData> = {
+            name: `${index + 1}. ${value.name}`,
+            x: x_time,
+            y: y_data,
+            type: 'scatter',
+        };
+        cgroup_datas.push(cgroup_data);
+    });
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var layout = {
+        title: cgroup_rankings[ranking].title,
+        xaxis: {
+            title: 'Time (s)',
+            range: [0, time_diff_seconds(data.collection_time)],
+        },
+        yaxis: {
+            title: cgroup_rankings[ranking].yaxis,
+        },
+    }
+    Plotly.newPlot(elem, cgroup_datas, add_markers(run, layout), { frameMargins: 0 });
+
+    var list = document.createElement('ol');
+    data.entries.forEach(function (value, index, arr) {
+        var item = document.createElement('li');
+        item.innerText = `${value.name}: ${value.detail}`;
+        list.appendChild(item);
+    });
+    addElemToNode(container_id, list);
+}
+
+function cgroup(set) {
+    let ranking = set.replace('cgroup-', '');
+    if (ranking == got_cgroup_data) {
+        return;
+    }
+    got_cgroup_data = ranking;
+    clear_and_create('cgroup');
+    for (let i = 0; i < cgroup_raw_data['runs'].length; i++) {
+        let run_name = cgroup_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-cgroup-per-data`;
+        let this_run_data = cgroup_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        getCgroupRanking(run_name, elem_id, ranking, this_run_data['key_values']);
+    }
+}
@@ -26,7 +26,12 @@ function formGlobalConfig() {
 
         /* Elemet 0 is aggregate. Don't use that. */
         let key = this_run_data['keys'][1];
-        config.cpu_count = JSON.parse(this_run_data['key_values'][key]).length;
+        if (key != undefined) {
+            config.cpu_count = JSON.parse(this_run_data['key_values'][key]).length;
+        } else {
+            /* CPU utilization was not collected, fall back to the system info */
+            config.cpu_count = Number(get_data_key("system_info", "Total CPUs").get(run_name)) || 0;
+        }
         config.cpu_list = new Array<string>();
         for (let i = 0; i < config.cpu_count; i++) {
             config.cpu_list.push(i.toString());
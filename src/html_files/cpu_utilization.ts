@@ -57,7 +57,7 @@ function getUtilizationType(run, elem, type, run_data) {
         cpu = value.cpu.toString();
         type_data = value.data;
         type_data.forEach(function (i_value, i_index, i_arr) {
-            x_time.push(i_value.time.TimeDiff);
+            x_time.push(time_diff_seconds(i_value.time));
             y_data.push(i_value.value);
         });
         var cpu_type_data: Partial<Plotly.PlotData> = {
@@ -82,7 +82,7 @@ function getUtilizationType(run, elem, type, run_data) {
             range: [0, 100],
         },
     };
-    Plotly.newPlot(TESTER, cpu_type_datas, layout, { frameMargins: 0 });
+    Plotly.newPlot(TESTER, cpu_type_datas, add_markers(run, layout), { frameMargins: 0 });
 }
 function getUtilizationTypes(run, container_id, keys, run_data) {
     var data = keys;
@@ -110,7 +110,7 @@ function getCpuUtilization(elem, run, run_data) {
 
     var data = JSON.parse(run_data);
     data.forEach(function (value, index, arr) {
-        x_time.push(value.time.TimeDiff);
+        x_time.push(time_diff_seconds(value.time));
         y_user.push(value.values.user);
         y_nice.push(value.values.nice);
         y_system.push(value.values.system);
@@ -180,7 +180,7 @@ function getCpuUtilization(elem, run, run_data) {
         },
     };
     var data_list = [user, nice, system, irq, softirq, idle, iowait, steal];
-    Plotly.newPlot(TESTER, data_list, layout, { frameMargins: 0 });
+    Plotly.newPlot(TESTER, data_list, add_markers(run, layout), { frameMargins: 0 });
 }
 function cpuUtilization() {
     if (got_cpu_util_data && allRunCPUListUnchanged(util_cpu_list)) {
@@ -192,6 +192,9 @@ function cpuUtilization() {
         util_cpu_list.set(run_name, getCPUList(run_name));
         let elem_id = `${run_name}-cpuutilization-per-data`;
         let this_run_data = cpu_utilization_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
         getCpuUtilization(document.getElementById(elem_id), run_name, this_run_data['key_values']['aggregate']);
         getUtilizationTypes(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
     }
@@ -0,0 +1,141 @@
+let got_cpufreq_data = false;
+let cpufreq_cpu_list: Map<string, CPUList> = new Map<string, CPUList>();
+
+let cpufreq_rules = {
+    data_type: "cpufreq",
+    pretty_name: "CPU Frequency",
+    rules: [
+        {
+            name: "frequency",
+            per_run_rule: function* (opts): Generator<Finding, void, any> {
+                if (opts.base_run_data == undefined || opts.this_run_data == undefined) {
+                    return;
+                }
+                let diff = percent_difference(opts.base_run_data, opts.this_run_data);
+                if (diff > 10) {
+                    yield new Finding(
+                        `The average CPU frequency of '${opts.this_run}' is ${opts.this_run_data.toFixed(0)} MHz and of '${opts.base_run}' is ${opts.base_run_data.toFixed(0)} MHz.`,
+                        Status.NotGood,
+                        "Compare the cpufreq driver, governor and boost settings of the runs.",
+                    );
+                }
+            },
+        },
+    ]
+}
+
+function getCpufreqFrequency(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var cpu_datas = [];
+    data.per_cpu.forEach(function (value, index, arr) {
+        var x_time = [];
+        var y_data = [];
+        value.data.forEach(function (v, i, a) {
+            x_time.push(time_diff_seconds(v.time));
+            y_data.push(v.frequency);
+        });
+        var cpu_data: Partial<Plotly.PlotData> = {
+            name: `CPU ${value.cpu}`,
+            x: x_time,
+            y: y_data,
+            type: 'scatter',
+        };
+        if (cpufreq_cpu_list.get(run).cpulist.indexOf(value.cpu.toString()) == -1) {
+            cpu_data.visible = 'legendonly';
+        }
+        cpu_datas.push(cpu_data);
+    });
+    var layout = {
+        title: 'CPU frequency',
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'MHz',
+            range: [0, data.metadata.limits.high],
+        },
+    };
+    Plotly.newPlot(elem, cpu_datas, add_markers(run, layout), { frameMargins: 0 });
+}
+
+function getCpufreqIdle(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    data.forEach(function (state, index, arr) {
+        var elem = document.createElement('div');
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        var cpu_datas = [];
+        state.per_cpu.forEach(function (value, i, a) {
+            var x_time = [];
+            var y_data = [];
+            value.data.forEach(function (v, j, b) {
+                x_time.push(time_diff_seconds(v.time));
+                y_data.push(v.residency);
+            });
+            var cpu_data: Partial<Plotly.PlotData> = {
+                name: `CPU ${value.cpu}`,
+                x: x_time,
+                y: y_data,
+                type: 'scatter',
+            };
+            if (cpufreq_cpu_list.get(run).cpulist.indexOf(value.cpu.toString()) == -1) {
+                cpu_data.visible = 'legendonly';
+            }
+            cpu_datas.push(cpu_data);
+        });
+        var layout = {
+            title: `${state.name} residency`,
+            xaxis: {
+                title: 'Time (s)',
+            },
+            yaxis: {
+                title: 'Residency (%)',
+                range: [0, 100],
+            },
+        };
+        setTimeout(() => {
+            Plotly.newPlot(elem, cpu_datas, add_markers(run, layout), { frameMargins: 0 });
+        }, 0);
+    });
+}
+
+function cpufreq() {
+    if (got_cpufreq_data && allRunCPUListUnchanged(cpufreq_cpu_list)) {
+        return;
+    }
+    clear_and_create('cpufreq');
+    for (let i = 0; i < runs_raw.length; i++) {
+        let run_name = runs_raw[i];
+        cpufreq_cpu_list.set(run_name, getCPUList(run_name));
+        let elem_id = `${run_name}-cpufreq-per-data`;
+
+        let settings_data = cpufreq_settings_raw_data['runs'].find(r => r['name'] == run_name);
+        if (settings_data && settings_data['collected'] !== false) {
+            getCpufreqSettings(run_name, elem_id, settings_data['key_values']['values']);
+        }
+
+        let this_run_data = cpufreq_raw_data['runs'].find(r => r['name'] == run_name);
+        /* Most VMs expose neither cpufreq nor cpuidle, the collector is excluded then */
+        if (!this_run_data || this_run_data['collected'] === false || this_run_data['keys'].length == 0) {
+            var h3 = document.createElement('h3');
+            h3.innerHTML = "Frequency and idle states not available";
+            h3.style.textAlign = "center";
+            addElemToNode(elem_id, h3);
+            continue;
+        }
+        show_health(elem_id, this_run_data);
+        let key_values = this_run_data['key_values'];
+        setTimeout(() => {
+            if ('frequency' in key_values) {
+                getCpufreqFrequency(run_name, elem_id, key_values['frequency']);
+            }
+            if ('idle' in key_values) {
+                getCpufreqIdle(run_name, elem_id, key_values['idle']);
+            }
+        }, 0);
+    }
+    got_cpufreq_data = true;
+}
@@ -0,0 +1,21 @@
+function getCpufreqSettings(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var b = document.createElement('b');
+    b.innerHTML = 'Frequency and idle settings';
+    addElemToNode(container_id, b);
+    if (Object.keys(data).length == 0) {
+        var h3 = document.createElement('h3');
+        h3.innerHTML = "Not available";
+        h3.style.textAlign = "center";
+        addElemToNode(container_id, h3);
+        return;
+    }
+    var dl = document.createElement('dl');
+    dl.id = `${run}-dl-cpufreq-settings`;
+    dl.classList.add("extra");
+    dl.style.float = "none";
+    addElemToNode(container_id, dl);
+    for (var key in data) {
+        createNode(key, data[key], dl.id);
+    }
+}
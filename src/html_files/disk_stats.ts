@@ -7,14 +7,14 @@ let diskstats_rules = {
     rules: []
 }
 
-function getStatValues(elem, key, run_data) {
+function getStatValues(elem, key, run_data, run) {
     var disk_datas = [];
     var data = JSON.parse(run_data);
     data.data.forEach(function (v, i, a) {
         var x_time = [];
         var y_data = [];
         v.values.forEach(function (disk_value, disk_index, disk_arr) {
-            x_time.push(disk_value.time.TimeDiff);
+            x_time.push(time_diff_seconds(disk_value.time));
             y_data.push(disk_value.value);
         })
         var disk_data = {
@@ -45,7 +45,7 @@ function getStatValues(elem, key, run_data) {
             range: [limits.low, limits.high],
         },
     };
-    Plotly.newPlot(TESTER, disk_datas, layout, { frameMargins: 0 });
+    Plotly.newPlot(TESTER, disk_datas, add_markers(run, layout), { frameMargins: 0 });
 }
 
 function getStatKeys(run, container_id, keys, run_data) {
@@ -55,7 +55,7 @@ function getStatKeys(run, container_id, keys, run_data) {
         elem.id = `disk-stat-${run}-${value}`;
         elem.style.float = "none";
         addElemToNode(container_id, elem);
-        emptyOrCallback(keys, diskstat_hide_zero_na_graphs, getStatValues, elem, value, run_data);
+        emptyOrCallback(keys, diskstat_hide_zero_na_graphs, getStatValues, elem, value, run_data, run);
     }
 }
 
@@ -70,6 +70,9 @@ function diskStats(hide: boolean) {
         let run_name = disk_stats_raw_data['runs'][i]['name']
         let elem_id = `${run_name}-diskstat-per-data`;
         let this_run_data = disk_stats_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
         getStatKeys(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
     }
     got_disk_stat_data = true;
@@ -0,0 +1,94 @@
+let got_filesystem_usage_data = false;
+
+let filesystem_usage_rules = {
+    data_type: "filesystem_usage",
+    pretty_name: "Filesystems",
+    rules: [
+        {
+            name: "capacity_used_percent",
+            single_run_rule: function* (opts): Generator<Finding, void, any> {
+                let thresh = 90;
+                if (opts.base_run_data > thresh) {
+                    let mount_point = get_data_key(opts.data_type, "capacity_fullest_mount").get(opts.base_run);
+                    yield new Finding(
+                        `'${mount_point}' in '${opts.base_run}' is ${opts.base_run_data.toFixed(2)}% full.`,
+                        Status.NotGood,
+                        "Free up or grow the volume, writes fail once it is full and some filesystems slow down well before.",
+                    );
+                }
+            },
+        },
+        {
+            name: "inodes_used_percent",
+            single_run_rule: function* (opts): Generator<Finding, void, any> {
+                let thresh = 90;
+                if (opts.base_run_data > thresh) {
+                    let mount_point = get_data_key(opts.data_type, "inodes_fullest_mount").get(opts.base_run);
+                    yield new Finding(
+                        `'${mount_point}' in '${opts.base_run}' has used ${opts.base_run_data.toFixed(2)}% of its inodes.`,
+                        Status.NotGood,
+                        "Remove small files or recreate the filesystem with more inodes.",
+                    );
+                }
+            },
+        },
+    ]
+}
+
+function getFilesystemUsage(run, container_id, key, run_data) {
+    var data = JSON.parse(run_data);
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var mount_datas = [];
+    data.per_mount.forEach(function (value, index, arr) {
+        var x_time = [];
+        var y_data = [];
+        var text = [];
+        value.data.forEach(function (v, i, a) {
+            x_time.push(time_diff_seconds(v.time));
+            y_data.push(v.used_percent);
+            text.push(key == 'capacity' ? `${v.used.toFixed(2)} of ${value.size.toFixed(2)} GiB` : `${v.used.toFixed(0)} of ${value.size} inodes`);
+        });
+        mount_datas.push({ x: x_time, y: y_data, text: text, type: 'scatter', name: value.mount_point });
+    });
+    var layout = {
+        title: key == 'capacity' ? 'Capacity used' : 'Inodes used',
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'Used (%)',
+            range: [0, 100],
+        },
+    };
+    Plotly.newPlot(elem, mount_datas, add_markers(run, layout), { frameMargins: 0 });
+}
+
+function filesystems() {
+    if (got_filesystem_usage_data) {
+        return;
+    }
+    clear_and_create('filesystems');
+    for (let i = 0; i < runs_raw.length; i++) {
+        let run_name = runs_raw[i];
+        let elem_id = `${run_name}-filesystems-per-data`;
+
+        let this_run_data = filesystem_usage_raw_data['runs'].find(r => r['name'] == run_name);
+        if (this_run_data && !not_collected(elem_id, this_run_data)) {
+            let key_values = this_run_data['key_values'];
+            setTimeout(() => {
+                getFilesystemUsage(run_name, elem_id, 'capacity', key_values['capacity']);
+                getFilesystemUsage(run_name, elem_id, 'inodes', key_values['inodes']);
+            }, 0);
+        }
+
+        let mounts_data = mounts_raw_data['runs'].find(r => r['name'] == run_name);
+        if (mounts_data && mounts_data['collected'] !== false) {
+            setTimeout(() => {
+                getMounts(run_name, elem_id, mounts_data['key_values']['values']);
+            }, 0);
+        }
+    }
+    got_filesystem_usage_data = true;
+}
@@ -44,6 +44,9 @@ function flamegraphs(set) {
     for (let i = 0; i < raw_data['runs'].length; i++) {
         let run_name = raw_data['runs'][i]['name'];
         let elem_id = `${run_name}-flamegraphs-per-data`;
+        if (not_collected(elem_id, raw_data['runs'][i])) {
+            continue;
+        }
         setTimeout(() => {
             switch(set){
                 case 'flamegraphs':
@@ -0,0 +1,164 @@
+let got_fragmentation_data = false;
+
+function getFreePages(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    data.forEach(function (value, index, arr) {
+        var elem = document.createElement('div');
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        var x_time = value.times.map(t => time_diff_seconds(t));
+        var orders = value.percent.map((v, order) => `${order}`);
+        var text = value.blocks.map(row => row.map(b => `${b} free block(s)`));
+        var heatmap: Partial<Plotly.PlotData> = {
+            x: x_time,
+            y: orders,
+            z: value.percent,
+            text: text,
+            type: 'heatmap',
+            colorscale: 'Viridis',
+            colorbar: { title: '% of free' },
+        };
+        var layout = {
+            title: `Node ${value.node} ${value.zone}: free pages per order`,
+            xaxis: {
+                title: 'Time (s)',
+            },
+            yaxis: {
+                title: 'Order',
+                type: 'category',
+            },
+        };
+        setTimeout(() => {
+            Plotly.newPlot(elem, [heatmap], add_markers(run, layout), { frameMargins: 0 });
+        }, 0);
+    });
+}
+
+function getFragmentationIndex(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    [
+        { key: 'unusable_index', title: 'Unusable free space index', range: [0, 1] },
+        { key: 'fragmentation_index', title: 'Fragmentation index (-1: a free block is large enough)', range: [-1, 1] },
+    ].forEach(function (graph, index, arr) {
+        var elem = document.createElement('div');
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        var zone_datas = [];
+        data.per_zone.forEach(function (value, i, a) {
+            var x_time = [];
+            var y_data = [];
+            value.data.forEach(function (v, j, b) {
+                x_time.push(time_diff_seconds(v.time));
+                y_data.push(v[graph.key]);
+            });
+            zone_datas.push({ x: x_time, y: y_data, type: 'scatter', name: `Node ${value.node} ${value.zone}` });
+        });
+        var layout = {
+            title: `${graph.title} for order ${data.order} allocations`,
+            xaxis: {
+                title: 'Time (s)',
+            },
+            yaxis: {
+                range: graph.range,
+            },
+        };
+        Plotly.newPlot(elem, zone_datas, add_markers(run, layout), { frameMargins: 0 });
+    });
+}
+
+function getWatermarks(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    data.forEach(function (value, index, arr) {
+        if (value.data.length == 0) {
+            return;
+        }
+        var elem = document.createElement('div');
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        var zone_datas = [];
+        ['free', 'min', 'low', 'high'].forEach(function (key, i, a) {
+            var x_time = [];
+            var y_data = [];
+            value.data.forEach(function (v, j, b) {
+                x_time.push(time_diff_seconds(v.time));
+                y_data.push(v.watermarks[key]);
+            });
+            zone_datas.push({ x: x_time, y: y_data, type: 'scatter', name: key });
+        });
+        var layout = {
+            title: `Node ${value.node} ${value.zone}: free pages and watermarks`,
+            xaxis: {
+                title: 'Time (s)',
+            },
+            yaxis: {
+                title: 'Pages',
+                rangemode: 'tozero',
+            },
+        };
+        setTimeout(() => {
+            Plotly.newPlot(elem, zone_datas, add_markers(run, layout), { frameMargins: 0 });
+        }, 0);
+    });
+}
+
+function getMigrateTypes(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    data.forEach(function (value, index, arr) {
+        if (value.data.length == 0) {
+            return;
+        }
+        var elem = document.createElement('div');
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        var type_datas = [];
+        Object.keys(value.data[0].free).forEach(function (type, i, a) {
+            var x_time = [];
+            var y_data = [];
+            value.data.forEach(function (v, j, b) {
+                x_time.push(time_diff_seconds(v.time));
+                y_data.push(v.free[type]);
+            });
+            type_datas.push({ x: x_time, y: y_data, type: 'scatter', name: type });
+        });
+        var layout = {
+            title: `Node ${value.node} ${value.zone}: free pages per migrate type`,
+            xaxis: {
+                title: 'Time (s)',
+            },
+            yaxis: {
+                title: 'Pages',
+                rangemode: 'tozero',
+            },
+        };
+        setTimeout(() => {
+            Plotly.newPlot(elem, type_datas, add_markers(run, layout), { frameMargins: 0 });
+        }, 0);
+    });
+}
+
+function fragmentation() {
+    if (got_fragmentation_data) {
+        return;
+    }
+    clear_and_create('fragmentation');
+    for (let i = 0; i < fragmentation_raw_data['runs'].length; i++) {
+        let run_name = fragmentation_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-fragmentation-per-data`;
+        let this_run_data = fragmentation_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        let key_values = this_run_data['key_values'];
+        setTimeout(() => {
+            getFragmentationIndex(run_name, elem_id, key_values['fragmentation']);
+            getFreePages(run_name, elem_id, key_values['free_pages']);
+            if ('watermarks' in key_values) {
+                getWatermarks(run_name, elem_id, key_values['watermarks']);
+            }
+            if ('migrate_types' in key_values) {
+                getMigrateTypes(run_name, elem_id, key_values['migrate_types']);
+            }
+        }, 0);
+    }
+    got_fragmentation_data = true;
+}
@@ -0,0 +1,85 @@
+let hardware_topology_rules = {
+    data_type: "hardware_topology",
+    pretty_name: "Hardware Topology",
+    rules: [
+        {
+            name: "topology",
+            all_run_rule: function* (opts): Generator<Finding, void, any> {
+                if (opts.base_run_data == undefined) {
+                    return;
+                }
+                let base_topology = JSON.parse(opts.base_run_data);
+                let diff_keys = [];
+                for (const [run, run_data] of opts.other_run_data) {
+                    let topology = JSON.parse(run_data);
+                    let keys = new Set([...Object.keys(base_topology), ...Object.keys(topology)]);
+                    for (let key of keys) {
+                        if (base_topology[key] != topology[key] && !diff_keys.includes(key)) {
+                            diff_keys.push(key);
+                        }
+                    }
+                }
+                if (diff_keys.length == 0) {
+                    yield new Finding("Hardware topology is the same across runs.", Status.Good);
+                } else {
+                    yield new Finding(
+                        `Hardware topology differs across runs in: ${diff_keys.join(', ')}.`,
+                        Status.NotGood,
+                        "Compare runs on the same instance type and kernel command line.",
+                    );
+                }
+            },
+        },
+    ]
+}
+
+/* The topology of every run, to mark the entries which differ from the other runs */
+function hardwareTopologies() {
+    let topologies = new Map<string, any>();
+    hardware_topology_raw_data['runs'].forEach(function (value, index, arr) {
+        if (value['key_values'] && 'values' in value['key_values']) {
+            topologies.set(value['name'], JSON.parse(value['key_values']['values']));
+        }
+    });
+    return topologies;
+}
+
+function getHardwareTopology(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var topologies = hardwareTopologies();
+    var base_data = topologies.get(runs_raw[0]);
+    var b = document.createElement('b');
+    b.innerHTML = 'Hardware topology';
+    addElemToNode(container_id, b);
+    var table = document.createElement('table');
+    addElemToNode(container_id, table);
+    for (let key in data) {
+        var row = table.insertRow();
+        var differs = false;
+        for (let [name, topology] of topologies) {
+            if (topology[key] != data[key]) {
+                differs = true;
+            }
+        }
+        var key_cell = row.insertCell();
+        key_cell.textContent = key;
+        var value_cell = row.insertCell();
+        if (key == 'CPU flags' && run != runs_raw[0] && base_data != undefined && key in base_data) {
+            let flags = data[key].split(' ');
+            let base_flags = base_data[key].split(' ');
+            let added = flags.filter(f => !base_flags.includes(f));
+            let missing = base_flags.filter(f => !flags.includes(f));
+            if (added.length == 0 && missing.length == 0) {
+                value_cell.textContent = `Same as ${runs_raw[0]} (${flags.length} flags)`;
+            } else {
+                value_cell.textContent = `Added: ${added.join(' ') || 'none'}; Missing: ${missing.join(' ') || 'none'} (vs ${runs_raw[0]})`;
+            }
+        } else {
+            value_cell.textContent = data[key];
+        }
+        if (differs) {
+            key_cell.style.color = 'red';
+            value_cell.style.color = 'red';
+        }
+    }
+}
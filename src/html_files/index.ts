@@ -15,20 +15,34 @@ class DataType {
 }
 
 var DataTypes: Map<string, DataType> = new Map<string, DataType>();
+DataTypes.set('fragmentation', new DataType('fragmentation', '', '', fragmentation, ''));
+DataTypes.set('slabinfo', new DataType('slabinfo', 'slabRanking', '', slabinfo, ''));
 DataTypes.set('kernel_config', new DataType('kernel', 'kernelDiff', 'kernel-button-yes', kernelConfig, 'no'));
 DataTypes.set('sysctl', new DataType('sysctl', 'sysctlDiff', 'sysctl-button-yes', sysctl, 'no'));
+DataTypes.set('kernel_runtime', new DataType('kernelruntime', 'kernelRuntimeDiff', 'kernelruntime-button-yes', kernelRuntime, 'no'));
 DataTypes.set('vmstat', new DataType('vmstat', 'vmstatHide', 'vmstat-button-yes', vmStat, ''));
 DataTypes.set('disk_stats', new DataType('diskstat', 'diskstatHide', 'diskstat-button-yes', diskStats, ''));
 DataTypes.set('meminfo', new DataType('meminfo', 'meminfoHide', 'meminfo-button-yes', meminfo, ''));
 DataTypes.set('netstat', new DataType('netstat', 'netstatHide', 'netstat-button-yes', netStat, ''));
+DataTypes.set('net_dev', new DataType('netdev', 'netdevHide', 'netdev-button-yes', netDev, ''));
+DataTypes.set('snmp', new DataType('snmp', 'snmpHide', 'snmp-button-yes', snmp, ''));
+DataTypes.set('pressure', new DataType('pressure', 'pressureHide', 'pressure-button-yes', pressure, ''));
 DataTypes.set('interrupts', new DataType('interrupts', '', '', interrupts, ''));
+DataTypes.set('softirqs', new DataType('softirqs', '', '', softirqs, ''));
+DataTypes.set('scheduler', new DataType('scheduler', '', '', scheduler, ''));
+DataTypes.set('numa', new DataType('numa', '', '', numa, ''));
+DataTypes.set('cpufreq', new DataType('cpufreq', '', '', cpufreq, ''));
+DataTypes.set('filesystems', new DataType('filesystems', '', '', filesystems, ''));
 DataTypes.set('cpu_utilization', new DataType('cpuutilization', '', '', cpuUtilization, ''));
 DataTypes.set('system_info', new DataType('systeminfo', 'landingChoice', '', systemInfo, ''));
 DataTypes.set('flamegraphs', new DataType('flamegraphs', 'flamegraphsSelection', '', flamegraphs, ''));
 DataTypes.set('top_functions', new DataType('topfunctions', '', '', topFunctions, ''));
-DataTypes.set('processes', new DataType('processes', '', '', processes, ''));
+DataTypes.set('processes', new DataType('processes', 'processRanking', '', processes, ''));
+DataTypes.set('threads', new DataType('threads', '', '', threads, ''));
+DataTypes.set('cgroup', new DataType('cgroup', 'cgroupRanking', '', cgroup, ''));
 DataTypes.set('perfstat', new DataType('perfstat', '', '', perfStat, ''));
 DataTypes.set('aperfstat', new DataType('aperfstat', '', '', aperfStat, ''));
+DataTypes.set('aperfworkload', new DataType('aperfworkload', '', '', aperfWorkload, ''));
 DataTypes.set('aperfrunlog', new DataType('aperfrunlog', '', '', aperfRunlog, ''));
 DataTypes.set('configure', new DataType('configure', '', '', configure, ''));
 
@@ -11,7 +11,7 @@ function getLine(run, elem, key, run_data) {
         data.forEach(function (value, index, arr) {
             value.per_cpu.forEach(function (v, i, a) {
                 if (v.cpu == cpu) {
-                    x_time.push(value.time.TimeDiff);
+                    x_time.push(time_diff_seconds(value.time));
                     y_data.push(v.count);
                 }
             })
@@ -43,7 +43,7 @@ function getLine(run, elem, key, run_data) {
             title: 'Count',
         }
     };
-    Plotly.newPlot(TESTER, interrupt_type_datas, layout, { frameMargins: 0 });
+    Plotly.newPlot(TESTER, interrupt_type_datas, add_markers(run, layout), { frameMargins: 0 });
 }
 
 function getLines(run, container_id, keys, run_data) {
@@ -69,6 +69,9 @@ function interrupts() {
         interrupt_cpu_list.set(run_name, getCPUList(run_name));
         let elem_id = `${run_name}-interrupts-per-data`;
         let this_run_data = interrupts_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
         getLines(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
     }
     got_interrupt_data = true;
@@ -123,6 +123,9 @@ function kernelConfig(diff: boolean) {
             for (let i = 0; i < kernel_config_raw_data['runs'].length; i++) {
                 if (kernel_config_raw_data['runs'][i]['name'] == value) {
                     this_run_data = kernel_config_raw_data['runs'][i];
+                    if (this_run_data['collected'] === false) {
+                        continue;
+                    }
                     form_kernel_data(value, this_run_data);
                 }
             }
@@ -132,6 +135,10 @@ function kernelConfig(diff: boolean) {
     clear_and_create('kernel');
     data.forEach(function (value, index, arr) {
         let elem_id = `${value}-kernel-per-data`;
+        if (!kernel_config_runs.has(value)) {
+            show_not_collected(elem_id);
+            return;
+        }
         if (current_kernel_diff_status) {
             kernelConfigDiff(value, elem_id);
         } else {
@@ -0,0 +1,102 @@
+let got_kernel_runtime_data = false;
+let current_kernel_runtime_diff_status = false;
+
+var kernel_runtime_runs: Map<string, RunEntry> = new Map<string, RunEntry>();
+var kernel_runtime_run_names: Array<string> = [];
+var kernel_runtime_common_keys: Array<string> = [];
+
+function form_kernel_runtime_data(run, run_data) {
+    kernel_runtime_run_names.push(run);
+    var run_entry = new RunEntry();
+    run_entry.run = run;
+    run_entry.entries = new Map<string, string>();
+    run_entry.keys = new Array();
+    run_entry.diff_keys = new Array();
+    kernel_runtime_runs.set(run, run_entry);
+    var data = JSON.parse(run_data['key_values']['values']);
+    for (var key in data) {
+        var value = data[key];
+        let run_entry = kernel_runtime_runs.get(run);
+        run_entry.entries.set(key, value);
+        run_entry.keys.push(key);
+    }
+}
+function kernelRuntimeNoDiff(run, container_id) {
+    var dl = document.createElement('dl');
+    dl.id = `${run}-dl-kernelruntime-data`;
+    dl.classList.add("extra");
+    dl.style.float = "none";
+    var dl_id = dl.id;
+    addElemToNode(container_id, dl);
+    let run_entry = kernel_runtime_runs.get(run);
+    for (let [key, value] of run_entry.entries) {
+        createNode(key, value, dl_id);
+    }
+}
+
+function kernelRuntimeDiff(value, container_id) {
+    clearElements(container_id);
+    var dl = document.createElement('dl');
+    dl.id = `${value}-dl-kernelruntime-data`;
+    dl.classList.add("extra");
+    dl.style.float = "none";
+    var dl_id = dl.id;
+    addElemToNode(container_id, dl);
+    let run_entry = kernel_runtime_runs.get(value);
+    var h3_common = document.createElement('h3');
+    h3_common.innerHTML = 'Common Keys';
+    h3_common.style.textAlign = "center";
+    addElemToNode(dl_id, h3_common);
+    for (let i = 0; i < kernel_runtime_common_keys.length; i++) {
+        if (isDiffAcrossRuns(kernel_runtime_common_keys[i], kernel_runtime_run_names, kernel_runtime_runs)) {
+            let e = run_entry.entries.get(kernel_runtime_common_keys[i]);
+            createNode(kernel_runtime_common_keys[i], e, dl_id);
+        }
+    }
+    var h3_diff = document.createElement('h3');
+    h3_diff.innerHTML = 'Different Keys';
+    h3_diff.style.textAlign = "center";
+    addElemToNode(dl_id, h3_diff);
+    for (let i = 0; i < run_entry.diff_keys.length; i++) {
+        let key = run_entry.diff_keys[i];
+        let e = run_entry.entries.get(key);
+        createNode(key, e, dl_id);
+    }
+}
+
+function kernelRuntime(diff: boolean) {
+    if (got_kernel_runtime_data && current_kernel_runtime_diff_status == diff) {
+        return;
+    }
+    current_kernel_runtime_diff_status = diff;
+    var data = runs_raw;
+    if (!got_kernel_runtime_data) {
+        data.forEach(function (value, index, arr) {
+            let this_run_data;
+            for (let i = 0; i < kernel_runtime_raw_data['runs'].length; i++) {
+                if (kernel_runtime_raw_data['runs'][i]['name'] == value) {
+                    this_run_data = kernel_runtime_raw_data['runs'][i];
+                    if (this_run_data['collected'] === false) {
+                        continue;
+                    }
+                    form_kernel_runtime_data(value, this_run_data);
+                }
+            }
+        });
+        split_keys(kernel_runtime_runs, kernel_runtime_common_keys);
+    }
+
+    clear_and_create('kernelruntime');
+    data.forEach(function (value, index, arr) {
+        if (!kernel_runtime_runs.has(value)) {
+            show_not_collected(`${value}-kernelruntime-per-data`);
+            return;
+        }
+        if (current_kernel_runtime_diff_status) {
+            kernelRuntimeDiff(value, `${value}-kernelruntime-per-data`);
+        } else {
+            kernelRuntimeNoDiff(value, `${value}-kernelruntime-per-data`);
+        }
+    })
+    got_kernel_runtime_data = true;
+}
\ No newline at end of file
@@ -63,12 +63,12 @@ function get_divisor_unit(key) {
     };
 }
 
-function getMeminfo(elem, key, run_data) {
+function getMeminfo(elem, key, run_data, run) {
     var data = JSON.parse(run_data);
     var x_data = [];
     var y_data = [];
     data.data.values.forEach(function (value, index, arr) {
-        x_data.push(value.time.TimeDiff);
+        x_data.push(time_diff_seconds(value.time));
         y_data.push(value.value);
     })
 
@@ -110,7 +110,7 @@ function getMeminfo(elem, key, run_data) {
             range: [limits.low/divisor, limits.high/divisor],
         }
     };
-    Plotly.newPlot(TESTER, [meminfodata], layout, { frameMargins: 0 });
+    Plotly.newPlot(TESTER, [meminfodata], add_markers(run, layout), { frameMargins: 0 });
 }
 
 function getMeminfoKeys(run, container_id, keys, run_data) {
@@ -120,7 +120,7 @@ function getMeminfoKeys(run, container_id, keys, run_data) {
         elem.id = `disk-stat-${run}-${value}`;
         elem.style.float = "none";
         addElemToNode(container_id, elem);
-        emptyOrCallback(keys, meminfo_hide_zero_na_graphs, getMeminfo, elem, value, run_data);
+        emptyOrCallback(keys, meminfo_hide_zero_na_graphs, getMeminfo, elem, value, run_data, run);
     }
 }
 
@@ -136,6 +136,9 @@ function meminfo(hide: boolean) {
         let run_name = meminfo_raw_data['runs'][i]['name'];
         let elem_id = `${run_name}-meminfo-per-data`;
         let this_run_data = meminfo_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
         getMeminfoKeys(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
     }
     got_meminfo_data = true;
@@ -0,0 +1,47 @@
+let mounts_rules = {
+    data_type: "mounts",
+    pretty_name: "Mounts",
+    rules: [
+        {
+            name: "atime_sync_options",
+            per_run_rule: function* (opts): Generator<Finding, void, any> {
+                if (opts.base_run_data == undefined || opts.this_run_data == undefined) {
+                    return;
+                }
+                let base_options = JSON.parse(opts.base_run_data);
+                let this_options = JSON.parse(opts.this_run_data);
+                for (let mount_point in base_options) {
+                    if (!(mount_point in this_options) || base_options[mount_point] == this_options[mount_point]) {
+                        continue;
+                    }
+                    yield new Finding(
+                        `'${mount_point}' is mounted with '${this_options[mount_point] || "default"}' in '${opts.this_run}' and with '${base_options[mount_point] || "default"}' in '${opts.base_run}'.`,
+                        Status.NotGood,
+                        "sync writes every change through to the disk and noatime skips access time updates, mount the runs the same way.",
+                    );
+                }
+            },
+        },
+    ]
+}
+
+function getMounts(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var b = document.createElement('b');
+    b.innerHTML = 'Mounts';
+    addElemToNode(container_id, b);
+    var table = document.createElement('table');
+    addElemToNode(container_id, table);
+    var header = table.insertRow();
+    ['Mount point', 'Type', 'Source', 'Device', 'Options'].forEach(function (value, index, arr) {
+        header.insertCell().textContent = value;
+    });
+    data.forEach(function (value, index, arr) {
+        var row = table.insertRow();
+        row.insertCell().textContent = value.root == '/' ? value.mount_point : `${value.mount_point} (${value.root})`;
+        row.insertCell().textContent = value.fs_type;
+        row.insertCell().textContent = value.source;
+        row.insertCell().textContent = value.device;
+        row.insertCell().textContent = `${value.options} ${value.super_options}`;
+    });
+}
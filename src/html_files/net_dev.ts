@@ -0,0 +1,111 @@
+let got_net_dev_data = false;
+let net_dev_hide_zero_na_graphs = false;
+
+function netDevLossRule(name: string, what: string) {
+    return {
+        name: name,
+        single_run_rule: function* (opts): Generator<Finding, void, any> {
+            if (opts.base_run_data == undefined) {
+                return;
+            }
+            if (opts.base_run_data > 0) {
+                yield new Finding(
+                    `Interfaces of '${opts.base_run}' had ${opts.base_run_data.toFixed(2)} ${what}/s on average.`,
+                    Status.NotGood,
+                );
+            }
+        },
+        per_run_rule: function* (opts): Generator<Finding, void, any> {
+            if (opts.base_run_data == undefined || opts.this_run_data == undefined) {
+                return;
+            }
+            let diff = Math.abs(opts.this_run_data - opts.base_run_data);
+            if (diff > 0) {
+                yield new Finding(
+                    `Average ${what}/s differ between '${opts.base_run}' and '${opts.this_run}' by ${diff.toFixed(2)}.`,
+                    Status.NotGood,
+                );
+            }
+        },
+    };
+}
+
+let net_dev_rules = {
+    data_type: "net_dev",
+    pretty_name: "Network Interfaces",
+    rules: [
+        netDevLossRule("aggregate drops", "dropped packets"),
+        netDevLossRule("aggregate errors", "packet errors"),
+    ]
+}
+
+function getNetDevEntries(run, container_id, keys, run_data) {
+    for (let i = 0; i < all_run_keys.length; i++) {
+        let value = all_run_keys[i];
+        var elem = document.createElement('div');
+        elem.id = `netdev-${run}-${value}`;
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        emptyOrCallback(keys, net_dev_hide_zero_na_graphs, getNetDevEntry, elem, value, run_data, run);
+    }
+}
+
+function getNetDevEntry(elem, key, run_data, run) {
+    var data = JSON.parse(run_data);
+    var x_time = [];
+    var rx_bytes = [];
+    var tx_bytes = [];
+    var drops = [];
+    var errors = [];
+    data.data.forEach(function (value, index, arr) {
+        x_time.push(time_diff_seconds(value.time));
+        rx_bytes.push(value.rx_bytes);
+        tx_bytes.push(value.tx_bytes);
+        drops.push(value.drops);
+        errors.push(value.errors);
+    });
+    var TESTER = elem;
+    let series: Array<Partial<Plotly.PlotData>> = [
+        { x: x_time, y: rx_bytes, type: 'scatter', name: 'rx bytes/s' },
+        { x: x_time, y: tx_bytes, type: 'scatter', name: 'tx bytes/s' },
+        { x: x_time, y: drops, type: 'scatter', name: 'drops/s', yaxis: 'y2' },
+        { x: x_time, y: errors, type: 'scatter', name: 'errors/s', yaxis: 'y2' },
+    ];
+    let limits = key_limits.get(key);
+    var layout = {
+        title: `${key}`,
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'Bytes/s',
+            range: [limits.low, limits.high],
+        },
+        yaxis2: {
+            title: 'Packets/s',
+            overlaying: 'y',
+            side: 'right',
+            rangemode: 'tozero',
+        },
+    }
+    Plotly.newPlot(TESTER, series, add_markers(run, layout), { frameMargins: 0 });
+}
+
+function netDev(hide: boolean) {
+    if (got_net_dev_data && hide == net_dev_hide_zero_na_graphs) {
+        return;
+    }
+    net_dev_hide_zero_na_graphs = hide;
+    clear_and_create('netdev');
+    form_graph_limits(net_dev_raw_data);
+    for (let i = 0; i < net_dev_raw_data['runs'].length; i++) {
+        let run_name = net_dev_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-netdev-per-data`;
+        let this_run_data = net_dev_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        getNetDevEntries(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
+    }
+    got_net_dev_data = true;
+}
@@ -14,16 +14,16 @@ function getNetstatEntries(run, container_id, keys, run_data) {
         elem.id = `netstat-${run}-${value}`;
         elem.style.float = "none";
         addElemToNode(container_id, elem);
-        emptyOrCallback(keys, netstat_hide_zero_na_graphs, getNetstatEntry, elem, value, run_data);
+        emptyOrCallback(keys, netstat_hide_zero_na_graphs, getNetstatEntry, elem, value, run_data, run);
     }
 }
 
-function getNetstatEntry(elem, key, run_data) {
+function getNetstatEntry(elem, key, run_data, run) {
     var data = JSON.parse(run_data);
     var x_time = [];
     var y_data = [];
     data.data.forEach(function (value, index, arr) {
-        x_time.push(value.time.TimeDiff);
+        x_time.push(time_diff_seconds(value.time));
         y_data.push(value.value);
     });
     var TESTER = elem;
@@ -43,7 +43,7 @@ function getNetstatEntry(elem, key, run_data) {
             range: [limits.low, limits.high],
         },
     }
-    Plotly.newPlot(TESTER, [netstat_data], layout, { frameMargins: 0 });
+    Plotly.newPlot(TESTER, [netstat_data], add_markers(run, layout), { frameMargins: 0 });
 }
 
 function netStat(hide: boolean) {
@@ -57,6 +57,9 @@ function netStat(hide: boolean) {
         let run_name = netstat_raw_data['runs'][i]['name'];
         let elem_id = `${run_name}-netstat-per-data`;
         let this_run_data = netstat_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
         getNetstatEntries(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
     }
     got_netstat_data = true;
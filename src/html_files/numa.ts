@@ -0,0 +1,134 @@
+let got_numa_data = false;
+
+let numa_rules = {
+    data_type: "numa",
+    pretty_name: "NUMA",
+    rules: [
+        {
+            name: "numa_miss_percent",
+            single_run_rule: function* (opts): Generator<Finding, void, any> {
+                let thresh = 5;
+                if (opts.base_run_data > thresh) {
+                    yield new Finding(
+                        `${opts.base_run_data.toFixed(2)}% of the page allocations in '${opts.base_run}' were satisfied by a remote NUMA node.`,
+                        Status.NotGood,
+                        "Bind the workload and its memory to the same node with numactl or a cpuset, or check for a node running out of free memory.",
+                    );
+                }
+            },
+            per_run_rule: function* (opts): Generator<Finding, void, any> {
+                let diff = opts.this_run_data - opts.base_run_data;
+                if (Math.abs(diff) > 1) {
+                    yield new Finding(
+                        `Remote NUMA allocations are ${opts.this_run_data.toFixed(2)}% in '${opts.this_run}' and ${opts.base_run_data.toFixed(2)}% in '${opts.base_run}'.`,
+                        diff > 0 ? Status.NotGood : Status.Good,
+                    );
+                }
+            },
+        },
+    ]
+}
+
+function getNumaTopology(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var b = document.createElement('b');
+    b.innerHTML = 'NUMA topology';
+    addElemToNode(container_id, b);
+    var table = document.createElement('table');
+    addElemToNode(container_id, table);
+    var header = table.insertRow();
+    ['Node', 'CPUs', 'Memory (GiB)'].forEach(function (value, index, arr) {
+        header.insertCell().textContent = value;
+    });
+    data.forEach(function (value, index, arr) {
+        var row = table.insertRow();
+        row.insertCell().textContent = `${value.node}`;
+        row.insertCell().textContent = value.cpulist;
+        row.insertCell().textContent = (value.mem_total / (1024 * 1024)).toFixed(2);
+    });
+}
+
+function getNumaMemory(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var node_datas = [];
+    data.per_node.forEach(function (value, index, arr) {
+        var x_time = [];
+        var free = [];
+        var used = [];
+        value.data.forEach(function (v, i, a) {
+            x_time.push(time_diff_seconds(v.time));
+            free.push(v.free);
+            used.push(v.used);
+        });
+        node_datas.push({ x: x_time, y: used, type: 'scatter', name: `Node ${value.node} used` });
+        node_datas.push({ x: x_time, y: free, type: 'scatter', name: `Node ${value.node} free` });
+    });
+    var layout = {
+        title: 'Memory per node',
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'kB',
+            range: [0, data.metadata.limits.high],
+        },
+    };
+    Plotly.newPlot(elem, node_datas, add_markers(run, layout), { frameMargins: 0 });
+}
+
+function getNumaStat(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    ['numa_hit', 'numa_miss', 'numa_foreign', 'interleave_hit'].forEach(function (key, index, arr) {
+        var elem = document.createElement('div');
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        var node_datas = [];
+        data.per_node.forEach(function (value, i, a) {
+            var x_time = [];
+            var y_data = [];
+            value.data.forEach(function (v, j, b) {
+                x_time.push(time_diff_seconds(v.time));
+                y_data.push(v[key]);
+            });
+            node_datas.push({ x: x_time, y: y_data, type: 'scatter', name: `Node ${value.node}` });
+        });
+        var layout = {
+            title: key,
+            xaxis: {
+                title: 'Time (s)',
+            },
+            yaxis: {
+                title: 'Pages',
+                rangemode: 'tozero',
+            },
+        };
+        setTimeout(() => {
+            Plotly.newPlot(elem, node_datas, add_markers(run, layout), { frameMargins: 0 });
+        }, 0);
+    });
+}
+
+function numa() {
+    if (got_numa_data) {
+        return;
+    }
+    clear_and_create('numa');
+    for (let i = 0; i < numa_raw_data['runs'].length; i++) {
+        let run_name = numa_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-numa-per-data`;
+        let this_run_data = numa_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        let key_values = this_run_data['key_values'];
+        setTimeout(() => {
+            getNumaTopology(run_name, elem_id, key_values['topology']);
+            getNumaMemory(run_name, elem_id, key_values['memory']);
+            getNumaStat(run_name, elem_id, key_values['numastat']);
+        }, 0);
+    }
+    got_numa_data = true;
+}
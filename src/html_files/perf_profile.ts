@@ -23,6 +23,9 @@ function topFunctions() {
         let run_name = perf_profile_raw_data['runs'][i]['name'];
         let elem_id = `${run_name}-topfunctions-per-data`;
         let this_run_data = perf_profile_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
         setTimeout(() => {
             getTopFunctionsInfo(run_name, elem_id, this_run_data['key_values']['values']);
         }, 0);
@@ -115,7 +115,7 @@ function getEvent(elem, key, run_data, run) {
     });
     data.data.forEach(function (value, index, arr) {
         value.cpus.forEach(function (stat, i_index, i_arr) {
-            addData(perfstat_datas, stat, value.time.TimeDiff);
+            addData(perfstat_datas, stat, time_diff_seconds(value.time));
         })
     });
     var TESTER = elem;
@@ -158,7 +158,7 @@ function getEvent(elem, key, run_data, run) {
             range: [limits.low, limits.high],
         },
     }
-    Plotly.newPlot(TESTER, end_datas, layout, { frameMargins: 0 });
+    Plotly.newPlot(TESTER, end_datas, add_markers(run, layout), { frameMargins: 0 });
 }
 
 function perfStat() {
@@ -172,6 +172,9 @@ function perfStat() {
         perf_cpu_list.set(run_name, getCPUList(run_name));
         let elem_id = `${run_name}-perfstat-per-data`;
         let this_run_data = perf_stat_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
         getEvents(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
     }
     got_perf_stat_data = true;
@@ -0,0 +1,124 @@
+let got_pressure_data = false;
+let pressure_hide_zero_na_graphs = false;
+
+/* Share of time (%) over which some or all tasks stalled on a resource, as the avg10 of the run */
+function pressureRule(key, what, threshold) {
+    return {
+        name: `${key} avg10`,
+        single_run_rule: function* (opts): Generator<Finding, void, any> {
+            if (opts.base_run_data == undefined) {
+                return;
+            }
+            if (opts.base_run_data > threshold) {
+                yield new Finding(
+                    `${what} in '${opts.base_run}' for ${opts.base_run_data.toFixed(2)}% of the time (avg10), over ${threshold}%.`,
+                    Status.NotGood,
+                );
+            }
+        },
+    };
+}
+
+let pressure_rules = {
+    data_type: "pressure",
+    pretty_name: "PSI",
+    rules: [
+        pressureRule("cpu_some", "Tasks waited for CPU", 10),
+        pressureRule("memory_some", "Tasks stalled on memory", 5),
+        pressureRule("memory_full", "All tasks stalled on memory", 1),
+        pressureRule("io_some", "Tasks stalled on IO", 10),
+        pressureRule("io_full", "All tasks stalled on IO", 5),
+    ]
+}
+
+function getPressureEntries(run, container_id, keys, run_data) {
+    for (let i = 0; i < all_run_keys.length; i++) {
+        let value = all_run_keys[i];
+        var elem = document.createElement('div');
+        elem.id = `pressure-${run}-${value}`;
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        emptyOrCallback(keys, pressure_hide_zero_na_graphs, getPressureEntry, elem, value, run_data, run);
+    }
+}
+
+function getPressureEntry(elem, key, run_data, run) {
+    var data = JSON.parse(run_data);
+    var x_time = [];
+    var stall = [];
+    var avg10 = [];
+    var avg60 = [];
+    var avg300 = [];
+    data.data.forEach(function (value, index, arr) {
+        x_time.push(time_diff_seconds(value.time));
+        stall.push(value.stall);
+        avg10.push(value.avg10);
+        avg60.push(value.avg60);
+        avg300.push(value.avg300);
+    });
+    var TESTER = elem;
+    var stall_data: Partial<Plotly.PlotData> = {
+        x: x_time,
+        y: stall,
+        type: 'scatter',
+        name: 'Stall time',
+    };
+    var avg10_data: Partial<Plotly.PlotData> = {
+        x: x_time,
+        y: avg10,
+        type: 'scatter',
+        name: 'avg10',
+        yaxis: 'y2',
+    };
+    var avg60_data: Partial<Plotly.PlotData> = {
+        x: x_time,
+        y: avg60,
+        type: 'scatter',
+        name: 'avg60',
+        yaxis: 'y2',
+    };
+    var avg300_data: Partial<Plotly.PlotData> = {
+        x: x_time,
+        y: avg300,
+        type: 'scatter',
+        name: 'avg300',
+        yaxis: 'y2',
+    };
+    let limits = key_limits.get(key);
+    var layout = {
+        title: `${key}`,
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'Stall time (us)',
+            range: [limits.low, limits.high],
+        },
+        yaxis2: {
+            title: 'Stalled (%)',
+            overlaying: 'y',
+            side: 'right',
+            range: [0, 100],
+        },
+    }
+    Plotly.newPlot(TESTER, [stall_data, avg10_data, avg60_data, avg300_data], add_markers(run, layout), { frameMargins: 0 });
+}
+
+function pressure(hide: boolean) {
+    if (got_pressure_data && hide == pressure_hide_zero_na_graphs) {
+        return;
+    }
+    pressure_hide_zero_na_graphs = hide;
+    clear_and_create('pressure');
+    form_graph_limits(pressure_raw_data);
+    for (let i = 0; i < pressure_raw_data['runs'].length; i++) {
+        let run_name = pressure_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-pressure-per-data`;
+        let this_run_data = pressure_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        getPressureEntries(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
+    }
+    got_pressure_data = true;
+}
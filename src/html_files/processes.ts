@@ -1,21 +1,34 @@
-let got_process_data = false;
+let got_process_data: boolean|string = "none";
 
-function getProcesses(run, container_id, run_data) {
-    if (run_data.values == "No data collected") {
+let process_rankings = {
+    "cpu": { yaxis: 'Aggregate CPU Time (%)' },
+    "rss": { yaxis: 'RSS (MiB)' },
+    "vsz": { yaxis: 'VSZ (MiB)' },
+    "minor_faults": { yaxis: 'Minor faults/s' },
+    "major_faults": { yaxis: 'Major faults/s' },
+    "threads": { yaxis: 'Threads' },
+    "voluntary_ctxt": { yaxis: 'Voluntary context switches/s' },
+    "involuntary_ctxt": { yaxis: 'Involuntary context switches/s' },
+    "read_bytes": { yaxis: 'Read (bytes/s)' },
+    "write_bytes": { yaxis: 'Written (bytes/s)' },
+};
+
+function getProcesses(run, container_id, ranking, run_data) {
+    if (!(ranking in run_data)) {
         var no_data_div = document.createElement('div');
         no_data_div.id = `processes-${run}-no-data`;
         no_data_div.innerHTML = "No data collected";
         addElemToNode(container_id, no_data_div);
         return;
     }
-    var data = JSON.parse(run_data.values);
+    var data = JSON.parse(run_data[ranking]);
     data.end_entries.forEach(function (value, index, arr) {
         let process_datas = [];
         var x_time = [];
         var y_data = [];
         value.entries.forEach(function (v, i, a) {
-            x_time.push(v.time.TimeDiff);
-            y_data.push(v.cpu_time);
+            x_time.push(time_diff_seconds(v.time));
+            y_data.push(v.value);
         });
         var process_data: Partial<Plotly.PlotData> = {
             name: `${value.name}`,
@@ -32,26 +45,30 @@ function getProcesses(run, container_id, run_data) {
             title: value.name,
             xaxis: {
                 title: 'Time(s)',
-                range: [0, data.collection_time.TimeDiff],
+                range: [0, time_diff_seconds(data.collection_time)],
             },
             yaxis: {
-                title: 'Aggregate CPU Time (%)',
+                title: process_rankings[ranking].yaxis,
             },
         }
-        Plotly.newPlot(TESTER, process_datas, layout, { frameMargins: 0 });
+        Plotly.newPlot(TESTER, process_datas, add_markers(run, layout), { frameMargins: 0 });
     })
 }
 
-function processes() {
-    if (got_process_data) {
+function processes(set) {
+    let ranking = set.replace('processes-', '');
+    if (ranking == got_process_data) {
         return;
     }
+    got_process_data = ranking;
     clear_and_create('processes');
     for (let i = 0; i < processes_raw_data['runs'].length; i++) {
         let run_name = processes_raw_data['runs'][i]['name'];
         let elem_id = `${run_name}-processes-per-data`;
         let this_run_data = processes_raw_data['runs'][i];
-        getProcesses(run_name, elem_id, this_run_data['key_values']);
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        getProcesses(run_name, elem_id, ranking, this_run_data['key_values']);
     }
-    got_process_data = true;
 }
@@ -0,0 +1,158 @@
+let got_scheduler_data = false;
+let scheduler_cpu_list: Map<string, CPUList> = new Map<string, CPUList>();
+
+function getSchedulerKernel(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var x_time = [];
+    var ctxt = [];
+    var running = [];
+    var blocked = [];
+    data.forEach(function (value, index, arr) {
+        x_time.push(time_diff_seconds(value.time));
+        ctxt.push(value.ctxt);
+        running.push(value.procs_running);
+        blocked.push(value.procs_blocked);
+    });
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var ctxt_data: Partial<Plotly.PlotData> = {
+        x: x_time,
+        y: ctxt,
+        type: 'scatter',
+        name: 'Context switches/s',
+    };
+    var running_data: Partial<Plotly.PlotData> = {
+        x: x_time,
+        y: running,
+        type: 'scatter',
+        name: 'Running',
+        yaxis: 'y2',
+    };
+    var blocked_data: Partial<Plotly.PlotData> = {
+        x: x_time,
+        y: blocked,
+        type: 'scatter',
+        name: 'Blocked',
+        yaxis: 'y2',
+    };
+    var layout = {
+        title: 'Context switches and tasks',
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'Context switches/s',
+        },
+        yaxis2: {
+            title: 'Tasks',
+            overlaying: 'y',
+            side: 'right',
+            rangemode: 'tozero',
+        },
+    };
+    Plotly.newPlot(elem, [ctxt_data, running_data, blocked_data], add_markers(run, layout), { frameMargins: 0 });
+}
+
+function getSchedulerLoad(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var x_time = [];
+    var load1 = [];
+    var load5 = [];
+    var load15 = [];
+    var runnable = [];
+    data.forEach(function (value, index, arr) {
+        x_time.push(time_diff_seconds(value.time));
+        load1.push(value.values.load1);
+        load5.push(value.values.load5);
+        load15.push(value.values.load15);
+        runnable.push(value.values.runnable);
+    });
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var layout = {
+        title: 'Load average',
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'Load',
+            rangemode: 'tozero',
+        },
+    };
+    let load_datas: Array<Partial<Plotly.PlotData>> = [
+        { x: x_time, y: load1, type: 'scatter', name: '1 min' },
+        { x: x_time, y: load5, type: 'scatter', name: '5 min' },
+        { x: x_time, y: load15, type: 'scatter', name: '15 min' },
+        { x: x_time, y: runnable, type: 'scatter', name: 'Runnable tasks' },
+    ];
+    Plotly.newPlot(elem, load_datas, add_markers(run, layout), { frameMargins: 0 });
+}
+
+function getSchedulerWaitLatency(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var cpu_datas = [];
+    data.per_cpu.forEach(function (value, index, arr) {
+        var x_time = [];
+        var y_data = [];
+        value.data.forEach(function (v, i, a) {
+            x_time.push(time_diff_seconds(v.time));
+            y_data.push(v.latency);
+        });
+        var cpu_data: Partial<Plotly.PlotData> = {
+            name: `CPU ${value.cpu}`,
+            x: x_time,
+            y: y_data,
+            type: 'scatter',
+        };
+        if (scheduler_cpu_list.get(run).cpulist.indexOf(value.cpu.toString()) == -1) {
+            cpu_data.visible = 'legendonly';
+        }
+        cpu_datas.push(cpu_data);
+    });
+    var layout = {
+        title: 'Run queue wait per timeslice',
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'Wait (us)',
+            range: [data.metadata.limits.low, data.metadata.limits.high],
+        },
+    };
+    Plotly.newPlot(elem, cpu_datas, add_markers(run, layout), { frameMargins: 0 });
+}
+
+function scheduler() {
+    if (got_scheduler_data && allRunCPUListUnchanged(scheduler_cpu_list)) {
+        return;
+    }
+    clear_and_create('scheduler');
+    for (let i = 0; i < runs_raw.length; i++) {
+        let run_name = runs_raw[i];
+        scheduler_cpu_list.set(run_name, getCPUList(run_name));
+        let elem_id = `${run_name}-scheduler-per-data`;
+
+        let this_run_data = scheduler_raw_data['runs'].find(r => r['name'] == run_name);
+        if (!this_run_data || not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        let key_values = this_run_data['key_values'];
+        setTimeout(() => {
+            if ('load' in key_values) {
+                getSchedulerLoad(run_name, elem_id, key_values['load']);
+            }
+            if ('kernel' in key_values) {
+                getSchedulerKernel(run_name, elem_id, key_values['kernel']);
+            }
+            if ('wait_latency' in key_values) {
+                getSchedulerWaitLatency(run_name, elem_id, key_values['wait_latency']);
+            }
+        }, 0);
+    }
+    got_scheduler_data = true;
+}
@@ -0,0 +1,66 @@
+let got_slabinfo_data: boolean|string = "none";
+
+let slabinfo_rankings = {
+    "memory": { title: 'Top slab caches by memory growth', yaxis: 'Memory (KiB)', unit: 'KiB' },
+    "active_objects": { title: 'Top slab caches by active objects growth', yaxis: 'Active objects', unit: 'objects' },
+};
+
+function getSlabinfo(run, container_id, ranking, run_data) {
+    var data = JSON.parse(run_data[ranking]);
+    let cache_datas = [];
+    data.end_entries.forEach(function (value, index, arr) {
+        var x_time = [];
+        var y_data = [];
+        value.entries.forEach(function (v, i, a) {
+            x_time.push(time_diff_seconds(v.time));
+            y_data.push(v.value);
+        });
+        var cache_data: Partial<Plotly.PlotData> = {
+            name: `${index + 1}. ${value.name}`,
+            x: x_time,
+            y: y_data,
+            type: 'scatter',
+        };
+        cache_datas.push(cache_data);
+    });
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var layout = {
+        title: slabinfo_rankings[ranking].title,
+        xaxis: {
+            title: 'Time (s)',
+            range: [0, time_diff_seconds(data.collection_time)],
+        },
+        yaxis: {
+            title: slabinfo_rankings[ranking].yaxis,
+        },
+    }
+    Plotly.newPlot(elem, cache_datas, add_markers(run, layout), { frameMargins: 0 });
+
+    var list = document.createElement('ol');
+    data.end_entries.forEach(function (value, index, arr) {
+        var item = document.createElement('li');
+        item.innerText = `${value.name}: ${value.total >= 0 ? '+' : ''}${value.total.toFixed(0)} ${slabinfo_rankings[ranking].unit}`;
+        list.appendChild(item);
+    });
+    addElemToNode(container_id, list);
+}
+
+function slabinfo(set) {
+    let ranking = set.replace('slabinfo-', '');
+    if (ranking == got_slabinfo_data) {
+        return;
+    }
+    got_slabinfo_data = ranking;
+    clear_and_create('slabinfo');
+    for (let i = 0; i < slabinfo_raw_data['runs'].length; i++) {
+        let run_name = slabinfo_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-slabinfo-per-data`;
+        let this_run_data = slabinfo_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        getSlabinfo(run_name, elem_id, ranking, this_run_data['key_values']);
+    }
+}
@@ -0,0 +1,135 @@
+let got_snmp_data = false;
+let snmp_hide_zero_na_graphs = false;
+
+function snmpRetransRatio(run) {
+    let out_segs = get_data_key("snmp", "Tcp.OutSegs").get(run);
+    let retrans = get_data_key("snmp", "Tcp.RetransSegs").get(run);
+    if (!out_segs || retrans == undefined) {
+        return undefined;
+    }
+    return retrans / out_segs * 100;
+}
+
+function snmpDropRule(name: string, what: string) {
+    return {
+        name: name,
+        single_run_rule: function* (opts): Generator<Finding, void, any> {
+            if (opts.base_run_data > 0) {
+                yield new Finding(
+                    `${what} for '${opts.base_run}' average ${opts.base_run_data.toFixed(2)} per interval.`,
+                    Status.NotGood,
+                );
+            }
+        },
+        per_run_rule: function* (opts): Generator<Finding, void, any> {
+            if (opts.base_run_data == undefined || opts.this_run_data == undefined) {
+                return;
+            }
+            if (opts.this_run_data > opts.base_run_data && percent_difference(opts.base_run_data, opts.this_run_data) > 10) {
+                yield new Finding(
+                    `${what} for '${opts.this_run}' are higher than for '${opts.base_run}' (${opts.this_run_data.toFixed(2)} vs ${opts.base_run_data.toFixed(2)} per interval).`,
+                    Status.NotGood,
+                );
+            }
+        },
+    };
+}
+
+let snmp_rules = {
+    data_type: "snmp",
+    pretty_name: "SNMP",
+    rules: [
+        {
+            name: "Tcp.RetransSegs",
+            single_run_rule: function* (opts): Generator<Finding, void, any> {
+                let ratio = snmpRetransRatio(opts.base_run);
+                if (ratio == undefined) {
+                    return;
+                }
+                let thresh = 1.;
+                yield new Finding(
+                    `TCP retransmits for '${opts.base_run}' are ${ratio.toFixed(2)}% of segments sent.`,
+                    ratio > thresh ? Status.NotGood : Status.Good,
+                );
+            },
+            per_run_rule: function* (opts): Generator<Finding, void, any> {
+                let base_ratio = snmpRetransRatio(opts.base_run);
+                let this_ratio = snmpRetransRatio(opts.this_run);
+                if (base_ratio == undefined || this_ratio == undefined) {
+                    return;
+                }
+                let diff = Math.abs(this_ratio - base_ratio);
+                if (diff > 0.5) {
+                    yield new Finding(
+                        `TCP retransmit rate difference between '${opts.base_run}' and '${opts.this_run}' is ${diff.toFixed(2)}% of segments sent.`,
+                        Status.NotGood,
+                    );
+                }
+            },
+        },
+        snmpDropRule("Udp.RcvbufErrors", "UDP receive buffer errors"),
+        snmpDropRule("Udp.InErrors", "UDP receive errors"),
+        snmpDropRule("Ip.InDiscards", "Discarded IP packets"),
+        snmpDropRule("Icmp.InErrors", "ICMP errors"),
+        snmpDropRule("Udp6.RcvbufErrors", "UDPv6 receive buffer errors"),
+        snmpDropRule("Ip6.InDiscards", "Discarded IPv6 packets"),
+    ]
+}
+
+function getSnmpEntries(run, container_id, keys, run_data) {
+    for (let i = 0; i < all_run_keys.length; i++) {
+        let value = all_run_keys[i];
+        var elem = document.createElement('div');
+        elem.id = `snmp-${run}-${value}`;
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        emptyOrCallback(keys, snmp_hide_zero_na_graphs, getSnmpEntry, elem, value, run_data, run);
+    }
+}
+
+function getSnmpEntry(elem, key, run_data, run) {
+    var data = JSON.parse(run_data);
+    var x_time = [];
+    var y_data = [];
+    data.data.forEach(function (value, index, arr) {
+        x_time.push(time_diff_seconds(value.time));
+        y_data.push(value.value);
+    });
+    var TESTER = elem;
+    var snmp_data: Partial<Plotly.PlotData> = {
+        x: x_time,
+        y: y_data,
+        type: 'scatter',
+    };
+    let limits = key_limits.get(key);
+    var layout = {
+        title: `${key}`,
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'Count',
+            range: [limits.low, limits.high],
+        },
+    }
+    Plotly.newPlot(TESTER, [snmp_data], add_markers(run, layout), { frameMargins: 0 });
+}
+
+function snmp(hide: boolean) {
+    if (got_snmp_data && hide == snmp_hide_zero_na_graphs) {
+        return;
+    }
+    snmp_hide_zero_na_graphs = hide;
+    clear_and_create('snmp');
+    form_graph_limits(snmp_raw_data);
+    for (let i = 0; i < snmp_raw_data['runs'].length; i++) {
+        let run_name = snmp_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-snmp-per-data`;
+        let this_run_data = snmp_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        getSnmpEntries(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
+    }
+    got_snmp_data = true;
+}
@@ -0,0 +1,92 @@
+let got_softirq_data = false;
+let softirq_cpu_list: Map<string, CPUList> = new Map<string, CPUList>();
+
+let softirqs_rules = {
+    data_type: "softirqs",
+    pretty_name: "Softirqs",
+    rules: [
+        {
+            name: "NET_RX imbalance",
+            single_run_rule: function* (opts): Generator<Finding, void, any> {
+                let thresh = 2;
+                if (opts.base_run_data > thresh) {
+                    yield new Finding(
+                        `The busiest CPU of '${opts.base_run}' handled ${opts.base_run_data.toFixed(1)} times its share of NET_RX softirqs.`,
+                        Status.NotGood,
+                        "Spread receive processing over more CPUs with RSS, RPS or irqbalance.",
+                    );
+                }
+            },
+        },
+    ]
+}
+
+function getSoftirq(run, elem, key, run_data) {
+    var data = JSON.parse(run_data);
+    var cpus = data[0].per_cpu.length;
+    var softirq_datas = [];
+    for (let cpu = 0; cpu < cpus; cpu++) {
+        var x_time = [];
+        var y_data = [];
+        data.forEach(function (value, index, arr) {
+            value.per_cpu.forEach(function (v, i, a) {
+                if (v.cpu == cpu) {
+                    x_time.push(time_diff_seconds(value.time));
+                    y_data.push(v.count);
+                }
+            })
+        })
+        var softirq_cpu_data: Partial<Plotly.PlotData> = {
+            name: `CPU ${cpu}`,
+            x: x_time,
+            y: y_data,
+            type: 'scatter',
+        };
+        if (softirq_cpu_list.get(run).cpulist.indexOf(cpu.toString()) == -1) {
+            softirq_cpu_data.visible = 'legendonly';
+        }
+        softirq_datas.push(softirq_cpu_data);
+    }
+    var TESTER = elem;
+    var layout = {
+        title: key,
+        xaxis: {
+            title: 'Time (s)',
+        },
+        yaxis: {
+            title: 'Count',
+        }
+    };
+    Plotly.newPlot(TESTER, softirq_datas, add_markers(run, layout), { frameMargins: 0 });
+}
+
+function getSoftirqs(run, container_id, keys, run_data) {
+    var data = keys;
+    data.forEach(function (value, index, arr) {
+        var elem = document.createElement('div');
+        elem.id = `softirq-${run}-${value}`;
+        elem.style.float = "none";
+        addElemToNode(container_id, elem);
+        setTimeout(() => {
+            getSoftirq(run, elem, value, run_data[value]);
+        }, 0);
+    })
+}
+
+function softirqs() {
+    if (got_softirq_data && allRunCPUListUnchanged(softirq_cpu_list)) {
+        return;
+    }
+    clear_and_create('softirqs');
+    for (let i = 0; i < softirqs_raw_data['runs'].length; i++) {
+        let run_name = softirqs_raw_data['runs'][i]['name'];
+        softirq_cpu_list.set(run_name, getCPUList(run_name));
+        let elem_id = `${run_name}-softirqs-per-data`;
+        let this_run_data = softirqs_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        getSoftirqs(run_name, elem_id, this_run_data['keys'], this_run_data['key_values']);
+    }
+    got_softirq_data = true;
+}
@@ -76,6 +76,9 @@ function sysctl(diff: boolean) {
             for (let i = 0; i < sysctl_raw_data['runs'].length; i++) {
                 if (sysctl_raw_data['runs'][i]['name'] == value) {
                     this_run_data = sysctl_raw_data['runs'][i];
+                    if (this_run_data['collected'] === false) {
+                        continue;
+                    }
                     form_sysctl_data(value, this_run_data);
                 }
             }
@@ -85,6 +88,10 @@ function sysctl(diff: boolean) {
 
     clear_and_create('sysctl');
     data.forEach(function (value, index, arr) {
+        if (!sysctl_runs.has(value)) {
+            show_not_collected(`${value}-sysctl-per-data`);
+            return;
+        }
         if (current_sysctl_diff_status) {
             sysctlDiff(value, `${value}-sysctl-per-data`);
         } else {
@@ -163,9 +163,25 @@ function sutconfig() {
         let run_name = system_info_raw_data['runs'][i]['name'];
         let elem_id = `${run_name}-systeminfo-per-data`;
         let this_run_data = system_info_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
         setTimeout(() => {
             getSystemInfo(run_name, elem_id, this_run_data['key_values']['values']);
         }, 0);
+        /* The NUMA topology is recorded by the numa collector */
+        let numa_run_data = numa_raw_data['runs'].find(r => r['name'] == run_name);
+        if (numa_run_data && numa_run_data['key_values'] && 'topology' in numa_run_data['key_values']) {
+            setTimeout(() => {
+                getNumaTopology(run_name, elem_id, numa_run_data['key_values']['topology']);
+            }, 0);
+        }
+        let topology_run_data = hardware_topology_raw_data['runs'].find(r => r['name'] == run_name);
+        if (topology_run_data && topology_run_data['key_values'] && 'values' in topology_run_data['key_values']) {
+            setTimeout(() => {
+                getHardwareTopology(run_name, elem_id, topology_run_data['key_values']['values']);
+            }, 0);
+        }
     }
 }
 
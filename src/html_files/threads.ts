@@ -0,0 +1,66 @@
+let got_threads_data = false;
+
+function getThreads(run, container_id, run_data) {
+    var data = JSON.parse(run_data);
+    if (data.groups.length == 0) {
+        var h3 = document.createElement('h3');
+        h3.innerText = "No thread of the processes was sampled.";
+        addElemToNode(container_id, h3);
+        return;
+    }
+    let thread_datas = [];
+    data.groups.forEach(function (value, index, arr) {
+        var x_time = [];
+        var y_data = [];
+        value.samples.forEach(function (v, i, a) {
+            x_time.push(time_diff_seconds(v.time));
+            y_data.push(v.value);
+        });
+        var thread_data: Partial<Plotly.PlotData> = {
+            name: `${index + 1}. ${value.name}`,
+            x: x_time,
+            y: y_data,
+            type: 'scatter',
+        };
+        thread_datas.push(thread_data);
+    });
+    var elem = document.createElement('div');
+    elem.style.float = "none";
+    addElemToNode(container_id, elem);
+    var layout = {
+        title: 'Top threads by CPU time',
+        xaxis: {
+            title: 'Time (s)',
+            range: [0, time_diff_seconds(data.collection_time)],
+        },
+        yaxis: {
+            title: 'CPU (% of one CPU)',
+        },
+    }
+    Plotly.newPlot(elem, thread_datas, add_markers(run, layout), { frameMargins: 0 });
+
+    var list = document.createElement('ol');
+    data.groups.forEach(function (value, index, arr) {
+        var item = document.createElement('li');
+        item.innerText = `${value.name}: ${value.total.toFixed(2)} s of CPU time, up to ${value.threads} thread(s)`;
+        list.appendChild(item);
+    });
+    addElemToNode(container_id, list);
+}
+
+function threads() {
+    if (got_threads_data) {
+        return;
+    }
+    clear_and_create('threads');
+    for (let i = 0; i < threads_raw_data['runs'].length; i++) {
+        let run_name = threads_raw_data['runs'][i]['name'];
+        let elem_id = `${run_name}-threads-per-data`;
+        let this_run_data = threads_raw_data['runs'][i];
+        if (not_collected(elem_id, this_run_data)) {
+            continue;
+        }
+        getThreads(run_name, elem_id, this_run_data['key_values']['values']);
+    }
+    got_threads_data = true;
+}
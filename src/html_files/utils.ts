@@ -4,7 +4,19 @@ declare let cpu_utilization_raw_data;
 declare let vmstat_raw_data;
 declare let kernel_config_raw_data;
 declare let sysctl_raw_data;
+declare let hardware_topology_raw_data;
+declare let kernel_runtime_raw_data;
 declare let interrupts_raw_data;
+declare let softirqs_raw_data;
+declare let scheduler_raw_data;
+declare let numa_raw_data;
+declare let cpufreq_raw_data;
+declare let cpufreq_settings_raw_data;
+declare let mounts_raw_data;
+declare let filesystem_usage_raw_data;
+declare let slabinfo_raw_data;
+declare let fragmentation_raw_data;
+declare let threads_raw_data;
 declare let disk_stats_raw_data;
 declare let perf_stat_raw_data;
 declare let processes_raw_data;
@@ -15,6 +27,12 @@ declare let flamegraph_raw_data;
 declare let aperf_run_stats_raw_data;
 declare let java_profile_raw_data;
 declare let aperf_runlog_raw_data;
+declare let aperf_workload_raw_data;
+declare let aperf_markers_raw_data;
+declare let net_dev_raw_data;
+declare let snmp_raw_data;
+declare let pressure_raw_data;
+declare let cgroup_raw_data;
 declare let raw_analytics;
 
 let comparator = 'mean';
@@ -115,6 +133,34 @@ function clearElements(id: string) {
 	}
 }
 
+function show_not_collected(elem_id) {
+    var h3 = document.createElement('h3');
+    h3.innerHTML = "Not collected";
+    h3.style.textAlign = "center";
+    addElemToNode(elem_id, h3);
+}
+
+/* Shows a banner if only part of the data of this run could be read. */
+function show_health(elem_id, run_data) {
+    if (!run_data['health']) {
+        return;
+    }
+    var div = document.createElement('div');
+    div.className = "health-banner";
+    div.innerText = `Damaged data: ${run_data['health']}`;
+    addElemToNode(elem_id, div);
+}
+
+/* Shows a notice and returns true if the collector was skipped for this run. */
+function not_collected(elem_id, run_data) {
+    show_health(elem_id, run_data);
+    if (run_data['collected'] === false) {
+        show_not_collected(elem_id);
+        return true;
+    }
+    return false;
+}
+
 function addElemToNode(node_id: string, elem: HTMLElement) {
 	let node: HTMLElement = document.getElementById(node_id);
 	node.appendChild(elem);
@@ -203,3 +249,8 @@ function get_data_key(data_type, key) {
 function percent_difference(v1, v2) {
     return Math.ceil((Math.abs(v1 - v2)/((v1 + v2)/2)) * 100);
 }
+
+/* TimeDiff values are in milliseconds, graphs plot time in seconds. */
+function time_diff_seconds(time) {
+    return time.TimeDiff / 1000;
+}
@@ -1,29 +1,41 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod config;
+pub mod control;
+pub mod daemon;
 pub mod data;
+pub mod overhead;
 pub mod pmu;
 pub mod record;
+pub mod recover;
 pub mod report;
 pub mod utils;
 pub mod visualizer;
 use anyhow::Result;
 use chrono::prelude::*;
+use control::{ControlSocket, Request};
+use data::cgroup::{DEFAULT_CGROUP_DEPTH, DEFAULT_CGROUP_ROOT};
+use data::markers::{self, MARKERS_FILE_NAME};
+use data::records;
+use data::workload::WorkloadRunner;
 use data::TimeEnum;
 use flate2::{write::GzEncoder, Compression};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
 use nix::sys::{
     signal,
     signalfd::{SfdFlags, SigSet, SignalFd},
 };
+use overhead::{OverheadBudget, SampleRate, Throttle};
 use serde::{Deserialize, Serialize};
 use serde_json::{self};
 use std::collections::HashMap;
+use std::io::Write;
 use std::os::unix::io::AsFd;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use std::{fs, process, time};
+use std::{fs, process, thread, time};
 use thiserror::Error;
 use timerfd::{SetTimeFlags, TimerFd, TimerState};
 use utils::DataMetrics;
@@ -31,6 +43,10 @@ use utils::DataMetrics;
 pub static APERF_FILE_FORMAT: &str = "bin";
 pub static APERF_TMP: &str = "/tmp";
 pub static APERF_RUNLOG: &str = "aperf_runlog";
+pub static PMU_CONFIG_FILE_NAME: &str = "pmu_config.json";
+
+/// How often the data files of a run are flushed to disk while recording.
+pub const FSYNC_INTERVAL: time::Duration = time::Duration::from_secs(5);
 
 #[derive(Error, Debug)]
 pub enum PDError {
@@ -111,6 +127,63 @@ pub enum PDError {
 
     #[error("Dependency error: {}", .0)]
     DependencyError(String),
+
+    #[error("Unknown collector '{}'. Available collectors: {}", .0, .1)]
+    CollectorUnknown(String, String),
+
+    #[error("Invalid config file {}: {}", .0, .1)]
+    ConfigFileInvalid(String, String),
+
+    #[error("Config file {} must be YAML (.yaml, .yml) or TOML (.toml)", .0)]
+    ConfigFileUnsupported(String),
+
+    #[error("Invalid record settings: {}", .0)]
+    RecordConfigInvalid(String),
+
+    #[error("Markers are not collected in this run")]
+    MarkersNotCollected,
+
+    #[error("Record at byte {} is incomplete", .0)]
+    RecordTorn(u64),
+
+    #[error("Record at byte {} does not match its checksum", .0)]
+    RecordChecksumMismatch(u64),
+
+    #[error("{} is not an aperf run directory", .0)]
+    RecoverNotARun(String),
+
+    #[error("Invalid Data type in raw file")]
+    InvalidRawDataType,
+
+    #[error("Malformed {} data: {}", .0, .1)]
+    ProcessorMalformedData(String, String),
+
+    #[error("Pressure Stall Information is not available")]
+    CollectorPressureUnavailable,
+
+    #[error("Error getting Pressure value for {}", .0)]
+    VisualizerPressureValueGetError(String),
+
+    #[error("Error getting SNMP value for {}", .0)]
+    VisualizerSnmpValueGetError(String),
+
+    #[error("Error getting Softirq value for {}", .0)]
+    VisualizerSoftirqValueGetError(String),
+
+    #[error("No process given to sample the threads of")]
+    CollectorThreadsNoTarget,
+
+    #[error("CPU frequency and idle states are not available")]
+    CollectorCpufreqUnavailable,
+
+    #[error("No NUMA nodes, the kernel is built without NUMA support")]
+    CollectorNumaUnavailable,
+
+    #[error("No cgroup v2 hierarchy at {}", .0)]
+    CollectorCgroupV2Unavailable(String),
+
+    #[error("Neither /proc/slabinfo nor /sys/kernel/slab is readable")]
+    CollectorSlabinfoUnavailable,
 }
 
 #[macro_export]
@@ -153,9 +226,15 @@ impl AperfStat {
 #[allow(missing_docs)]
 pub struct PerformanceData {
     pub collectors: HashMap<String, data::DataType>,
+    /// Collectors that are not selected or could not be prepared, kept for the daemon to select
+    /// them again when its config is reloaded.
+    pub unselected: HashMap<String, data::DataType>,
+    /// Flags the daemon was started with, merged again with its config file on SIGHUP.
+    pub daemon: Option<daemon::Daemon>,
     pub init_params: InitParams,
     pub aperf_stats_path: PathBuf,
     pub aperf_stats_handle: Option<fs::File>,
+    pub aperf_stats_ring: Option<data::RingBuffer>,
 }
 
 impl PerformanceData {
@@ -165,9 +244,12 @@ impl PerformanceData {
 
         PerformanceData {
             collectors,
+            unselected: HashMap::new(),
+            daemon: None,
             init_params,
             aperf_stats_path: PathBuf::new(),
             aperf_stats_handle: None,
+            aperf_stats_ring: None,
         }
     }
 
@@ -179,25 +261,67 @@ impl PerformanceData {
         self.collectors.insert(name, dt);
     }
 
+    /// Keep only the collectors chosen with --include/--exclude. Markers are kept unless excluded.
+    /// The selection is made from all collectors, including those left out by an earlier one.
+    pub fn select_collectors(&mut self, include: &[String], exclude: &[String]) -> Result<()> {
+        for name in include.iter().chain(exclude) {
+            if !self.collectors.contains_key(name) && !self.unselected.contains_key(name) {
+                let mut names: Vec<String> = self
+                    .collectors
+                    .keys()
+                    .chain(self.unselected.keys())
+                    .cloned()
+                    .collect();
+                names.sort();
+                return Err(PDError::CollectorUnknown(name.clone(), names.join(", ")).into());
+            }
+        }
+        self.collectors.extend(self.unselected.drain());
+        let unselected: Vec<String> = self
+            .collectors
+            .keys()
+            .filter(|name| {
+                (!include.is_empty() && !include.contains(name) && *name != MARKERS_FILE_NAME)
+                    || exclude.contains(name)
+            })
+            .cloned()
+            .collect();
+        for name in unselected {
+            let datatype = self.collectors.remove(&name).unwrap();
+            self.unselected.insert(name, datatype);
+        }
+        Ok(())
+    }
+
     pub fn init_collectors(&mut self) -> Result<()> {
         fs::create_dir(self.init_params.dir_name.clone())?;
 
+        self.set_collected();
+        self.init_run_dir()
+    }
+
+    /// Record the collectors of the run in the meta data, for the report to tell the collectors
+    /// which were not selected or not available from the ones whose data is missing.
+    fn set_collected(&mut self) {
+        let mut collectors: Vec<String> = self
+            .collectors
+            .iter()
+            .filter(|(name, datatype)| {
+                !datatype.is_profile_option || self.init_params.profile.contains_key(*name)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        collectors.sort();
+        self.init_params.collectors = collectors;
+    }
+
+    /// Write the meta data and create the data files of every collector in the run directory.
+    fn init_run_dir(&mut self) -> Result<()> {
         /*
          * Create a meta_data file to hold the InitParams that was used by the collector.
          * This will help when we visualize the data and we don't have to guess these values.
          */
-        let meta_data_path = format!(
-            "{}/meta_data.{}",
-            self.init_params.dir_name.clone(),
-            APERF_FILE_FORMAT
-        );
-        let meta_data_handle = fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .open(meta_data_path.clone())
-            .expect("Could not create meta-data file");
-
-        bincode::serialize_into(meta_data_handle, &self.init_params)?;
+        self.write_meta_data()?;
 
         self.aperf_stats_path = PathBuf::from(self.init_params.dir_name.clone())
             .join(format!("aperf_run_stats.{}", APERF_FILE_FORMAT));
@@ -208,6 +332,11 @@ impl PerformanceData {
                 .open(self.aperf_stats_path.clone())
                 .expect("Could not create aperf-stats file"),
         );
+        self.aperf_stats_handle
+            .as_ref()
+            .unwrap()
+            .write_all(records::RECORD_MAGIC)?;
+        self.aperf_stats_ring = self.init_params.flight_recorder.map(data::RingBuffer::new);
 
         for (_name, datatype) in self.collectors.iter_mut() {
             datatype.init_data_type(&self.init_params)?;
@@ -215,6 +344,82 @@ impl PerformanceData {
         Ok(())
     }
 
+    fn write_meta_data(&self) -> Result<()> {
+        let meta_data_path = format!(
+            "{}/meta_data.{}",
+            self.init_params.dir_name.clone(),
+            APERF_FILE_FORMAT
+        );
+        let meta_data_handle = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(meta_data_path.clone())
+            .expect("Could not create meta-data file");
+
+        bincode::serialize_into(&meta_data_handle, &self.init_params)?;
+        meta_data_handle.sync_all()?;
+        Ok(())
+    }
+
+    /// Close the current window of a daemon run into its archive and continue collecting into
+    /// a new run directory. Static data is collected again so that each archive is a complete run.
+    /// With `prepare` the collectors are prepared again, which re-reads the PMU config file and
+    /// takes up the settings of a reloaded config.
+    pub fn rotate(&mut self, prepare: bool) -> Result<()> {
+        let old_dir = PathBuf::from(&self.init_params.dir_name);
+        self.archive_window()?;
+
+        /* Run directories are named by the second they start in */
+        loop {
+            self.init_params.time_now = Utc::now();
+            self.init_params.time_str = self
+                .init_params
+                .time_now
+                .format("%Y-%m-%d_%H_%M_%S")
+                .to_string();
+            self.init_params.dir_name = format!(
+                "{}_{}",
+                self.init_params.dump_prefix, self.init_params.time_str
+            );
+            if !Path::new(&self.init_params.dir_name).exists() {
+                break;
+            }
+            thread::sleep(time::Duration::from_millis(100));
+        }
+        fs::create_dir(&self.init_params.dir_name)?;
+        let pmu_config = old_dir.join(PMU_CONFIG_FILE_NAME);
+        if pmu_config.exists() {
+            fs::copy(
+                &pmu_config,
+                Path::new(&self.init_params.dir_name).join(PMU_CONFIG_FILE_NAME),
+            )?;
+        }
+        fs::remove_dir_all(&old_dir)?;
+
+        /* Data files are named after the collector and the start time of the window */
+        for (name, datatype) in self.collectors.iter_mut() {
+            datatype.file_name = name.clone();
+        }
+        self.init_run_dir()?;
+        if prepare {
+            self.prepare_data_collectors()?;
+        }
+        self.collect_static_data()?;
+        daemon::prune_archives(
+            &self.init_params.dump_prefix,
+            self.init_params.keep,
+            self.init_params.max_size,
+        )
+    }
+
+    /// Archive the run directory of the current daemon window.
+    fn archive_window(&mut self) -> Result<()> {
+        let dst_path = PathBuf::from(&self.init_params.dir_name).join(APERF_RUNLOG);
+        fs::copy(&self.init_params.runlog, dst_path)?;
+        self.create_data_archive()
+    }
+
     pub fn prepare_data_collectors(&mut self) -> Result<()> {
         let mut remove_entries: Vec<String> = Vec::new();
 
@@ -244,9 +449,30 @@ impl PerformanceData {
                 _ => continue,
             }
         }
+        if remove_entries.is_empty() {
+            return Ok(());
+        }
         for key in remove_entries {
-            self.collectors.remove_entry(&key);
+            let datatype = self.collectors.remove(&key).unwrap();
+            self.unselected.insert(key, datatype);
         }
+        self.set_collected();
+        self.write_meta_data()
+    }
+
+    /// Read the config file of the daemon again and apply its settings. Nothing is changed if it
+    /// is not valid. The settings take effect with the next window.
+    pub fn reload_config(&mut self) -> Result<()> {
+        let daemon = match &self.daemon {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+        let config = daemon::daemon_config(daemon)?;
+        self.select_collectors(&config.collectors.include, &config.collectors.exclude)?;
+        daemon::apply_config(&mut self.init_params, &config);
+        /* Collectors are throttled afresh under the new budget */
+        self.init_params.sample_rates.clear();
+        info!("Reloaded the daemon config");
         Ok(())
     }
 
@@ -257,11 +483,24 @@ impl PerformanceData {
             }
             datatype.collect_data()?;
             datatype.write_to_file()?;
+            datatype.sync_data_file()?;
         }
 
         Ok(())
     }
 
+    /// Flush the data files of the run to disk, so that `aperf recover` can salvage them after
+    /// a crash or a power loss.
+    fn sync_data_files(&self) -> Result<()> {
+        for (_name, datatype) in self.collectors.iter() {
+            datatype.sync_data_file()?;
+        }
+        if let Some(handle) = &self.aperf_stats_handle {
+            handle.sync_data()?;
+        }
+        Ok(())
+    }
+
     pub fn collect_data_serial(&mut self) -> Result<()> {
         let start = time::Instant::now();
         let mut aperf_collect_data = AperfStat::new("aperf-collect-data".to_string());
@@ -272,27 +511,83 @@ impl PerformanceData {
         let mut tfd = TimerFd::new()?;
         tfd.set_state(
             TimerState::Periodic {
-                current: time::Duration::from_secs(self.init_params.interval),
-                interval: time::Duration::from_secs(self.init_params.interval),
+                current: self.init_params.interval,
+                interval: self.init_params.interval,
             },
             SetTimeFlags::Default,
         );
-        let timer_pollfd = PollFd::new(tfd.as_fd(), PollFlags::POLLIN);
 
         // SignalFd
         let mut mask = SigSet::empty();
         mask.add(signal::SIGINT);
         mask.add(signal::SIGTERM);
+        if !self.init_params.workload.is_empty() {
+            mask.add(signal::SIGCHLD);
+        }
+        if self.init_params.flight_recorder.is_some() {
+            mask.add(signal::SIGUSR1);
+        }
+        if self.init_params.rotate.is_some() {
+            mask.add(signal::SIGHUP);
+        }
         mask.thread_block()?;
+        if let Some(window) = self.init_params.flight_recorder {
+            info!(
+                "Flight recorder keeping the last {:?}. Send SIGUSR1 to PID {} to dump it.",
+                window,
+                process::id()
+            );
+        }
+        if let Some(rotate) = self.init_params.rotate {
+            info!(
+                "Starting a new archive every {:?}. Send SIGHUP to PID {} to start one now.",
+                rotate,
+                process::id()
+            );
+        }
         let sfd = SignalFd::with_flags(&mask, SfdFlags::SFD_NONBLOCK)?;
-        let signal_pollfd = PollFd::new(sfd.as_fd(), PollFlags::POLLIN);
 
-        let mut poll_fds = [timer_pollfd, signal_pollfd];
+        // Control socket
+        let control = match ControlSocket::bind(&self.init_params.tmp_dir) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                warn!(
+                    "Could not create the control socket, markers cannot be added: {}",
+                    e
+                );
+                None
+            }
+        };
+
+        let mut throttles = self.init_throttles();
+        let mut last_sync = time::Instant::now();
         let mut datatype_signal = signal::SIGTERM;
+        let mut collection_start: Option<DateTime<Utc>> = None;
+        let mut workload: Option<WorkloadRunner> = None;
+        let mut workload_error = None;
+        let mut rearm_timer = false;
 
         while current <= end {
             aperf_collect_data.time = TimeEnum::DateTime(Utc::now());
             aperf_collect_data.data = HashMap::new();
+            /* The interval may have changed with a reloaded daemon config */
+            if rearm_timer {
+                tfd.set_state(
+                    TimerState::Periodic {
+                        current: self.init_params.interval,
+                        interval: self.init_params.interval,
+                    },
+                    SetTimeFlags::Default,
+                );
+                rearm_timer = false;
+            }
+            let mut poll_fds = vec![
+                PollFd::new(tfd.as_fd(), PollFlags::POLLIN),
+                PollFd::new(sfd.as_fd(), PollFlags::POLLIN),
+            ];
+            if let Some(c) = &control {
+                poll_fds.push(PollFd::new(c.as_fd(), PollFlags::POLLIN));
+            }
             if poll(&mut poll_fds, PollTimeout::NONE)? <= 0 {
                 error!("Poll error.");
             }
@@ -303,11 +598,16 @@ impl PerformanceData {
                         error!("Missed {} interval(s)", ret - 1);
                     }
                     debug!("Time elapsed: {:?}", start.elapsed());
-                    current += time::Duration::from_secs(ret * self.init_params.interval);
+                    current += self.init_params.interval * ret as u32;
+                    let first_tick = *collection_start.get_or_insert_with(Utc::now);
+                    let mut throttled = Vec::new();
                     for (name, datatype) in self.collectors.iter_mut() {
                         if datatype.is_static {
                             continue;
                         }
+                        if throttles.get_mut(name).is_some_and(|t| !t.tick()) {
+                            continue;
+                        }
 
                         datatype.collector_params.elapsed_time = start.elapsed().as_secs();
 
@@ -322,16 +622,65 @@ impl PerformanceData {
                             datatype.write_to_file()?;
                             Ok(())
                         })?;
+                        if let Some(throttle) = throttles.get_mut(name) {
+                            let cost = aperf_collect_data.data[&(name.clone() + "-collect")]
+                                + aperf_collect_data.data[&(name.clone() + "-print")];
+                            if let Some(every) = throttle.sampled(cost) {
+                                throttled.push((name.clone(), every));
+                            }
+                        }
+                    }
+                    for (name, every) in throttled {
+                        self.set_sample_rate(&name, every)?;
                     }
                     let data_collection_time = time::Instant::now() - current;
                     aperf_collect_data
                         .data
                         .insert("aperf".to_string(), data_collection_time.as_micros() as u64);
                     debug!("Collection time: {:?}", data_collection_time);
-                    bincode::serialize_into(
-                        self.aperf_stats_handle.as_ref().unwrap(),
-                        &aperf_collect_data,
-                    )?;
+                    match self.aperf_stats_ring.as_mut() {
+                        Some(ring) => ring.push(records::encode(&aperf_collect_data)?),
+                        None => records::write_record(
+                            self.aperf_stats_handle.as_ref().unwrap(),
+                            &aperf_collect_data,
+                        )?,
+                    }
+                    if last_sync.elapsed() >= FSYNC_INTERVAL {
+                        self.sync_data_files()?;
+                        last_sync = time::Instant::now();
+                    }
+
+                    if let Some(rotate) = self.init_params.rotate {
+                        let window = (Utc::now() - self.init_params.time_now).to_std();
+                        if window.is_ok_and(|w| w >= rotate) {
+                            self.rotate(false)?;
+                        }
+                    }
+
+                    /*
+                     * The workload is started once the first samples are in, so that deltas
+                     * cover its whole lifetime. Collection stops at the first tick after it exits.
+                     */
+                    if !self.init_params.workload.is_empty() {
+                        match &workload {
+                            None => {
+                                match WorkloadRunner::spawn(&self.init_params.workload, first_tick)
+                                {
+                                    Ok(w) => {
+                                        markers::add_marker("Workload started");
+                                        workload = Some(w);
+                                    }
+                                    Err(e) => {
+                                        error!("Could not start the workload: {}", e);
+                                        workload_error = Some(e);
+                                        break;
+                                    }
+                                }
+                            }
+                            Some(w) if w.has_exited() => break,
+                            Some(_) => {}
+                        }
+                    }
                 }
             }
             if let Some(ev) = poll_fds[1].revents() {
@@ -342,6 +691,30 @@ impl PerformanceData {
                             datatype_signal = signal::SIGINT;
                         } else if siginfo.ssi_signo == signal::SIGTERM as u32 {
                             info!("Caught SIGTERM. Exiting...");
+                        } else if siginfo.ssi_signo == signal::SIGUSR1 as u32 {
+                            info!("Caught SIGUSR1. Dumping flight recorder data...");
+                            if let Err(e) = self.dump_flight_recorder() {
+                                error!("Could not dump flight recorder data: {}", e);
+                            }
+                            continue;
+                        } else if siginfo.ssi_signo == signal::SIGHUP as u32 {
+                            info!(
+                                "Caught SIGHUP. Reloading the config and starting a new archive..."
+                            );
+                            if let Err(e) = self.reload_config() {
+                                error!("Keeping the current settings: {}", e);
+                            }
+                            self.rotate(true)?;
+                            rearm_timer = true;
+                            throttles = self.init_throttles();
+                            continue;
+                        } else if siginfo.ssi_signo == signal::SIGCHLD as u32 {
+                            if let Some(w) = workload.as_mut() {
+                                if !w.has_exited() && w.try_wait()? {
+                                    markers::add_marker("Workload exited");
+                                }
+                            }
+                            continue;
                         } else {
                             panic!("Caught an unknown signal: {}", siginfo.ssi_signo);
                         }
@@ -349,6 +722,17 @@ impl PerformanceData {
                     }
                 }
             }
+            if let Some(ev) = poll_fds.get(2).and_then(|p| p.revents()) {
+                if ev.contains(PollFlags::POLLIN) {
+                    let started = collection_start.is_some();
+                    control.as_ref().unwrap().serve(|request| match request {
+                        Request::Mark(label) => self.add_marker(&label, started),
+                    })?;
+                }
+            }
+        }
+        if let Some(w) = workload {
+            w.write_to_file(Path::new(&self.init_params.dir_name))?;
         }
         for (_name, datatype) in self.collectors.iter_mut() {
             datatype.set_signal(datatype_signal);
@@ -358,10 +742,89 @@ impl PerformanceData {
             datatype.after_data_collection()?;
         }
         tfd.set_state(TimerState::Disarmed, SetTimeFlags::Default);
+        if let Some(e) = workload_error {
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Throttles of the collectors that may be sampled less often to stay within --max-overhead.
+    /// The budget is shared equally between them. Profilers and markers are never throttled.
+    fn init_throttles(&self) -> HashMap<String, Throttle> {
+        let budget = match self.init_params.max_overhead {
+            Some(b) => b.per_interval(self.init_params.interval),
+            None => return HashMap::new(),
+        };
+        let names: Vec<&String> = self
+            .collectors
+            .iter()
+            .filter(|(name, datatype)| {
+                !datatype.is_static && !datatype.is_profile_option && *name != MARKERS_FILE_NAME
+            })
+            .map(|(name, _)| name)
+            .collect();
+        if names.is_empty() {
+            return HashMap::new();
+        }
+        let share = budget / names.len() as u32;
+        info!(
+            "Overhead budget of {:?} per interval, {:?} for each collector",
+            budget, share
+        );
+        names
+            .into_iter()
+            .map(|name| (name.clone(), Throttle::new(share)))
+            .collect()
+    }
+
+    /// Record that a collector is now sampled once every `every` intervals. The rates are kept
+    /// in the meta data so that the report can plot the samples per interval.
+    fn set_sample_rate(&mut self, name: &str, every: u64) -> Result<()> {
+        info!(
+            "{} is over its share of the overhead budget, collecting it every {} intervals",
+            name, every
+        );
+        markers::add_marker(&format!("{} collected every {} intervals", name, every));
+        self.init_params
+            .sample_rates
+            .entry(name.to_string())
+            .or_default()
+            .push(SampleRate {
+                time: TimeEnum::DateTime(Utc::now()),
+                every,
+            });
+        self.write_meta_data()
+    }
+
+    /// Add a marker to the run. Once collection has started it is written out straight away.
+    fn add_marker(&mut self, label: &str, started: bool) -> Result<()> {
+        let datatype = self
+            .collectors
+            .get_mut(MARKERS_FILE_NAME)
+            .ok_or(PDError::MarkersNotCollected)?;
+        markers::add_marker(label);
+        if started {
+            datatype.collect_data()?;
+            datatype.write_to_file()?;
+        }
         Ok(())
     }
 
     pub fn end(&mut self) -> Result<()> {
+        if self.init_params.flight_recorder.is_some() {
+            // The working directory is in the tmp dir, keep the final window.
+            return self.dump_flight_recorder();
+        }
+        if self.init_params.rotate.is_some() {
+            // Only the archives of a daemon run are kept.
+            self.archive_window()?;
+            fs::remove_dir_all(&self.init_params.dir_name)?;
+            return daemon::prune_archives(
+                &self.init_params.dump_prefix,
+                self.init_params.keep,
+                self.init_params.max_size,
+            );
+        }
         let dst_path = PathBuf::from(&self.init_params.dir_name).join(APERF_RUNLOG);
         fs::copy(&self.init_params.runlog, dst_path)?;
 
@@ -370,31 +833,91 @@ impl PerformanceData {
         Ok(())
     }
 
+    /// Write the window held by the flight recorder into a new, timestamped run directory
+    /// next to the one given by the user and archive it.
+    pub fn dump_flight_recorder(&mut self) -> Result<()> {
+        /* Dumps are named by the millisecond they are taken in, so that close ones are all kept */
+        let (time_str, dump_dir) = loop {
+            let time_str = Utc::now().format("%Y-%m-%d_%H_%M_%S_%3f").to_string();
+            let dump_dir = format!("{}_{}", self.init_params.dump_prefix, time_str);
+            if !Path::new(&dump_dir).exists() {
+                break (time_str, dump_dir);
+            }
+            thread::sleep(time::Duration::from_millis(1));
+        };
+        fs::create_dir(&dump_dir)?;
+
+        /* Static data, the PMU config and the runlog are the same for every dump */
+        for entry in fs::read_dir(&self.init_params.dir_name)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                fs::copy(entry.path(), Path::new(&dump_dir).join(entry.file_name()))?;
+            }
+        }
+        fs::copy(
+            &self.init_params.runlog,
+            Path::new(&dump_dir).join(APERF_RUNLOG),
+        )?;
+
+        let mut params = self.init_params.clone();
+        params.dir_name = dump_dir.clone();
+        params.time_str = time_str;
+        let meta_data_path = Path::new(&dump_dir).join(format!("meta_data.{}", APERF_FILE_FORMAT));
+        bincode::serialize_into(fs::File::create(meta_data_path)?, &params)?;
+
+        for (_name, datatype) in self.collectors.iter() {
+            if let Some(ring) = &datatype.ring {
+                ring.write_to_file(&Path::new(&dump_dir).join(&datatype.file_name))?;
+            }
+        }
+        if let Some(ring) = &self.aperf_stats_ring {
+            let stats_path =
+                Path::new(&dump_dir).join(format!("aperf_run_stats.{}", APERF_FILE_FORMAT));
+            ring.write_to_file(&stats_path)?;
+        }
+        create_archive(&dump_dir)
+    }
+
     pub fn create_data_archive(&mut self) -> Result<()> {
-        let dir_name = Path::new(&self.init_params.dir_name).file_name().unwrap();
-        let archive_path = format!("{}.tar.gz", self.init_params.dir_name);
-        let tar_gz = fs::File::create(&archive_path)?;
-        let enc = GzEncoder::new(tar_gz, Compression::default());
-        let mut tar = tar::Builder::new(enc);
-        tar.append_dir_all(dir_name, &self.init_params.dir_name)?;
-        info!(
-            "Data collected in {}/, archived in {}",
-            self.init_params.dir_name, archive_path
-        );
-        Ok(())
+        create_archive(&self.init_params.dir_name)
     }
 }
 
+/// Archive a run directory into <dir>.tar.gz next to it.
+pub fn create_archive(dir: &str) -> Result<()> {
+    let dir_name = Path::new(dir).file_name().unwrap();
+    let archive_path = format!("{}.tar.gz", dir);
+    let tar_gz = fs::File::create(&archive_path)?;
+    let enc = GzEncoder::new(tar_gz, Compression::default());
+    let mut tar = tar::Builder::new(enc);
+    tar.append_dir_all(dir_name, dir)?;
+    info!("Data collected in {}/, archived in {}", dir, archive_path);
+    Ok(())
+}
+
 impl Default for PerformanceData {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Whether a file in a run directory holds the data named `name`. The name is followed by the
+/// time of the run or the extension, so that "cpufreq" does not match "cpufreq_settings_<time>".
+fn is_data_file_of(file_name: &str, name: &str) -> bool {
+    let rest = match file_name.find(name) {
+        Some(pos) => &file_name[pos + name.len()..],
+        None => return false,
+    };
+    match rest.strip_prefix('_') {
+        Some(time) => time.starts_with(|c: char| c.is_ascii_digit()),
+        None => rest.is_empty() || rest.starts_with('.'),
+    }
+}
+
 pub fn get_file(dir: String, name: String) -> Result<fs::File> {
     for path in fs::read_dir(dir.clone())? {
         let mut file_name = path?.file_name().into_string().unwrap();
-        if file_name.contains(&name) {
+        if is_data_file_of(&file_name, &name) {
             let file_path = Path::new(&dir).join(file_name.clone());
             file_name = file_path.to_str().unwrap().to_string();
             return Ok(fs::OpenOptions::new()
@@ -409,7 +932,7 @@ pub fn get_file(dir: String, name: String) -> Result<fs::File> {
 pub fn get_file_name(dir: String, name: String) -> Result<String> {
     for path in fs::read_dir(dir.clone())? {
         let file_name = path?.file_name().into_string().unwrap();
-        if file_name.contains(&name) {
+        if is_data_file_of(&file_name, &name) {
             return Ok(file_name);
         }
     }
@@ -451,12 +974,34 @@ impl VisualizationData {
         let visualizers_len = self.visualizers.len();
         let mut error_count = 0;
 
-        for (_name, visualizer) in self.visualizers.iter_mut() {
+        /* Runs recorded before collectors could be selected have every collector */
+        let params = get_file(dir.clone(), "meta_data".to_string())
+            .ok()
+            .and_then(|file| bincode::deserialize_from::<_, InitParams>(file).ok());
+        if let Some(p) = &params {
+            overhead::set_run_sample_rates(&dir_name, p.sample_rates.clone());
+        }
+        let collected = params.map(|params| params.collectors);
+        let collector_names: Vec<String> = PERFORMANCE_DATA
+            .lock()
+            .unwrap()
+            .collectors
+            .keys()
+            .cloned()
+            .collect();
+
+        for (name, visualizer) in self.visualizers.iter_mut() {
             if let Err(e) =
                 visualizer.init_visualizer(dir.clone(), dir_name.clone(), tmp_dir, fin_dir)
             {
                 debug!("{:#?}", e);
-                visualizer.data_not_available(dir_name.clone())?;
+                match &collected {
+                    Some(c) if collector_names.contains(name) && !c.contains(name) => {
+                        info!("{} was not collected in {}", name, dir_name);
+                        visualizer.data_not_collected(dir_name.clone())?;
+                    }
+                    _ => visualizer.data_not_available(dir_name.clone())?,
+                }
                 error_count += 1;
             }
         }
@@ -498,10 +1043,10 @@ impl VisualizationData {
         Ok(file)
     }
 
-    pub fn unpack_data(&mut self, name: String) -> Result<()> {
+    pub fn unpack_data(&mut self, name: String, strict: bool) -> Result<()> {
         for (dvname, datavisualizer) in self.visualizers.iter_mut() {
             debug!("Unpacking data for: {}", dvname);
-            datavisualizer.process_raw_data(name.clone())?;
+            datavisualizer.process_raw_data(name.clone(), strict)?;
         }
         Ok(())
     }
@@ -544,6 +1089,20 @@ impl VisualizationData {
         visualizer.get_calls()
     }
 
+    /// What could not be read of the data of a visualizer for a run, if anything.
+    pub fn get_health(&self, run_name: &str, visualizer_name: &str) -> Option<String> {
+        self.visualizers
+            .get(visualizer_name)
+            .and_then(|v| v.get_health(run_name))
+            .map(|h| h.to_string())
+    }
+
+    pub fn is_collected(&self, run_name: &str, visualizer_name: &str) -> bool {
+        self.visualizers
+            .get(visualizer_name)
+            .is_none_or(|v| v.is_collected(run_name))
+    }
+
     pub fn get_analytics(&mut self) -> Result<String> {
         Ok(serde_json::to_string(&self.analytics_data)?)
     }
@@ -557,13 +1116,25 @@ pub struct InitParams {
     pub period: u64,
     pub profile: HashMap<String, String>,
     pub pmu_config: Option<PathBuf>,
-    pub interval: u64,
+    pub interval: time::Duration,
     pub run_name: String,
     pub collector_version: String,
     pub commit_sha_short: String,
     pub tmp_dir: PathBuf,
     pub runlog: PathBuf,
     pub perf_frequency: u32,
+    pub workload: Vec<String>,
+    pub collectors: Vec<String>,
+    pub flight_recorder: Option<time::Duration>,
+    pub dump_prefix: String,
+    pub rotate: Option<time::Duration>,
+    pub keep: Option<usize>,
+    pub max_size: Option<u64>,
+    pub tags: HashMap<String, String>,
+    pub max_overhead: Option<OverheadBudget>,
+    pub sample_rates: HashMap<String, Vec<SampleRate>>,
+    pub cgroup_root: PathBuf,
+    pub cgroup_depth: usize,
 }
 
 impl InitParams {
@@ -597,13 +1168,25 @@ impl InitParams {
             period: 0,
             profile: HashMap::new(),
             pmu_config: Option::None,
-            interval: 0,
+            interval: time::Duration::ZERO,
             run_name,
             collector_version,
             commit_sha_short,
             tmp_dir: PathBuf::from(APERF_TMP),
             runlog: PathBuf::new(),
             perf_frequency: 99,
+            workload: Vec::new(),
+            collectors: Vec::new(),
+            flight_recorder: None,
+            dump_prefix: String::new(),
+            rotate: None,
+            keep: None,
+            max_size: None,
+            tags: HashMap::new(),
+            max_overhead: None,
+            sample_rates: HashMap::new(),
+            cgroup_root: PathBuf::from(DEFAULT_CGROUP_ROOT),
+            cgroup_depth: DEFAULT_CGROUP_DEPTH,
         }
     }
 }
@@ -616,9 +1199,35 @@ impl Default for InitParams {
 
 #[cfg(test)]
 mod tests {
-    use super::{InitParams, PerformanceData, APERF_FILE_FORMAT};
+    use super::{is_data_file_of, InitParams, PerformanceData, APERF_FILE_FORMAT};
+    use crate::data::cgroup::CgroupRaw;
+    use crate::data::cpu_utilization::CpuUtilizationRaw;
+    use crate::data::{Data, DataType, TimeEnum};
+    use chrono::prelude::*;
     use std::fs;
     use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_data_file_of() {
+        assert!(is_data_file_of(
+            "cpufreq_2024-01-01_00_00_00.bin",
+            "cpufreq"
+        ));
+        assert!(!is_data_file_of(
+            "cpufreq_settings_2024-01-01_00_00_00.bin",
+            "cpufreq"
+        ));
+        assert!(is_data_file_of(
+            "cpufreq_settings_2024-01-01_00_00_00.bin",
+            "cpufreq_settings"
+        ));
+        assert!(is_data_file_of("meta_data.bin", "meta_data"));
+        assert!(is_data_file_of(
+            "aperf_markers_2024-01-01_00_00_00.bin",
+            "aperf_markers"
+        ));
+    }
 
     #[test]
     fn test_performance_data_new() {
@@ -649,4 +1258,70 @@ mod tests {
         assert!(Path::new(&full_path).exists());
         fs::remove_dir_all(pd.init_params.dir_name).unwrap();
     }
+
+    #[test]
+    fn test_unavailable_collector_not_recorded() {
+        let cgroup_root = TempDir::with_prefix("aperf_no_cgroup").unwrap();
+        let mut params = InitParams::new("".to_string());
+        params.dir_name = format!("./performance_data_unavailable_{}", params.time_str);
+        params.cgroup_root = cgroup_root.path().to_path_buf();
+
+        let mut pd = PerformanceData::new();
+        pd.set_params(params);
+        pd.add_datatype(
+            "cpu_utilization".to_string(),
+            DataType::new(
+                Data::CpuUtilizationRaw(CpuUtilizationRaw::new()),
+                "cpu_utilization".to_string(),
+                false,
+            ),
+        );
+        /* No cgroup.controllers, so not a cgroup v2 hierarchy */
+        pd.add_datatype(
+            "cgroup".to_string(),
+            DataType::new(
+                Data::CgroupRaw(CgroupRaw {
+                    time: TimeEnum::DateTime(Utc::now()),
+                    cgroups: Vec::new(),
+                }),
+                "cgroup".to_string(),
+                false,
+            ),
+        );
+        pd.init_collectors().unwrap();
+        assert_eq!(pd.init_params.collectors, vec!["cgroup", "cpu_utilization"]);
+        pd.prepare_data_collectors().unwrap();
+
+        let meta_data = fs::File::open(format!(
+            "{}/meta_data.{}",
+            pd.init_params.dir_name, APERF_FILE_FORMAT
+        ))
+        .unwrap();
+        let recorded: InitParams = bincode::deserialize_from(meta_data).unwrap();
+        assert_eq!(recorded.collectors, vec!["cpu_utilization"]);
+        fs::remove_dir_all(pd.init_params.dir_name).unwrap();
+    }
+
+    #[test]
+    fn test_select_collectors() {
+        let mut pd = PerformanceData::new();
+        for name in ["cpu_utilization", "vmstat", "processes"] {
+            pd.add_datatype(
+                name.to_string(),
+                DataType::new(
+                    Data::CpuUtilizationRaw(CpuUtilizationRaw::new()),
+                    name.to_string(),
+                    false,
+                ),
+            );
+        }
+        assert!(pd.select_collectors(&["unknown".to_string()], &[]).is_err());
+        pd.select_collectors(&[], &["processes".to_string()])
+            .unwrap();
+        assert!(!pd.collectors.contains_key("processes"));
+        assert_eq!(pd.collectors.len(), 2);
+        pd.select_collectors(&["vmstat".to_string()], &[]).unwrap();
+        assert!(pd.collectors.contains_key("vmstat"));
+        assert_eq!(pd.collectors.len(), 1);
+    }
 }
@@ -0,0 +1,263 @@
+use crate::data::TimeEnum;
+use crate::record::parse_duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of consecutive samples over its share after which a collector is sampled less often.
+const OVER_BUDGET_SAMPLES: usize = 5;
+
+/// A collector is sampled at least once every this many intervals.
+pub const MAX_SAMPLE_EVERY: u64 = 60;
+
+lazy_static! {
+    /// Sampling rate changes of each run in the report, by collector.
+    static ref RUN_SAMPLE_RATES: Mutex<HashMap<String, HashMap<String, Vec<SampleRate>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// How much time aperf may spend collecting, given with --max-overhead.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum OverheadBudget {
+    /// Percentage of one CPU, e.g. "2%".
+    Percent(f64),
+    /// Time per interval, e.g. "20ms".
+    PerInterval(Duration),
+}
+
+impl OverheadBudget {
+    /// The time that can be spent collecting in each interval.
+    pub fn per_interval(&self, interval: Duration) -> Duration {
+        match self {
+            OverheadBudget::Percent(p) => interval.mul_f64(p / 100.0),
+            OverheadBudget::PerInterval(d) => *d,
+        }
+    }
+}
+
+impl FromStr for OverheadBudget {
+    type Err = String;
+
+    fn from_str(arg: &str) -> Result<Self, String> {
+        let arg = arg.trim();
+        if let Some(p) = arg.strip_suffix('%') {
+            return match p.trim().parse::<f64>() {
+                Ok(p) if p > 0.0 && p <= 100.0 => Ok(OverheadBudget::Percent(p)),
+                _ => Err(format!(
+                    "Invalid overhead '{}', the percentage must be above 0 and at most 100",
+                    arg
+                )),
+            };
+        }
+        /* A bare number is ambiguous between a percentage and a time */
+        if !arg.ends_with('s') {
+            return Err(format!(
+                "Invalid overhead '{}', expected a percentage of one CPU (e.g. 2%) or a time per interval (e.g. 20ms)",
+                arg
+            ));
+        }
+        match parse_duration(arg)? {
+            d if d.is_zero() => Err(format!("Invalid overhead '{}', must not be 0", arg)),
+            d => Ok(OverheadBudget::PerInterval(d)),
+        }
+    }
+}
+
+impl TryFrom<String> for OverheadBudget {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for OverheadBudget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OverheadBudget::Percent(p) => write!(f, "{}%", p),
+            OverheadBudget::PerInterval(d) => write!(f, "{}ms", d.as_millis()),
+        }
+    }
+}
+
+impl From<OverheadBudget> for String {
+    fn from(budget: OverheadBudget) -> String {
+        budget.to_string()
+    }
+}
+
+/// Parse a --max-overhead given as a percentage of one CPU or as a time per interval.
+pub fn parse_overhead(arg: &str) -> Result<OverheadBudget, String> {
+    arg.parse()
+}
+
+/// From `time` on, a collector was sampled once every `every` intervals.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SampleRate {
+    pub time: TimeEnum,
+    pub every: u64,
+}
+
+/// Lowers the sampling rate of a collector that keeps taking more than its share of the
+/// overhead budget.
+#[derive(Clone, Debug)]
+pub struct Throttle {
+    /// Microseconds per interval.
+    share: u64,
+    every: u64,
+    skip: u64,
+    over: Vec<u64>,
+}
+
+impl Throttle {
+    pub fn new(share: Duration) -> Self {
+        Throttle {
+            share: (share.as_micros() as u64).max(1),
+            every: 1,
+            skip: 0,
+            over: Vec::new(),
+        }
+    }
+
+    pub fn every(&self) -> u64 {
+        self.every
+    }
+
+    /// Whether the collector is to be sampled on this tick.
+    pub fn tick(&mut self) -> bool {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return false;
+        }
+        self.skip = self.every - 1;
+        true
+    }
+
+    /// Account the time taken by a sample, in microseconds. Returns the new number of intervals
+    /// between samples if the collector is to be sampled less often.
+    pub fn sampled(&mut self, cost: u64) -> Option<u64> {
+        if cost <= self.share * self.every {
+            self.over.clear();
+            return None;
+        }
+        self.over.push(cost);
+        if self.over.len() < OVER_BUDGET_SAMPLES {
+            return None;
+        }
+        let average = self.over.iter().sum::<u64>() / self.over.len() as u64;
+        self.over.clear();
+        let every = average.div_ceil(self.share).min(MAX_SAMPLE_EVERY);
+        if every <= self.every {
+            return None;
+        }
+        self.every = every;
+        self.skip = every - 1;
+        Some(every)
+    }
+}
+
+/// Keep the sampling rate changes recorded in a run for the report.
+pub fn set_run_sample_rates(run: &str, rates: HashMap<String, Vec<SampleRate>>) {
+    RUN_SAMPLE_RATES
+        .lock()
+        .unwrap()
+        .insert(run.to_string(), rates);
+}
+
+/// Number of intervals covered by the sample of a collector taken at `time`. Counters are
+/// divided by it so that throttled samples are plotted per interval like the others.
+pub fn sample_every(run: &str, collector: &str, time: TimeEnum) -> u64 {
+    let rates = RUN_SAMPLE_RATES.lock().unwrap();
+    rates
+        .get(run)
+        .and_then(|r| r.get(collector))
+        .and_then(|changes| changes.iter().rev().find(|c| c.time < time))
+        .map_or(1, |c| c.every)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_overhead, sample_every, set_run_sample_rates, OverheadBudget, SampleRate, Throttle,
+        MAX_SAMPLE_EVERY,
+    };
+    use crate::data::TimeEnum;
+    use chrono::prelude::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_overhead() {
+        let interval = Duration::from_millis(500);
+        let budget = parse_overhead("2%").unwrap();
+        assert_eq!(budget, OverheadBudget::Percent(2.0));
+        assert_eq!(budget.per_interval(interval), Duration::from_millis(10));
+        let budget = parse_overhead("20ms").unwrap();
+        assert_eq!(budget.per_interval(interval), Duration::from_millis(20));
+        assert_eq!(budget.to_string(), "20ms");
+        assert!(parse_overhead("0%").is_err());
+        assert!(parse_overhead("150%").is_err());
+        assert!(parse_overhead("20").is_err());
+        assert!(parse_overhead("0ms").is_err());
+    }
+
+    #[test]
+    fn test_throttle() {
+        let mut throttle = Throttle::new(Duration::from_millis(1));
+        for _ in 0..4 {
+            assert!(throttle.tick());
+            assert_eq!(throttle.sampled(4500), None);
+        }
+        /* A sample within the share starts the count again */
+        assert!(throttle.tick());
+        assert_eq!(throttle.sampled(900), None);
+        for _ in 0..4 {
+            assert!(throttle.tick());
+            assert_eq!(throttle.sampled(4500), None);
+        }
+        assert!(throttle.tick());
+        assert_eq!(throttle.sampled(4500), Some(5));
+        let sampled: Vec<bool> = (0..10).map(|_| throttle.tick()).collect();
+        assert_eq!(sampled.iter().filter(|s| **s).count(), 2);
+        assert!(!sampled[0] && sampled[4] && sampled[9]);
+
+        /* Within the share at the lower rate */
+        for _ in 0..10 {
+            assert_eq!(throttle.sampled(4500), None);
+        }
+        for _ in 0..5 {
+            throttle.sampled(1_000_000);
+        }
+        assert_eq!(throttle.every(), MAX_SAMPLE_EVERY);
+    }
+
+    #[test]
+    fn test_sample_every() {
+        let start = Utc::now();
+        let at = |ms| TimeEnum::DateTime(start + chrono::Duration::milliseconds(ms));
+        let mut rates = HashMap::new();
+        rates.insert(
+            "processes".to_string(),
+            vec![
+                SampleRate {
+                    time: at(5000),
+                    every: 5,
+                },
+                SampleRate {
+                    time: at(30000),
+                    every: 10,
+                },
+            ],
+        );
+        set_run_sample_rates("throttled_run", rates);
+        assert_eq!(sample_every("throttled_run", "processes", at(4000)), 1);
+        assert_eq!(sample_every("throttled_run", "processes", at(10000)), 5);
+        assert_eq!(sample_every("throttled_run", "processes", at(40000)), 10);
+        assert_eq!(sample_every("throttled_run", "vmstat", at(40000)), 1);
+        assert_eq!(sample_every("other_run", "processes", at(40000)), 1);
+    }
+}
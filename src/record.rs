@@ -1,9 +1,14 @@
+use crate::config::{parse_tag, RecordConfig};
+use crate::data::cgroup::{DEFAULT_CGROUP_DEPTH, DEFAULT_CGROUP_ROOT};
+use crate::overhead::{parse_overhead, OverheadBudget};
 use crate::{data, InitParams, PERFORMANCE_DATA};
 use anyhow::anyhow;
 use anyhow::Result;
 use clap::Args;
 use log::{debug, error, info};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Args, Debug)]
 pub struct Record {
@@ -11,88 +16,248 @@ pub struct Record {
     #[clap(short, long, value_parser)]
     pub run_name: Option<String>,
 
-    /// Interval (in seconds) at which performance data is to be collected.
-    #[clap(short, long, value_parser, default_value_t = 1)]
-    pub interval: u64,
+    /// Interval at which performance data is to be collected. Accepts a unit suffix
+    /// of 'ms' or 's' (e.g. 250ms). A bare number is taken as seconds. Defaults to 1.
+    #[clap(short, long, value_parser = parse_duration)]
+    pub interval: Option<Duration>,
 
-    /// Time (in seconds) for which the performance data is to be collected.
-    #[clap(short, long, value_parser, default_value_t = 10)]
-    pub period: u64,
+    /// Time (in seconds) for which the performance data is to be collected. Defaults to 10, or
+    /// to the lifetime of the workload if one is given, in which case this is an upper limit.
+    #[clap(short, long, value_parser)]
+    pub period: Option<u64>,
 
     /// Gather profiling data using 'perf' binary.
     #[clap(long, value_parser)]
     pub profile: bool,
 
-    /// Frequency for perf profiling (Hz).
-    #[clap(short = 'F', long, value_parser, default_value_t = 99)]
-    pub perf_frequency: u32,
+    /// Frequency for perf profiling (Hz). Defaults to 99.
+    #[clap(short = 'F', long, value_parser)]
+    pub perf_frequency: Option<u32>,
 
     /// Profile JVMs using async-profiler. Specify args using comma separated values. Profiles all JVMs if no args are provided.
     #[clap(long, value_parser, default_missing_value = Some("jps"), value_names = &["PID/Name>,<PID/Name>,...,<PID/Name"], num_args = 0..=1)]
     pub profile_java: Option<String>,
 
+    /// Sample the CPU time of the threads of processes, given by PID or name as comma separated
+    /// values.
+    #[clap(long, value_parser, value_delimiter = ',', value_name = "PID/NAME")]
+    pub threads: Vec<String>,
+
+    /// Sample the slab caches from /proc/slabinfo, or from /sys/kernel/slab if not run as root.
+    #[clap(long, value_parser)]
+    pub slabinfo: bool,
+
     /// Custom PMU config file to use.
     #[clap(long, value_parser)]
     pub pmu_config: Option<String>,
+
+    /// Collectors to run, as a comma separated list. Runs all collectors if not given.
+    #[clap(long, value_parser, value_delimiter = ',', conflicts_with = "exclude")]
+    pub include: Vec<String>,
+
+    /// Collectors to skip, as a comma separated list.
+    #[clap(long, value_parser, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// Flight recorder mode. Keep only the most recent window of data (e.g. 10m) in memory and
+    /// write it to a new timestamped archive on SIGUSR1 and on exit. Runs until stopped if no
+    /// period is given.
+    #[clap(long, value_parser = parse_duration, value_name = "WINDOW")]
+    pub flight_recorder: Option<Duration>,
+
+    /// Most time to spend collecting, as a percentage of one CPU (e.g. 2%) or as a time per
+    /// interval (e.g. 20ms). Collectors that keep going over their share are collected less often.
+    #[clap(long, value_parser = parse_overhead)]
+    pub max_overhead: Option<OverheadBudget>,
+
+    /// Root of the cgroup v2 hierarchy to sample. Defaults to /sys/fs/cgroup.
+    #[clap(long, value_parser)]
+    pub cgroup_root: Option<String>,
+
+    /// Levels of cgroups below the root to sample. Defaults to 2.
+    #[clap(long, value_parser)]
+    pub cgroup_depth: Option<usize>,
+
+    /// YAML or TOML file with the settings for the run. Flags given here override it.
+    #[clap(long, value_parser)]
+    pub config: Option<String>,
+
+    /// Tag the run, as KEY=VALUE. Can be given more than once.
+    #[clap(long, value_parser = parse_tag, value_name = "KEY=VALUE")]
+    pub tag: Vec<(String, String)>,
+
+    /// Workload to run, given after '--'. Data is collected until it exits.
+    #[clap(last = true, value_parser, value_names = &["COMMAND", "ARGS"])]
+    pub workload: Vec<String>,
 }
 
-fn prepare_data_collectors() -> Result<()> {
+/// Default collection period (in seconds) when no workload is given.
+pub const DEFAULT_PERIOD: u64 = 10;
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const DEFAULT_PERF_FREQUENCY: u32 = 99;
+
+/// Collection period (in seconds) used when a workload or the flight recorder is used
+/// without a period. This is effectively unbounded.
+pub const UNBOUNDED_PERIOD: u64 = i32::MAX as u64;
+
+/// Parse a duration such as "250ms", "5s", "10m" or "1h". A bare number is taken as seconds.
+pub fn parse_duration(arg: &str) -> Result<Duration, String> {
+    let arg = arg.trim();
+    let split = arg
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(arg.len());
+    let (value, unit) = arg.split_at(split);
+    let value: f64 = value
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}'", arg))?;
+    let millis_per_unit = match unit.trim() {
+        "ms" => 1.0,
+        "" | "s" => 1000.0,
+        "m" => 60_000.0,
+        "h" => 3_600_000.0,
+        u => return Err(format!("Invalid unit '{}' in duration '{}'", u, arg)),
+    };
+    let millis = value * millis_per_unit;
+    if (millis - millis.round()).abs() > 1e-6 {
+        return Err(format!(
+            "Duration '{}' must be a whole number of milliseconds",
+            arg
+        ));
+    }
+    Ok(Duration::from_millis(millis.round() as u64))
+}
+
+pub(crate) fn prepare_data_collectors() -> Result<()> {
     info!("Preparing data collectors...");
     PERFORMANCE_DATA.lock().unwrap().prepare_data_collectors()?;
     Ok(())
 }
 
-fn start_collection_serial() -> Result<()> {
+pub(crate) fn start_collection_serial() -> Result<()> {
     info!("Collecting data...");
     PERFORMANCE_DATA.lock().unwrap().collect_data_serial()?;
     Ok(())
 }
 
-fn collect_static_data() -> Result<()> {
+pub(crate) fn collect_static_data() -> Result<()> {
     debug!("Collecting static data...");
     PERFORMANCE_DATA.lock().unwrap().collect_static_data()?;
     Ok(())
 }
 
 pub fn record(record: &Record, tmp_dir: &Path, runlog: &Path) -> Result<()> {
-    let mut run_name = String::new();
-    if record.period == 0 {
+    let mut config = match &record.config {
+        Some(c) => RecordConfig::from_file(Path::new(c))?,
+        None => RecordConfig::default(),
+    };
+    config.merge_record(record);
+    config.validate()?;
+
+    let interval = config.interval.unwrap_or(DEFAULT_INTERVAL);
+    let period = match config.period {
+        Some(p) => p,
+        None if !record.workload.is_empty() || record.flight_recorder.is_some() => UNBOUNDED_PERIOD,
+        None => DEFAULT_PERIOD,
+    };
+    if period == 0 {
         error!("Collection period cannot be 0.");
         return Err(anyhow!("Cannot start recording with the given parameters."));
     }
-    if record.interval == 0 {
+    if interval.is_zero() {
         error!("Collection interval cannot be 0.");
         return Err(anyhow!("Cannot start recording with the given parameters."));
     }
     // Check if interval > period , if so give error user and exit.
-    if record.interval >= record.period {
-        error!("The overall recording period of {period} seconds needs to be longer than the interval of {interval} seconds.\
-                Please increase the overall recording period or decrease the interval.", interval = record.interval, period =record.period);
+    if interval >= Duration::from_secs(period) {
+        error!("The overall recording period of {period} seconds needs to be longer than the interval of {interval:?}.\
+                Please increase the overall recording period or decrease the interval.", interval = interval, period = period);
         return Err(anyhow!("Cannot start recording with the given parameters."));
     }
-    match &record.run_name {
-        Some(r) => run_name = r.clone(),
-        None => {}
+    if let Some(window) = record.flight_recorder {
+        if interval >= window {
+            error!("The flight recorder window of {window:?} needs to be longer than the interval of {interval:?}.", window = window, interval = interval);
+            return Err(anyhow!("Cannot start recording with the given parameters."));
+        }
+    }
+    let mut params = InitParams::new(config.run_name.clone().unwrap_or_default());
+    if let Some(output_dir) = &config.output_dir {
+        fs::create_dir_all(output_dir)?;
+        params.dir_name = output_dir
+            .join(&params.dir_name)
+            .components()
+            .as_path()
+            .to_str()
+            .unwrap()
+            .to_string();
     }
-    let mut params = InitParams::new(run_name);
-    params.period = record.period;
-    params.interval = record.interval;
+
+    /* The settings saved with the run are the ones actually used */
+    config.run_name = Some(params.dir_name.clone());
+    config.output_dir = None;
+    config.interval = Some(interval);
+    /* Without a period, a workload or the flight recorder runs until stopped */
+    if period != UNBOUNDED_PERIOD {
+        config.period = Some(period);
+    }
+    let cgroup_root = config
+        .cgroup
+        .root
+        .get_or_insert(PathBuf::from(DEFAULT_CGROUP_ROOT));
+    params.cgroup_root = cgroup_root.clone();
+    params.cgroup_depth = *config.cgroup.depth.get_or_insert(DEFAULT_CGROUP_DEPTH);
+    if config.profile.perf {
+        config.profile.frequency = Some(config.profile.frequency.unwrap_or(DEFAULT_PERF_FREQUENCY));
+    }
+
+    if let Some(window) = record.flight_recorder {
+        /*
+         * Static data is kept in a working directory in the tmp dir. Each dump is written
+         * to a new directory named after the run with the dump time appended.
+         */
+        params.flight_recorder = Some(window);
+        params.dump_prefix = params.dir_name.clone();
+        let dir_name = Path::new(&params.dir_name).file_name().unwrap();
+        params.dir_name = tmp_dir.join(dir_name).to_str().unwrap().to_string();
+    }
+    params.period = period;
+    params.interval = interval;
+    params.workload = record.workload.clone();
     params.tmp_dir = tmp_dir.to_path_buf();
     params.runlog = runlog.to_path_buf();
-    if let Some(p) = &record.pmu_config {
+    params.tags = config.tags.clone().into_iter().collect();
+    params.max_overhead = config.max_overhead;
+    if let Some(p) = &config.pmu_config {
         params.pmu_config = Some(PathBuf::from(p));
     }
 
-    match &record.profile_java {
+    match &config.profile.java {
         Some(j) => {
+            let targets = match j.is_empty() {
+                true => "jps".to_string(),
+                false => j.join(","),
+            };
             params.profile.insert(
                 String::from(data::java_profile::JAVA_PROFILE_FILE_NAME),
-                j.clone(),
+                targets,
             );
         }
         None => {}
     }
-    if record.profile {
+    if !config.threads.is_empty() {
+        params.profile.insert(
+            String::from(data::threads::THREADS_FILE_NAME),
+            config.threads.join(","),
+        );
+    }
+    if config.slabinfo {
+        params.profile.insert(
+            String::from(data::slabinfo::SLABINFO_FILE_NAME),
+            String::new(),
+        );
+    }
+    if config.profile.perf {
         params.profile.insert(
             String::from(data::perf_profile::PERF_PROFILE_FILE_NAME),
             String::new(),
@@ -101,11 +266,17 @@ pub fn record(record: &Record, tmp_dir: &Path, runlog: &Path) -> Result<()> {
             String::from(data::flamegraphs::FLAMEGRAPHS_FILE_NAME),
             String::new(),
         );
-        params.perf_frequency = record.perf_frequency;
+        params.perf_frequency = config.profile.frequency.unwrap();
     }
+    let dir_name = params.dir_name.clone();
 
     PERFORMANCE_DATA.lock().unwrap().set_params(params);
+    PERFORMANCE_DATA
+        .lock()
+        .unwrap()
+        .select_collectors(&config.collectors.include, &config.collectors.exclude)?;
     PERFORMANCE_DATA.lock().unwrap().init_collectors()?;
+    config.write_to_file(Path::new(&dir_name))?;
     info!("Starting Data collection...");
     prepare_data_collectors()?;
     collect_static_data()?;
@@ -115,3 +286,22 @@ pub fn record(record: &Record, tmp_dir: &Path, runlog: &Path) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_duration;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("2").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("1s").unwrap(), Duration::from_secs(1));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("0.5").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+        assert!(parse_duration("1.5ms").is_err());
+        assert!(parse_duration("ms").is_err());
+        assert!(parse_duration("5x").is_err());
+    }
+}
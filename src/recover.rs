@@ -0,0 +1,135 @@
+use crate::data::markers::{Marker, MarkersRaw, MARKERS_FILE_NAME};
+use crate::data::records::{self, RecordReader, RECORD_MAGIC};
+use crate::data::{Data, TimeEnum};
+use crate::{
+    create_archive, get_file, get_file_name, InitParams, PDError, APERF_FILE_FORMAT, APERF_RUNLOG,
+};
+use anyhow::Result;
+use chrono::prelude::*;
+use clap::Args;
+use log::{info, warn};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Label of the marker added to a recovered run.
+pub static RECOVERED_MARKER: &str = "Recording ended abnormally";
+
+#[derive(Args, Debug)]
+pub struct Recover {
+    /// Run directory of a recording that did not finish, e.g. because aperf was killed.
+    #[clap(value_parser)]
+    pub dir: String,
+}
+
+/// Cut the data files of a run back to their last intact record.
+/// Returns a note for each file that was cut, and the time the run was last written to.
+fn truncate_torn_records(dir: &Path) -> Result<(Vec<String>, Option<DateTime<Utc>>)> {
+    let mut notes = Vec::new();
+    let mut last_write: Option<DateTime<Utc>> = None;
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<std::io::Result<_>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !entry.file_type()?.is_file() || !name.ends_with(APERF_FILE_FORMAT) {
+            continue;
+        }
+        let modified: DateTime<Utc> = entry.metadata()?.modified()?.into();
+        last_write = last_write.max(Some(modified));
+
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(entry.path())?;
+        let mut reader = RecordReader::new(&file)?;
+        if !reader.is_framed() {
+            continue;
+        }
+        let (count, err) = reader.skip_valid();
+        if let Some(e) = err {
+            let len = file.metadata()?.len();
+            file.set_len(reader.offset())?;
+            file.sync_all()?;
+            let note = format!(
+                "{}: kept {} records, removed {} bytes ({})",
+                name,
+                count,
+                len - reader.offset(),
+                e
+            );
+            warn!("{}", note);
+            notes.push(note);
+        }
+    }
+    Ok((notes, last_write))
+}
+
+/// Add a marker at `time` to the markers of the run, creating the markers file if needed.
+fn add_recovered_marker(dir: &Path, params: &InitParams, time: DateTime<Utc>) -> Result<()> {
+    let file_name = get_file_name(dir.display().to_string(), MARKERS_FILE_NAME.to_string())
+        .unwrap_or(format!(
+            "{}_{}.{}",
+            MARKERS_FILE_NAME, params.time_str, APERF_FILE_FORMAT
+        ));
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .create(true)
+        .append(true)
+        .open(dir.join(file_name))?;
+    let time = TimeEnum::DateTime(time);
+    let data = Data::MarkersRaw(MarkersRaw {
+        time,
+        markers: vec![Marker {
+            time,
+            label: RECOVERED_MARKER.to_string(),
+        }],
+    });
+    let framed = match file.metadata()?.len() {
+        0 => {
+            file.write_all(RECORD_MAGIC)?;
+            true
+        }
+        _ => RecordReader::new(&file)?.is_framed(),
+    };
+    if framed {
+        records::write_record(&file, &data)?;
+    } else {
+        bincode::serialize_into(&file, &data)?;
+    }
+    file.sync_all()?;
+    Ok(())
+}
+
+pub fn recover(recover: &Recover) -> Result<()> {
+    let dir_name = recover.dir.trim_end_matches('/');
+    let dir = Path::new(dir_name);
+    let params: InitParams = get_file(dir_name.to_string(), "meta_data".to_string())
+        .ok()
+        .and_then(|file| bincode::deserialize_from(file).ok())
+        .ok_or(PDError::RecoverNotARun(dir_name.to_string()))?;
+
+    info!("Recovering {}...", dir_name);
+    let (notes, last_write) = truncate_torn_records(dir)?;
+    let end_time = last_write.unwrap_or_else(Utc::now);
+    add_recovered_marker(dir, &params, end_time)?;
+
+    /* The runlog of the recording was in its tmp dir, note what was done instead */
+    let mut runlog = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(APERF_RUNLOG))?;
+    writeln!(
+        runlog,
+        "{}. Last written at {}, recovered with aperf recover at {}.",
+        RECOVERED_MARKER,
+        end_time.format("%Y-%m-%dT%H:%M:%SZ"),
+        Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+    )?;
+    for note in &notes {
+        writeln!(runlog, "{}", note)?;
+    }
+    if notes.is_empty() {
+        info!("No torn records found.");
+    }
+    create_archive(dir_name)
+}
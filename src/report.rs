@@ -1,4 +1,4 @@
-use crate::{PDError, VISUALIZATION_DATA};
+use crate::{PDError, VisualizationData, VISUALIZATION_DATA};
 use anyhow::Result;
 use clap::Args;
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
@@ -19,6 +19,11 @@ pub struct Report {
     /// Report name.
     #[clap(short, long, value_parser)]
     pub name: Option<String>,
+
+    /// Fail on the first collector whose data cannot be read, instead of reporting the data read
+    /// before the damage.
+    #[clap(long, value_parser)]
+    pub strict: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -39,6 +44,8 @@ impl Api {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Run {
     name: String,
+    collected: bool,
+    health: Option<String>,
     keys: Vec<String>,
     key_values: HashMap<String, String>,
 }
@@ -47,6 +54,8 @@ impl Run {
     fn new(name: String) -> Self {
         Run {
             name,
+            collected: true,
+            health: None,
             keys: Vec::new(),
             key_values: HashMap::new(),
         }
@@ -130,6 +139,43 @@ pub fn get_dir(dir: PathBuf, tmp_dir: &PathBuf) -> Result<PathBuf> {
     Err(PDError::RecordNotArchiveOrDirectory.into())
 }
 
+fn get_run_data(
+    visualizer: &mut VisualizationData,
+    api_name: &str,
+    calls: &[String],
+    run: &mut Run,
+) -> Result<()> {
+    let run_name = run.name.clone();
+    let mut temp_keys: Vec<String> = Vec::<String>::new();
+    let mut keys = false;
+    for call in calls {
+        let query = format!("run={}&get={}", run_name, call);
+        let mut data;
+        if call == "keys" {
+            data = visualizer.get_data(&run_name, api_name, query)?;
+            if data != "No data collected" {
+                temp_keys = serde_json::from_str(&data)?;
+            }
+            run.keys = temp_keys.clone();
+            keys = true;
+        }
+        if call == "values" {
+            if keys {
+                for key in &temp_keys {
+                    let query = format!("run={}&get=values&key={}", run_name, key);
+                    data = visualizer.get_data(&run_name, api_name, query.clone())?;
+                    run.key_values.insert(key.clone(), data.clone());
+                }
+            } else {
+                let query = format!("run={}&get=values", run_name);
+                data = visualizer.get_data(&run_name, api_name, query)?;
+                run.key_values.insert(call.clone(), data.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn report(report: &Report, tmp_dir: &PathBuf) -> Result<()> {
     let dirs: Vec<String> = report.run.clone();
     let mut pathbuf_dirs: Vec<PathBuf> = Vec::new();
@@ -238,7 +284,7 @@ pub fn report(report: &Report, tmp_dir: &PathBuf) -> Result<()> {
     /* Init visualizers */
     for dir in dir_paths {
         let name = visualizer.init_visualizers(dir.to_owned(), tmp_dir, &report_name)?;
-        visualizer.unpack_data(name)?;
+        visualizer.unpack_data(name, report.strict)?;
     }
 
     /* Generate visualizer JS files */
@@ -263,33 +309,21 @@ pub fn report(report: &Report, tmp_dir: &PathBuf) -> Result<()> {
         let calls = visualizer.get_calls(api_name.clone())?;
         let mut api = Api::new(name.clone());
         for run_name in &run_names {
-            let mut temp_keys: Vec<String> = Vec::<String>::new();
             let mut run = Run::new(run_name.clone());
-            let mut keys = false;
-            for call in &calls {
-                let query = format!("run={}&get={}", run_name, call);
-                let mut data;
-                if call == "keys" {
-                    data = visualizer.get_data(run_name, &api_name, query)?;
-                    if data != "No data collected" {
-                        temp_keys = serde_json::from_str(&data)?;
-                    }
-                    run.keys = temp_keys.clone();
-                    keys = true;
-                }
-                if call == "values" {
-                    if keys {
-                        for key in &temp_keys {
-                            let query = format!("run={}&get=values&key={}", run_name, key);
-                            data = visualizer.get_data(run_name, &api_name, query.clone())?;
-                            run.key_values.insert(key.clone(), data.clone());
-                        }
-                    } else {
-                        let query = format!("run={}&get=values", run_name);
-                        data = visualizer.get_data(run_name, &api_name, query)?;
-                        run.key_values.insert(call.clone(), data.clone());
-                    }
+            if !visualizer.is_collected(run_name, &name) {
+                run.collected = false;
+                api.runs.push(run);
+                continue;
+            }
+            run.health = visualizer.get_health(run_name, &name);
+            if let Err(e) = get_run_data(&mut visualizer, &api_name, &calls, &mut run) {
+                if report.strict {
+                    return Err(e);
                 }
+                error!("Could not report {} data of {}: {}", api_name, run_name, e);
+                run.keys.clear();
+                run.key_values.clear();
+                run.health = Some(format!("could not be reported: {}", e));
             }
             api.runs.push(run);
         }
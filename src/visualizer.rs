@@ -1,9 +1,11 @@
+use crate::data::records::RecordReader;
 use crate::utils::DataMetrics;
 use crate::{data::Data, data::ProcessedData, get_file, PDError};
 use anyhow::Result;
-use log::debug;
+use log::{debug, warn};
 use rustix::fd::AsRawFd;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::{collections::HashMap, fs::File};
@@ -29,6 +31,24 @@ impl ReportParams {
     }
 }
 
+/// How much of the data of a run could be used, when some of it could not.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataHealth {
+    pub decoded: usize,
+    pub total: usize,
+    pub problem: String,
+}
+
+impl fmt::Display for DataHealth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "decoded {}/{} samples; {}",
+            self.decoded, self.total, self.problem
+        )
+    }
+}
+
 pub struct DataVisualizer {
     pub data: ProcessedData,
     pub file_handle: Option<File>,
@@ -39,6 +59,8 @@ pub struct DataVisualizer {
     pub api_name: String,
     pub has_custom_raw_data_parser: bool,
     pub data_available: HashMap<String, bool>,
+    pub data_collected: HashMap<String, bool>,
+    pub health: HashMap<String, DataHealth>,
     pub report_params: ReportParams,
 }
 
@@ -60,6 +82,8 @@ impl DataVisualizer {
             api_name,
             has_custom_raw_data_parser: false,
             data_available: HashMap::new(),
+            data_collected: HashMap::new(),
+            health: HashMap::new(),
             report_params: ReportParams::new(),
         }
     }
@@ -93,40 +117,117 @@ impl DataVisualizer {
         Ok(())
     }
 
-    pub fn process_raw_data(&mut self, name: String) -> Result<()> {
+    /// The collector was deliberately skipped for this run with --include/--exclude.
+    pub fn data_not_collected(&mut self, name: String) -> Result<()> {
+        self.data_collected.insert(name.clone(), false);
+        self.data_not_available(name)
+    }
+
+    pub fn is_collected(&self, name: &str) -> bool {
+        *self.data_collected.get(name).unwrap_or(&true)
+    }
+
+    /// The problems found in the data of a run, if any.
+    pub fn get_health(&self, name: &str) -> Option<&DataHealth> {
+        self.health.get(name)
+    }
+
+    /// Keep what was read before `err` in the data of a run, or fail with it if `strict`.
+    fn salvage(
+        &mut self,
+        name: &str,
+        health: DataHealth,
+        err: anyhow::Error,
+        strict: bool,
+    ) -> Result<()> {
+        if strict {
+            return Err(err.context(format!("Could not read {} data of {}", self.api_name, name)));
+        }
+        warn!("{} data of {}: {} ({})", self.api_name, name, health, err);
+        self.health.insert(name.to_string(), health);
+        Ok(())
+    }
+
+    /// Process the raw data of a run. Unless `strict`, the records read before a damaged one are
+    /// kept and the damage is noted in the health of the run.
+    pub fn process_raw_data(&mut self, name: String, strict: bool) -> Result<()> {
         if !self.data_available.get(&name).unwrap() {
             debug!("Raw data unavailable for: {}", self.api_name);
             return Ok(());
         }
         debug!("Processing raw data for: {}", self.api_name);
+        let file = self.file_handle.as_ref().unwrap();
+        let len = file.metadata()?.len();
+        let mut reader = RecordReader::new(file)?;
         if self.has_custom_raw_data_parser {
-            self.run_values.insert(
-                name.clone(),
-                self.data
-                    .custom_raw_data_parser(self.report_params.clone())?,
-            );
+            match self.data.custom_raw_data_parser(self.report_params.clone()) {
+                Ok(values) => {
+                    self.run_values.insert(name.clone(), values);
+                }
+                Err(e) => {
+                    let health = DataHealth {
+                        decoded: 0,
+                        total: reader.estimate_total(0, len),
+                        problem: "could not be read".to_string(),
+                    };
+                    return self.salvage(&name, health, e, strict);
+                }
+            }
+            /* Custom parsers stop at the first damaged record, check if there was one */
+            if reader.is_framed() {
+                if let (decoded, Some(e)) = reader.skip_valid() {
+                    let health = DataHealth {
+                        decoded,
+                        total: reader.estimate_total(decoded, len),
+                        problem: "tail corrupted".to_string(),
+                    };
+                    return self.salvage(&name, health, e, strict);
+                }
+            }
             return Ok(());
         }
         let mut raw_data = Vec::new();
+        let mut damaged = None;
         loop {
-            match bincode::deserialize_from::<_, Data>(self.file_handle.as_ref().unwrap()) {
-                Ok(v) => raw_data.push(v),
-                Err(e) => match *e {
-                    // EOF
-                    bincode::ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                        break
-                    }
-                    e => panic!("Error when Deserializing {} data {}", self.api_name, e),
-                },
+            let err = match reader.read_record::<Data>() {
+                Ok(Some(v)) => {
+                    raw_data.push(v);
+                    continue;
+                }
+                /* Old data files have no frames, a record cut short just ends them early */
+                Ok(None) if reader.offset() < len => PDError::RecordTorn(reader.offset()).into(),
+                Ok(None) => break,
+                Err(e) => e,
+            };
+            let health = DataHealth {
+                decoded: raw_data.len(),
+                total: reader.estimate_total(raw_data.len(), len),
+                problem: "tail corrupted".to_string(),
             };
+            damaged = Some((health, err));
+            break;
         }
+        let total = damaged.as_ref().map_or(raw_data.len(), |(h, _)| h.total);
         let mut data = Vec::new();
         for value in raw_data {
-            let processed_data = self.data.process_raw_data(value)?;
-            data.push(processed_data);
+            match self.data.process_raw_data(value) {
+                Ok(processed_data) => data.push(processed_data),
+                Err(e) => {
+                    let health = DataHealth {
+                        decoded: data.len(),
+                        total,
+                        problem: format!("sample {} could not be processed", data.len() + 1),
+                    };
+                    damaged = Some((health, e));
+                    break;
+                }
+            }
         }
         self.run_values.insert(name.clone(), data);
-        Ok(())
+        match damaged {
+            Some((health, e)) => self.salvage(&name, health, e, strict),
+            None => Ok(()),
+        }
     }
 
     pub fn get_data(
@@ -258,11 +359,13 @@ pub trait GetData {
 
 #[cfg(test)]
 mod tests {
-    use super::DataVisualizer;
+    use super::{DataHealth, DataVisualizer};
     use crate::data::cpu_utilization::{CpuData, CpuUtilization};
     use crate::data::{ProcessedData, TimeEnum};
     use crate::utils::DataMetrics;
+    use std::fs;
     use std::path::PathBuf;
+    use tempfile::TempDir;
 
     #[test]
     fn test_unpack_data() {
@@ -280,7 +383,7 @@ mod tests {
             &PathBuf::new(),
         )
         .unwrap();
-        dv.process_raw_data("test".to_string()).unwrap();
+        dv.process_raw_data("test".to_string(), true).unwrap();
         let ret = dv
             .get_data(
                 "test".to_string(),
@@ -295,8 +398,66 @@ mod tests {
             _ => unreachable!(),
         }
         match values[1].time {
-            TimeEnum::TimeDiff(value) => assert!(value == 1),
+            TimeEnum::TimeDiff(value) => assert!(value == 999),
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn test_salvage() {
+        let dir = TempDir::with_prefix("aperf_salvage").unwrap();
+        let src =
+            "tests/test-data/aperf_2023-07-26_18_37_43/cpu_utilization_2023-07-26_18_37_43.bin";
+        let dst = dir.path().join("cpu_utilization_2023-07-26_18_37_43.bin");
+        fs::copy(src, &dst).unwrap();
+        let len = fs::metadata(&dst).unwrap().len();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&dst)
+            .unwrap()
+            .set_len(len - 10)
+            .unwrap();
+
+        let new_visualizer = || {
+            let mut dv = DataVisualizer::new(
+                ProcessedData::CpuUtilization(CpuUtilization::new()),
+                "cpu_utilization".to_string(),
+                String::new(),
+                String::new(),
+                "cpu_utilization".to_string(),
+            );
+            dv.init_visualizer(
+                dir.path().to_str().unwrap().to_string(),
+                "test".to_string(),
+                &PathBuf::new(),
+                &PathBuf::new(),
+            )
+            .unwrap();
+            dv
+        };
+        let mut dv = new_visualizer();
+        assert!(dv.process_raw_data("test".to_string(), true).is_err());
+
+        let mut dv = new_visualizer();
+        dv.process_raw_data("test".to_string(), false).unwrap();
+        let health = dv.get_health("test").unwrap().clone();
+        let decoded = dv.run_values.get("test").unwrap().len();
+        assert!(decoded > 0);
+        assert_eq!(
+            health,
+            DataHealth {
+                decoded,
+                total: decoded + 1,
+                problem: "tail corrupted".to_string(),
+            }
+        );
+        assert_eq!(
+            health.to_string(),
+            format!(
+                "decoded {}/{} samples; tail corrupted",
+                decoded,
+                decoded + 1
+            )
+        );
+    }
 }
@@ -1,11 +1,15 @@
 use anyhow::Result;
+use aperf::daemon::{daemon, Daemon};
 use aperf::record::{record, Record};
+use aperf::recover::{recover, Recover, RECOVERED_MARKER};
 use aperf::report::{report, Report};
-use aperf::APERF_RUNLOG;
+use aperf::{InitParams, APERF_RUNLOG};
 use flate2::read::GzDecoder;
 use serial_test::serial;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::{fs, panic};
+use std::time::Duration;
+use std::{fs, panic, thread};
 use tar::Archive;
 use tempfile::TempDir;
 
@@ -53,16 +57,409 @@ fn test_report_dot_in_run_name() {
     run_test(|tempdir, aperf_tmp| report_with_name("record.data".to_string(), tempdir, aperf_tmp))
 }
 
+#[test]
+#[serial]
+fn test_record_workload() {
+    run_test(|tempdir, aperf_tmp| {
+        let workload = ["sh", "-c", "echo hello; sleep 1; exit 3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let run_name = record_with_workload(
+            "test_record_workload".to_string(),
+            &tempdir,
+            &aperf_tmp,
+            None,
+            workload,
+        )
+        .unwrap();
+
+        let workload_file = Path::new(&run_name).join("aperf_workload.json");
+        let workload: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(workload_file).unwrap()).unwrap();
+        assert_eq!(workload["exit_code"], 3);
+        assert_eq!(workload["stdout_tail"][0], "hello");
+        assert!(workload["end_offset"].as_u64().unwrap() >= 1000);
+
+        fs::remove_dir_all(&run_name).unwrap();
+        fs::remove_file(run_name + ".tar.gz").unwrap();
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn test_record_flight_recorder() {
+    run_test(|tempdir, aperf_tmp| {
+        let run_name = tempdir
+            .join("test_flight_recorder")
+            .into_os_string()
+            .into_string()
+            .unwrap();
+        let rec = Record {
+            run_name: Some(run_name.clone()),
+            interval: Some(Duration::from_millis(500)),
+            period: Some(3),
+            profile: false,
+            perf_frequency: None,
+            profile_java: None,
+            threads: Vec::new(),
+            slabinfo: false,
+            pmu_config: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            flight_recorder: Some(Duration::from_secs(1)),
+            max_overhead: None,
+            cgroup_root: None,
+            cgroup_depth: None,
+            config: None,
+            tag: Vec::new(),
+            workload: Vec::new(),
+        };
+        let runlog = tempdir.join(APERF_RUNLOG);
+        fs::File::create(&runlog).unwrap();
+        record(&rec, &aperf_tmp, &runlog).unwrap();
+
+        // Only the dump made on exit is written, next to the requested run name.
+        assert!(!Path::new(&run_name).exists());
+        let dumps: Vec<PathBuf> = fs::read_dir(&tempdir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.is_dir())
+            .collect();
+        assert_eq!(dumps.len(), 1);
+        let dump = &dumps[0];
+        assert!(dump
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("test_flight_recorder_"));
+        assert!(Path::new(&format!("{}.tar.gz", dump.display())).exists());
+        assert!(dump.join("meta_data.bin").exists());
+
+        let report_loc = tempdir.join("test_report");
+        let rep = Report {
+            run: vec![dump.to_str().unwrap().to_string()],
+            name: Some(report_loc.to_str().unwrap().to_string()),
+            strict: false,
+        };
+        report(&rep, &aperf_tmp).unwrap();
+        assert!(report_loc.join("index.html").exists());
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn test_record_config() {
+    run_test(|tempdir, aperf_tmp| {
+        let config = tempdir.join("db.yaml");
+        fs::write(
+            &config,
+            format!(
+                "run_name: db\noutput_dir: {}\ninterval: 500ms\nperiod: 30\n\
+                 tags:\n  role: database\n",
+                tempdir.join("runs").display()
+            ),
+        )
+        .unwrap();
+        let rec = Record {
+            run_name: None,
+            interval: None,
+            period: Some(2),
+            profile: false,
+            perf_frequency: None,
+            profile_java: None,
+            threads: Vec::new(),
+            slabinfo: false,
+            pmu_config: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            flight_recorder: None,
+            max_overhead: None,
+            cgroup_root: None,
+            cgroup_depth: None,
+            config: Some(config.to_str().unwrap().to_string()),
+            tag: vec![("host".to_string(), "db1".to_string())],
+            workload: Vec::new(),
+        };
+        let runlog = tempdir.join(APERF_RUNLOG);
+        fs::File::create(&runlog).unwrap();
+        record(&rec, &aperf_tmp, &runlog).unwrap();
+
+        // The file places the run, the flags override it, and the result is saved with the run.
+        let run_dir = tempdir.join("runs/db");
+        assert!(run_dir.exists());
+        let saved: serde_yaml::Value =
+            serde_yaml::from_str(&fs::read_to_string(run_dir.join("record_config.yaml")).unwrap())
+                .unwrap();
+        assert_eq!(saved["interval"], "500ms");
+        assert_eq!(saved["period"], 2);
+        assert_eq!(saved["tags"]["role"], "database");
+        assert_eq!(saved["tags"]["host"], "db1");
+
+        fs::write(&config, "interval: 1\nperiod: often\n").unwrap();
+        let err = record(&rec, &aperf_tmp, &runlog).unwrap_err();
+        assert!(err.to_string().contains("db.yaml"));
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn test_daemon() {
+    run_test(|tempdir, aperf_tmp| {
+        let run_name = tempdir
+            .join("test_daemon")
+            .into_os_string()
+            .into_string()
+            .unwrap();
+        let d = Daemon {
+            run_name: run_name.clone(),
+            interval: Some(Duration::from_millis(250)),
+            rotate: Some(Duration::from_secs(1)),
+            keep: Some(2),
+            max_size: None,
+            max_overhead: None,
+            cgroup_root: None,
+            cgroup_depth: None,
+            pmu_config: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            config: None,
+        };
+        let runlog = tempdir.join(APERF_RUNLOG);
+        fs::File::create(&runlog).unwrap();
+
+        // The signal is directed at this thread, which reads it from its signalfd.
+        let collector = unsafe { libc::pthread_self() };
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(3500));
+            unsafe { libc::pthread_kill(collector, libc::SIGTERM) };
+        });
+        daemon(&d, &aperf_tmp, &runlog).unwrap();
+        stopper.join().unwrap();
+
+        // Windows are archived and removed, and only the newest archives are kept.
+        let archives: Vec<PathBuf> = fs::read_dir(&tempdir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.to_str().unwrap().ends_with(".tar.gz"))
+            .collect();
+        assert_eq!(archives.len(), 2);
+        assert!(fs::read_dir(&tempdir)
+            .unwrap()
+            .all(|e| !e.unwrap().path().is_dir()));
+
+        let report_loc = tempdir.join("test_report");
+        let rep = Report {
+            run: vec![archives[0].to_str().unwrap().to_string()],
+            name: Some(report_loc.to_str().unwrap().to_string()),
+            strict: false,
+        };
+        report(&rep, &aperf_tmp).unwrap();
+        assert!(report_loc.join("index.html").exists());
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn test_daemon_config_reload() {
+    run_test(|tempdir, aperf_tmp| {
+        let config = tempdir.join("daemon.yaml");
+        fs::write(
+            &config,
+            "interval: 250ms\ncollectors:\n  exclude: [vmstat]\n",
+        )
+        .unwrap();
+        let d = Daemon {
+            run_name: tempdir.join("reload").to_str().unwrap().to_string(),
+            interval: None,
+            rotate: None,
+            keep: None,
+            max_size: None,
+            max_overhead: None,
+            cgroup_root: None,
+            cgroup_depth: None,
+            pmu_config: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            config: Some(config.to_str().unwrap().to_string()),
+        };
+        let runlog = tempdir.join(APERF_RUNLOG);
+        fs::File::create(&runlog).unwrap();
+
+        // An invalid config is logged and the daemon goes on with the settings it has.
+        let collector = unsafe { libc::pthread_self() };
+        let reloader = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1200));
+            fs::write(&config, "interval: 0\n").unwrap();
+            unsafe { libc::pthread_kill(collector, libc::SIGHUP) };
+            thread::sleep(Duration::from_millis(1200));
+            fs::write(
+                &config,
+                "interval: 500ms\ntags:\n  role: database\ndaemon:\n  keep: 2\n",
+            )
+            .unwrap();
+            unsafe { libc::pthread_kill(collector, libc::SIGHUP) };
+            thread::sleep(Duration::from_millis(1500));
+            unsafe { libc::pthread_kill(collector, libc::SIGTERM) };
+        });
+        daemon(&d, &aperf_tmp, &runlog).unwrap();
+        reloader.join().unwrap();
+
+        let mut archives: Vec<PathBuf> = fs::read_dir(&tempdir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.to_str().unwrap().ends_with(".tar.gz"))
+            .collect();
+        archives.sort();
+        assert_eq!(archives.len(), 2);
+
+        let kept = archived_params(&archives[0], &tempdir.join("kept"));
+        assert_eq!(kept.interval, Duration::from_millis(250));
+        assert!(!kept.collectors.contains(&"vmstat".to_string()));
+        assert!(kept.tags.is_empty());
+
+        let reloaded = archived_params(&archives[1], &tempdir.join("reloaded"));
+        assert_eq!(reloaded.interval, Duration::from_millis(500));
+        assert!(reloaded.collectors.contains(&"vmstat".to_string()));
+        assert_eq!(reloaded.tags["role"], "database");
+        assert_eq!(reloaded.keep, Some(2));
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn test_recover() {
+    run_test(|tempdir, aperf_tmp| {
+        let run_name = record_with_name("test_recover".to_string(), &tempdir, &aperf_tmp).unwrap();
+        fs::remove_file(run_name.clone() + ".tar.gz").unwrap();
+
+        // Leave a record cut short at the end of a data file, as a crash would.
+        let vmstat = fs::read_dir(&run_name)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .find(|p| {
+                p.file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .starts_with("vmstat")
+            })
+            .unwrap();
+        let len = fs::metadata(&vmstat).unwrap().len();
+        let mut file = fs::OpenOptions::new().append(true).open(&vmstat).unwrap();
+        file.write_all(&[64, 0, 0, 0, 1, 2, 3]).unwrap();
+
+        recover(&Recover {
+            dir: run_name.clone(),
+        })
+        .unwrap();
+        assert_eq!(fs::metadata(&vmstat).unwrap().len(), len);
+        let runlog = fs::read_to_string(Path::new(&run_name).join(APERF_RUNLOG)).unwrap();
+        assert!(runlog.contains(RECOVERED_MARKER));
+        assert!(runlog.contains("removed 7 bytes"));
+        assert!(Path::new(&(run_name.clone() + ".tar.gz")).exists());
+
+        let report_loc = tempdir.join("test_report");
+        let rep = Report {
+            run: vec![run_name.clone() + ".tar.gz"],
+            name: Some(report_loc.to_str().unwrap().to_string()),
+            strict: false,
+        };
+        report(&rep, &aperf_tmp).unwrap();
+        let markers = fs::read_to_string(report_loc.join("data/js/aperf_markers.js")).unwrap();
+        assert!(markers.contains(RECOVERED_MARKER));
+
+        assert!(recover(&Recover {
+            dir: tempdir.to_str().unwrap().to_string()
+        })
+        .is_err());
+        Ok(())
+    })
+}
+
+#[test]
+#[serial]
+fn test_report_salvage() {
+    run_test(|tempdir, aperf_tmp| {
+        let run_name = record_with_name("test_salvage".to_string(), &tempdir, &aperf_tmp).unwrap();
+        let vmstat = fs::read_dir(&run_name)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .find(|p| {
+                p.file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .starts_with("vmstat")
+            })
+            .unwrap();
+        let mut file = fs::OpenOptions::new().append(true).open(&vmstat).unwrap();
+        file.write_all(&[64, 0, 0, 0, 1, 2, 3]).unwrap();
+
+        let report_loc = tempdir.join("test_report");
+        let mut rep = Report {
+            run: vec![run_name.clone()],
+            name: Some(report_loc.to_str().unwrap().to_string()),
+            strict: true,
+        };
+        assert!(report(&rep, &aperf_tmp).is_err());
+
+        rep.strict = false;
+        fs::remove_dir_all(&report_loc).unwrap();
+        report(&rep, &aperf_tmp).unwrap();
+        let vmstat_js = fs::read_to_string(report_loc.join("data/js/vmstat.js")).unwrap();
+        assert!(vmstat_js.contains("tail corrupted"));
+        let meminfo_js = fs::read_to_string(report_loc.join("data/js/meminfo.js")).unwrap();
+        assert!(meminfo_js.contains("\"health\":null"));
+        Ok(())
+    })
+}
+
+fn archived_params(archive: &Path, dir: &Path) -> InitParams {
+    let tar_gz = fs::File::open(archive).unwrap();
+    Archive::new(GzDecoder::new(tar_gz)).unpack(dir).unwrap();
+    let run = fs::read_dir(dir).unwrap().next().unwrap().unwrap().path();
+    let meta_data = fs::File::open(run.join("meta_data.bin")).unwrap();
+    bincode::deserialize_from(meta_data).unwrap()
+}
+
 fn record_with_name(run: String, tempdir: &Path, aperf_tmp: &Path) -> Result<String> {
+    record_with_workload(run, tempdir, aperf_tmp, Some(2), Vec::new())
+}
+
+fn record_with_workload(
+    run: String,
+    tempdir: &Path,
+    aperf_tmp: &Path,
+    period: Option<u64>,
+    workload: Vec<String>,
+) -> Result<String> {
     let run_name = tempdir.join(run).into_os_string().into_string().unwrap();
     let rec = Record {
         run_name: Some(run_name.clone()),
-        interval: 1,
-        period: 2,
+        interval: Some(Duration::from_secs(1)),
+        period,
         profile: false,
-        perf_frequency: 99,
+        perf_frequency: None,
         profile_java: None,
+        threads: Vec::new(),
+        slabinfo: false,
         pmu_config: None,
+        include: Vec::new(),
+        exclude: Vec::new(),
+        flight_recorder: None,
+        max_overhead: None,
+        cgroup_root: None,
+        cgroup_depth: None,
+        config: None,
+        tag: Vec::new(),
+        workload,
     };
     let runlog = tempdir.join(APERF_RUNLOG);
     fs::File::create(&runlog).unwrap();
@@ -85,6 +482,7 @@ fn report_with_name(run: String, tempdir: PathBuf, aperf_tmp: PathBuf) -> Result
     let rep = Report {
         run: [run_name.clone()].to_vec(),
         name: Some(report_loc.clone()),
+        strict: false,
     };
     report(&rep, &aperf_tmp).unwrap();
 